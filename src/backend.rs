@@ -0,0 +1,8 @@
+pub mod atlas;
+pub mod drawing_context;
+pub mod framebuffer;
+pub mod miniquad_backend;
+pub mod profiling_context;
+pub mod software;
+pub mod sprite_sheet;
+pub mod texture;
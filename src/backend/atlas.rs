@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use crate::backend::drawing_context::DrawingContext;
+use crate::backend::texture::{Texture, TextureError};
+use crate::shapes::sprite::Region;
+
+// Pages are cropped to the power of two that holds what was packed,
+// these are the upper bounds.
+pub const DEFAULT_PAGE_SIZE: u32 = 2048;
+// Edge pixels are repeated into the padding so linear filtering
+// never picks up a neighbour.
+pub const DEFAULT_PADDING: u32 = 2;
+
+#[derive(Debug)]
+pub enum AtlasError {
+    Texture(TextureError),
+    DuplicateName(String),
+    // Bigger than a whole page once padded.
+    ImageTooLarge { name: String, width: u32, height: u32 },
+    SpriteSheet(String),
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtlasError::Texture(error) =>
+                write!(f, "{}", error),
+            AtlasError::DuplicateName(name) =>
+                write!(f, "an image called '{}' was already added", name),
+            AtlasError::ImageTooLarge { name, width, height } =>
+                write!(f, "'{}' ({}x{}) does not fit on an atlas page", name, width, height),
+            AtlasError::SpriteSheet(error) =>
+                write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<TextureError> for AtlasError {
+    fn from(error: TextureError) -> AtlasError {
+        AtlasError::Texture(error)
+    }
+}
+
+// Where a packed image ended up. x, y, width and height are page
+// pixels, region is the same rectangle as uvs for Sprite.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasRegion {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub region: Region
+}
+
+pub struct Atlas {
+    pages: Vec<Texture>,
+    regions: HashMap<String, AtlasRegion>
+}
+
+impl Atlas {
+
+    pub fn get_pages(&self) -> &[Texture] {
+        &self.pages
+    }
+
+    pub fn get_page(&self, page: usize) -> Option<&Texture> {
+        self.pages.get(page)
+    }
+
+    pub fn get_region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    pub fn get_region_names(&self) -> impl Iterator<Item = &String> {
+        self.regions.keys()
+    }
+}
+
+struct PendingImage {
+    name: String,
+    width: u32,
+    height: u32,
+    // Straight alpha RGBA8.
+    pixels: Vec<u8>
+}
+
+// Collects images, then packs them onto as few pages as possible
+// and uploads the pages in build.
+pub struct AtlasBuilder {
+    page_width: u32,
+    page_height: u32,
+    padding: u32,
+    images: Vec<PendingImage>
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> AtlasBuilder {
+        AtlasBuilder::new()
+    }
+}
+
+impl AtlasBuilder {
+
+    pub fn new() -> AtlasBuilder {
+        AtlasBuilder {
+            page_width: DEFAULT_PAGE_SIZE,
+            page_height: DEFAULT_PAGE_SIZE,
+            padding: DEFAULT_PADDING,
+            images: vec![]
+        }
+    }
+
+    pub fn set_page_size(&mut self, page_width: u32, page_height: u32) {
+        self.page_width = page_width.max(1);
+        self.page_height = page_height.max(1);
+    }
+
+    pub fn set_padding(&mut self, padding: u32) {
+        self.padding = padding;
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.images.iter().any(|image| image.name == name)
+    }
+
+    // Straight alpha RGBA8, row 0 is the top of the image.
+    pub fn add_rgba8(&mut self, name: &str, width: u32, height: u32, pixels: Vec<u8>) -> Result<(), AtlasError> {
+        if self.contains(name) {
+            return Err(AtlasError::DuplicateName(name.to_string()));
+        }
+        assert_eq!(pixels.len(), (width * height * 4) as usize, "Pixel data does not match dimensions.");
+
+        self.images.push(PendingImage {
+            name: name.to_string(),
+            width,
+            height,
+            pixels
+        });
+        Ok(())
+    }
+
+    // Encoded PNG, JPEG, BMP, ... contents.
+    pub fn add_bytes(&mut self, name: &str, bytes: &[u8]) -> Result<(), AtlasError> {
+        let image = image::load_from_memory(bytes)
+            .map_err(|error| TextureError::Decode(error.to_string()))?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
+        self.add_rgba8(name, width, height, image.into_raw())
+    }
+
+    pub fn add_file(&mut self, name: &str, path: impl AsRef<Path>) -> Result<(), AtlasError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|error| TextureError::Io(path.to_path_buf(), error.to_string()))?;
+        self.add_bytes(name, &bytes)
+    }
+
+    pub fn build(self, context: &mut dyn DrawingContext) -> Result<Atlas, AtlasError> {
+        let padding = self.padding;
+
+        // Tallest first packs skylines tightest.
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by(|a, b| {
+            let (a, b) = (&self.images[*a], &self.images[*b]);
+            b.height.cmp(&a.height).then(b.width.cmp(&a.width))
+        });
+
+        let mut skylines: Vec<Skyline> = vec![];
+        let mut placements = vec![(0usize, 0u32, 0u32); self.images.len()];
+        for index in order {
+            let image = &self.images[index];
+            let (width, height) = (image.width + padding * 2, image.height + padding * 2);
+            if width > self.page_width || height > self.page_height {
+                return Err(AtlasError::ImageTooLarge {
+                    name: image.name.clone(),
+                    width: image.width,
+                    height: image.height
+                });
+            }
+
+            let placed = skylines
+                .iter_mut()
+                .enumerate()
+                .find_map(|(page, skyline)| skyline.insert(width, height).map(|(x, y)| (page, x, y)));
+            placements[index] = match placed {
+                Some(placement) => placement,
+                None => {
+                    let mut skyline = Skyline::new(self.page_width, self.page_height);
+                    let (x, y) = skyline.insert(width, height).unwrap();
+                    skylines.push(skyline);
+                    (skylines.len() - 1, x, y)
+                }
+            };
+        }
+
+        let page_sizes: Vec<(u32, u32)> = skylines
+            .iter()
+            .map(|skyline| {
+                let (used_width, used_height) = skyline.used_size();
+                (
+                    used_width.next_power_of_two().min(self.page_width),
+                    used_height.next_power_of_two().min(self.page_height)
+                )
+            })
+            .collect();
+        let mut page_pixels: Vec<Vec<u8>> = page_sizes
+            .iter()
+            .map(|(width, height)| vec![0u8; (width * height * 4) as usize])
+            .collect();
+
+        let mut regions = HashMap::new();
+        for (image, (page, x, y)) in self.images.iter().zip(placements) {
+            let (page_width, page_height) = page_sizes[page];
+            blit_extruded(&mut page_pixels[page], page_width, image, x, y, padding);
+
+            let (left, top) = (x + padding, y + padding);
+            regions.insert(image.name.clone(), AtlasRegion {
+                page,
+                x: left,
+                y: top,
+                width: image.width,
+                height: image.height,
+                region: Region {
+                    u_min: left as f32 / page_width as f32,
+                    v_min: top as f32 / page_height as f32,
+                    u_max: (left + image.width) as f32 / page_width as f32,
+                    v_max: (top + image.height) as f32 / page_height as f32
+                }
+            });
+        }
+
+        let mut pages = vec![];
+        for ((width, height), pixels) in page_sizes.into_iter().zip(page_pixels) {
+            pages.push(Texture::from_rgba8(context, width, height, pixels)?);
+        }
+
+        Ok(Atlas {
+            pages,
+            regions
+        })
+    }
+}
+
+// Copies the image at (x + padding, y + padding) and repeats its
+// outermost pixels across the padding.
+fn blit_extruded(page: &mut [u8], page_width: u32, image: &PendingImage, x: u32, y: u32, padding: u32) {
+    if image.width == 0 || image.height == 0 {
+        return;
+    }
+
+    for row in 0..image.height + padding * 2 {
+        let source_row = row.saturating_sub(padding).min(image.height - 1);
+        for column in 0..image.width + padding * 2 {
+            let source_column = column.saturating_sub(padding).min(image.width - 1);
+            let source = ((source_row * image.width + source_column) * 4) as usize;
+            let destination = (((y + row) * page_width + x + column) * 4) as usize;
+            page[destination..destination + 4].copy_from_slice(&image.pixels[source..source + 4]);
+        }
+    }
+}
+
+// Bottom-left skyline packer. The skyline is the top edge of
+// everything placed so far, as (x, y, width) segments left to right.
+// y grows downwards, the top of the page is 0.
+struct Skyline {
+    width: u32,
+    height: u32,
+    segments: Vec<(u32, u32, u32)>
+}
+
+impl Skyline {
+
+    fn new(width: u32, height: u32) -> Skyline {
+        Skyline {
+            width,
+            height,
+            segments: vec![(0, 0, width)]
+        }
+    }
+
+    // Lowest resting position, leftmost on ties. None when full.
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let mut best: Option<(usize, u32, u32)> = None;
+        for index in 0..self.segments.len() {
+            let Some(y) = self.fits(index, width, height) else {
+                continue;
+            };
+            if best.is_none_or(|(_, _, best_y)| y < best_y) {
+                best = Some((index, self.segments[index].0, y));
+            }
+        }
+
+        let (index, x, y) = best?;
+        self.place(index, x, y + height, width);
+        Some((x, y))
+    }
+
+    // Height the rectangle rests at if its left edge is on segment
+    // index, resting on the highest segment underneath it.
+    fn fits(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.segments[index].0;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = width as i64;
+        for &(_, segment_y, segment_width) in &self.segments[index..] {
+            if remaining <= 0 {
+                break;
+            }
+            y = y.max(segment_y);
+            remaining -= segment_width as i64;
+        }
+        (y + height <= self.height).then_some(y)
+    }
+
+    fn place(&mut self, index: usize, x: u32, top: u32, width: u32) {
+        self.segments.insert(index, (x, top, width));
+
+        // Trim or drop the segments the new one now covers.
+        let right = x + width;
+        let next = index + 1;
+        while next < self.segments.len() {
+            let (segment_x, segment_y, segment_width) = self.segments[next];
+            if segment_x >= right {
+                break;
+            }
+            let segment_right = segment_x + segment_width;
+            if segment_right <= right {
+                self.segments.remove(next);
+            } else {
+                self.segments[next] = (right, segment_y, segment_right - right);
+                break;
+            }
+        }
+
+        // Merge neighbours at the same height.
+        let mut merged: Vec<(u32, u32, u32)> = Vec::with_capacity(self.segments.len());
+        for segment in self.segments.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.1 == segment.1 => last.2 += segment.2,
+                _ => merged.push(segment)
+            }
+        }
+        self.segments = merged;
+    }
+
+    // Extent of everything placed, for cropping the page.
+    fn used_size(&self) -> (u32, u32) {
+        let used: Vec<&(u32, u32, u32)> = self.segments.iter().filter(|(_, y, _)| *y > 0).collect();
+        let width = used.iter().map(|(x, _, width)| x + width).max().unwrap_or(1);
+        let height = used.iter().map(|(_, y, _)| *y).max().unwrap_or(1);
+        (width, height)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::software::SoftwareBackend;
+
+    fn solid(width: u32, height: u32, value: u8) -> Vec<u8> {
+        vec![value; (width * height * 4) as usize]
+    }
+
+    #[test]
+    fn regions_do_not_overlap() {
+        let mut context = SoftwareBackend::new(1, 1);
+        let mut builder = AtlasBuilder::new();
+        builder.set_page_size(64, 64);
+        let sizes = [(20, 10), (7, 30), (16, 16), (33, 5), (9, 9), (12, 21), (3, 3), (40, 8)];
+        for (index, (width, height)) in sizes.iter().enumerate() {
+            builder.add_rgba8(&index.to_string(), *width, *height, solid(*width, *height, 255)).unwrap();
+        }
+        let atlas = builder.build(&mut context).unwrap();
+
+        let regions: Vec<&AtlasRegion> = (0..sizes.len()).map(|index| atlas.get_region(&index.to_string()).unwrap()).collect();
+        for (index, region) in regions.iter().enumerate() {
+            let page = atlas.get_page(region.page).unwrap();
+            assert_eq!((region.width, region.height), sizes[index]);
+            assert!(region.x >= DEFAULT_PADDING && region.x + region.width + DEFAULT_PADDING <= page.get_width());
+            assert!(region.y >= DEFAULT_PADDING && region.y + region.height + DEFAULT_PADDING <= page.get_height());
+
+            // Padding included, so extruded edges don't overlap either.
+            for other in &regions[index + 1..] {
+                let apart = region.page != other.page
+                    || region.x + region.width + DEFAULT_PADDING * 2 <= other.x
+                    || other.x + other.width + DEFAULT_PADDING * 2 <= region.x
+                    || region.y + region.height + DEFAULT_PADDING * 2 <= other.y
+                    || other.y + other.height + DEFAULT_PADDING * 2 <= region.y;
+                assert!(apart, "{:?} overlaps {:?}", region, other);
+            }
+        }
+    }
+
+    #[test]
+    fn padding_repeats_the_edge_pixels() {
+        // 2x2 image, one distinct value per pixel.
+        let image = PendingImage {
+            name: "image".to_string(),
+            width: 2,
+            height: 2,
+            pixels: [1u8, 2, 3, 4].iter().flat_map(|value| [*value; 4]).collect()
+        };
+        let mut page = vec![0u8; 8 * 8 * 4];
+        blit_extruded(&mut page, 8, &image, 1, 1, 2);
+
+        let pixel = |x: u32, y: u32| page[((y * 8 + x) * 4) as usize];
+        let expected = [
+            [1, 1, 1, 2, 2, 2],
+            [1, 1, 1, 2, 2, 2],
+            [1, 1, 1, 2, 2, 2],
+            [3, 3, 3, 4, 4, 4],
+            [3, 3, 3, 4, 4, 4],
+            [3, 3, 3, 4, 4, 4]
+        ];
+        for (row, values) in expected.iter().enumerate() {
+            for (column, value) in values.iter().enumerate() {
+                assert_eq!(pixel(column as u32 + 1, row as u32 + 1), *value, "({}, {})", column, row);
+            }
+        }
+        // Nothing outside the padded rectangle.
+        assert_eq!(pixel(0, 0), 0);
+        assert_eq!(pixel(7, 7), 0);
+    }
+
+    #[test]
+    fn pages_are_cropped_to_a_power_of_two() {
+        let mut context = SoftwareBackend::new(1, 1);
+        let mut builder = AtlasBuilder::new();
+        builder.add_rgba8("square", 10, 10, solid(10, 10, 255)).unwrap();
+        let atlas = builder.build(&mut context).unwrap();
+        let page = atlas.get_page(0).unwrap();
+        assert_eq!((page.get_width(), page.get_height()), (16, 16));
+        let region = atlas.get_region("square").unwrap().region;
+        assert_eq!((region.u_min, region.v_min, region.u_max, region.v_max), (2. / 16., 2. / 16., 12. / 16., 12. / 16.));
+
+        // Never past the page size, even when that isn't a power of two.
+        let mut builder = AtlasBuilder::new();
+        builder.set_page_size(40, 40);
+        builder.add_rgba8("wide", 30, 5, solid(30, 5, 255)).unwrap();
+        let atlas = builder.build(&mut context).unwrap();
+        let page = atlas.get_page(0).unwrap();
+        assert_eq!((page.get_width(), page.get_height()), (40, 16));
+    }
+
+    #[test]
+    fn images_too_large_for_a_page_are_rejected() {
+        let mut context = SoftwareBackend::new(1, 1);
+        let mut builder = AtlasBuilder::new();
+        builder.set_page_size(16, 16);
+        builder.add_rgba8("fits", 12, 12, solid(12, 12, 255)).unwrap();
+        assert!(builder.build(&mut context).is_ok());
+
+        let mut builder = AtlasBuilder::new();
+        builder.set_page_size(16, 16);
+        builder.add_rgba8("fits", 12, 12, solid(12, 12, 255)).unwrap();
+        builder.add_rgba8("too wide", 13, 4, solid(13, 4, 255)).unwrap();
+        match builder.build(&mut context) {
+            Err(AtlasError::ImageTooLarge { name, width, height }) =>
+                assert_eq!((name.as_str(), width, height), ("too wide", 13, 4)),
+            _ => panic!("expected ImageTooLarge")
+        }
+
+        // Without padding the same image fits.
+        let mut builder = AtlasBuilder::new();
+        builder.set_page_size(16, 16);
+        builder.set_padding(0);
+        builder.add_rgba8("too wide", 13, 4, solid(13, 4, 255)).unwrap();
+        assert!(builder.build(&mut context).is_ok());
+    }
+}
@@ -0,0 +1,86 @@
+use miniquad::{BufferUsage, FilterMode, PassAction, ShaderError, ShaderMeta, TextureWrap};
+use crate::shapes::shared_c_resources::Vertex;
+
+// Handles are indexes into the resource tables of whichever backend
+// created them, they are not interchangeable between backends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BufferId(pub(crate) usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureId(pub(crate) usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShaderId(pub(crate) usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pipeline(pub(crate) usize);
+
+// Fragment colours reach the blender premultiplied by alpha, each
+// mode is then a single pair of blend factors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode {
+    // Straight alpha colours, the usual over operator.
+    Alpha,
+    // Colours already premultiplied by their alpha.
+    Premultiplied,
+    Additive,
+    Multiply,
+    Screen
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings {
+    pub vertex_buffers: Vec<BufferId>,
+    pub index_buffer: BufferId,
+    pub images: Vec<TextureId>,
+}
+
+// The subset of miniquad's RenderingBackend the shapes rely on.
+// Vertex layout is fixed to `Vertex` (in_pos + in_uv) so pipelines
+// only need the shader.
+pub trait DrawingContext {
+
+    fn new_vertex_buffer(&mut self, usage: BufferUsage, vertices: &[Vertex]) -> BufferId;
+    fn new_index_buffer(&mut self, usage: BufferUsage, indices: &[u16]) -> BufferId;
+    fn delete_buffer(&mut self, buffer: BufferId);
+    fn new_texture_from_rgba8(&mut self, width: u16, height: u16, bytes: &[u8]) -> TextureId;
+    // Textures start out Linear and Clamp, like miniquad's.
+    fn set_texture_filter(&mut self, texture: TextureId, filter: FilterMode);
+    fn set_texture_wrap(&mut self, texture: TextureId, wrap: TextureWrap);
+    fn new_shader(&mut self, vertex: &str, fragment: &str, meta: ShaderMeta) -> Result<ShaderId, ShaderError>;
+    fn new_pipeline(&mut self, shader: ShaderId, blend_mode: BlendMode) -> Pipeline;
+    // Rebuilds the pipeline behind the handle, shapes keep their handle.
+    fn set_pipeline_blend_mode(&mut self, pipeline: &Pipeline, blend_mode: BlendMode);
+    fn apply_pipeline(&mut self, pipeline: &Pipeline);
+    fn apply_bindings(&mut self, bindings: &Bindings);
+    fn apply_uniforms_from_bytes(&mut self, bytes: &[u8]);
+    fn begin_default_pass(&mut self, action: PassAction);
+    // Window pixels, origin top left. Reset to the whole window by
+    // begin_default_pass.
+    fn apply_viewport(&mut self, x: i32, y: i32, width: i32, height: i32);
+    fn draw(&mut self, base_element: i32, num_elements: i32, num_instances: i32);
+    fn end_render_pass(&mut self);
+    fn commit_frame(&mut self);
+}
+
+/// Implemented by uniform structs that can be uploaded as raw bytes.
+///
+/// # Safety
+///
+/// The type must be #[repr(C)], made of f32 fields only so it has no
+/// padding bytes, and laid out in the same order as the UniformDesc
+/// list of its shader meta.
+pub unsafe trait UniformBlock {}
+
+impl dyn DrawingContext + '_ {
+
+    pub fn apply_uniforms<T: UniformBlock>(&mut self, uniforms: &T) {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                uniforms as *const T as *const u8,
+                std::mem::size_of::<T>()
+            )
+        };
+        self.apply_uniforms_from_bytes(bytes);
+    }
+}
@@ -0,0 +1,62 @@
+// Tightly packed RGBA8 pixels, row 0 is the top of the image.
+#[derive(Clone)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>
+}
+
+impl Framebuffer {
+
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![0u8; (width * height * 4) as usize]
+        }
+    }
+
+    pub fn from_rgba8(width: u32, height: u32, pixels: Vec<u8>) -> Framebuffer {
+        assert_eq!(pixels.len(), (width * height * 4) as usize, "Pixel data does not match dimensions.");
+        Framebuffer {
+            width,
+            height,
+            pixels
+        }
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn get_pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = self.index(x, y);
+        [self.pixels[index], self.pixels[index + 1], self.pixels[index + 2], self.pixels[index + 3]]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, rgba: [u8; 4]) {
+        let index = self.index(x, y);
+        self.pixels[index..index + 4].copy_from_slice(&rgba);
+    }
+
+    pub fn clear(&mut self, rgba: [u8; 4]) {
+        self.pixels.chunks_exact_mut(4).for_each(|pixel| pixel.copy_from_slice(&rgba));
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        ((y * self.width + x) * 4) as usize
+    }
+}
+
+pub fn to_rgba8(red: f32, green: f32, blue: f32, alpha: f32) -> [u8; 4] {
+    let channel = |value: f32| (value.clamp(0., 1.) * 255. + 0.5) as u8;
+    [channel(red), channel(green), channel(blue), channel(alpha)]
+}
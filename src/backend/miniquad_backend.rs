@@ -0,0 +1,172 @@
+use miniquad::{
+    BlendFactor, BlendState, BlendValue, BufferLayout, BufferSource, BufferType, BufferUsage,
+    Equation, FilterMode, MipmapFilterMode, PassAction, PipelineParams, RenderingBackend,
+    ShaderError, ShaderMeta, ShaderSource, TextureWrap, VertexAttribute, VertexFormat, window
+};
+use crate::backend::drawing_context::{
+    Bindings, BlendMode, BufferId, DrawingContext, Pipeline, ShaderId, TextureId
+};
+use crate::shapes::shared_c_resources::Vertex;
+
+// Forwards every call to the window's OpenGL/Metal backend, mapping
+// our handles onto the ones miniquad hands back.
+pub struct MiniquadBackend {
+    context: Box<dyn RenderingBackend>,
+    buffers: Vec<miniquad::BufferId>,
+    textures: Vec<miniquad::TextureId>,
+    shaders: Vec<miniquad::ShaderId>,
+    // Shader kept alongside so the pipeline can be rebuilt.
+    pipelines: Vec<(miniquad::Pipeline, ShaderId)>
+}
+
+impl MiniquadBackend {
+
+    pub fn new(context: Box<dyn RenderingBackend>) -> MiniquadBackend {
+        MiniquadBackend {
+            context,
+            buffers: vec![],
+            textures: vec![],
+            shaders: vec![],
+            pipelines: vec![]
+        }
+    }
+
+    fn create_pipeline(&mut self, shader: ShaderId, blend_mode: BlendMode) -> miniquad::Pipeline {
+        self.context.new_pipeline(
+            &[BufferLayout::default()],
+            &[
+                VertexAttribute::new("in_pos", VertexFormat::Float2),
+                VertexAttribute::new("in_uv", VertexFormat::Float2),
+            ],
+            self.shaders[shader.0],
+            PipelineParams {
+                color_blend: Some(color_blend(blend_mode)),
+                alpha_blend: Some(BlendState::new(
+                    Equation::Add,
+                    BlendFactor::One,
+                    BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                )),
+                ..Default::default()
+            },
+        )
+    }
+}
+
+// Source colours are premultiplied, see BlendMode.
+fn color_blend(blend_mode: BlendMode) -> BlendState {
+    let (source, destination) = match blend_mode {
+        BlendMode::Alpha | BlendMode::Premultiplied =>
+            (BlendFactor::One, BlendFactor::OneMinusValue(BlendValue::SourceAlpha)),
+        BlendMode::Additive =>
+            (BlendFactor::One, BlendFactor::One),
+        BlendMode::Multiply =>
+            (BlendFactor::Value(BlendValue::DestinationColor), BlendFactor::OneMinusValue(BlendValue::SourceAlpha)),
+        BlendMode::Screen =>
+            (BlendFactor::One, BlendFactor::OneMinusValue(BlendValue::SourceColor)),
+    };
+    BlendState::new(Equation::Add, source, destination)
+}
+
+impl DrawingContext for MiniquadBackend {
+
+    fn new_vertex_buffer(&mut self, usage: BufferUsage, vertices: &[Vertex]) -> BufferId {
+        let buffer = self.context.new_buffer(
+            BufferType::VertexBuffer,
+            usage,
+            BufferSource::slice(vertices),
+        );
+        self.buffers.push(buffer);
+        BufferId(self.buffers.len() - 1)
+    }
+
+    fn new_index_buffer(&mut self, usage: BufferUsage, indices: &[u16]) -> BufferId {
+        let buffer = self.context.new_buffer(
+            BufferType::IndexBuffer,
+            usage,
+            BufferSource::slice(indices),
+        );
+        self.buffers.push(buffer);
+        BufferId(self.buffers.len() - 1)
+    }
+
+    fn delete_buffer(&mut self, buffer: BufferId) {
+        // Slot is left in place so the other handles stay valid.
+        self.context.delete_buffer(self.buffers[buffer.0]);
+    }
+
+    fn new_texture_from_rgba8(&mut self, width: u16, height: u16, bytes: &[u8]) -> TextureId {
+        let texture = self.context.new_texture_from_rgba8(width, height, bytes);
+        self.textures.push(texture);
+        TextureId(self.textures.len() - 1)
+    }
+
+    fn set_texture_filter(&mut self, texture: TextureId, filter: FilterMode) {
+        self.context.texture_set_filter(self.textures[texture.0], filter, MipmapFilterMode::None);
+    }
+
+    fn set_texture_wrap(&mut self, texture: TextureId, wrap: TextureWrap) {
+        self.context.texture_set_wrap(self.textures[texture.0], wrap, wrap);
+    }
+
+    fn new_shader(&mut self, vertex: &str, fragment: &str, meta: ShaderMeta) -> Result<ShaderId, ShaderError> {
+        let shader = self.context.new_shader(
+            ShaderSource::Glsl {
+                vertex,
+                fragment,
+            },
+            meta,
+        )?;
+        self.shaders.push(shader);
+        Ok(ShaderId(self.shaders.len() - 1))
+    }
+
+    fn new_pipeline(&mut self, shader: ShaderId, blend_mode: BlendMode) -> Pipeline {
+        let pipeline = self.create_pipeline(shader, blend_mode);
+        self.pipelines.push((pipeline, shader));
+        Pipeline(self.pipelines.len() - 1)
+    }
+
+    fn set_pipeline_blend_mode(&mut self, pipeline: &Pipeline, blend_mode: BlendMode) {
+        let (old_pipeline, shader) = self.pipelines[pipeline.0];
+        self.context.delete_pipeline(old_pipeline);
+        self.pipelines[pipeline.0] = (self.create_pipeline(shader, blend_mode), shader);
+    }
+
+    fn apply_pipeline(&mut self, pipeline: &Pipeline) {
+        self.context.apply_pipeline(&self.pipelines[pipeline.0].0);
+    }
+
+    fn apply_bindings(&mut self, bindings: &Bindings) {
+        self.context.apply_bindings(&miniquad::Bindings {
+            vertex_buffers: bindings.vertex_buffers.iter().map(|buffer| self.buffers[buffer.0]).collect(),
+            index_buffer: self.buffers[bindings.index_buffer.0],
+            images: bindings.images.iter().map(|image| self.textures[image.0]).collect(),
+        });
+    }
+
+    fn apply_uniforms_from_bytes(&mut self, bytes: &[u8]) {
+        self.context.apply_uniforms_from_bytes(bytes.as_ptr(), bytes.len());
+    }
+
+    fn begin_default_pass(&mut self, action: PassAction) {
+        self.context.begin_default_pass(action);
+    }
+
+    fn apply_viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        // GL counts y up from the bottom of the window.
+        let (_, screen_height) = window::screen_size();
+        self.context.apply_viewport(x, screen_height as i32 - y - height, width, height);
+    }
+
+    fn draw(&mut self, base_element: i32, num_elements: i32, num_instances: i32) {
+        self.context.draw(base_element, num_elements, num_instances);
+    }
+
+    fn end_render_pass(&mut self) {
+        self.context.end_render_pass();
+    }
+
+    fn commit_frame(&mut self) {
+        self.context.commit_frame();
+    }
+}
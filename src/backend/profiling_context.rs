@@ -0,0 +1,135 @@
+use miniquad::{BufferUsage, FilterMode, PassAction, ShaderError, ShaderMeta, TextureWrap};
+use crate::backend::drawing_context::{BlendMode, Bindings, BufferId, DrawingContext, Pipeline, ShaderId, TextureId};
+use crate::shapes::shared_c_resources::Vertex;
+
+// What one frame asked the GPU for.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DrawCounters {
+    pub draw_calls: u32,
+    // Indices drawn times instances, shared vertices count each
+    // time they are used.
+    pub vertices: u32,
+    // apply_pipeline / apply_bindings calls that changed anything,
+    // reapplying the current one is nearly free and not counted.
+    pub pipeline_switches: u32,
+    pub binding_switches: u32
+}
+
+// Forwards everything to another DrawingContext, counting the
+// calls that cost something on the way.
+pub struct ProfilingContext {
+    inner: Box<dyn DrawingContext>,
+    counters: DrawCounters,
+    current_pipeline: Option<Pipeline>,
+    current_bindings: Option<Bindings>
+}
+
+impl ProfilingContext {
+
+    pub fn new(inner: Box<dyn DrawingContext>) -> ProfilingContext {
+        ProfilingContext {
+            inner,
+            counters: DrawCounters::default(),
+            current_pipeline: None,
+            current_bindings: None
+        }
+    }
+
+    pub fn get_counters(&self) -> DrawCounters {
+        self.counters
+    }
+
+    // The counts since the last call, starting the next frame at 0.
+    pub fn take_counters(&mut self) -> DrawCounters {
+        std::mem::take(&mut self.counters)
+    }
+}
+
+impl DrawingContext for ProfilingContext {
+
+    fn new_vertex_buffer(&mut self, usage: BufferUsage, vertices: &[Vertex]) -> BufferId {
+        self.inner.new_vertex_buffer(usage, vertices)
+    }
+
+    fn new_index_buffer(&mut self, usage: BufferUsage, indices: &[u16]) -> BufferId {
+        self.inner.new_index_buffer(usage, indices)
+    }
+
+    fn delete_buffer(&mut self, buffer: BufferId) {
+        // A new buffer may reuse the id.
+        self.current_bindings = None;
+        self.inner.delete_buffer(buffer);
+    }
+
+    fn new_texture_from_rgba8(&mut self, width: u16, height: u16, bytes: &[u8]) -> TextureId {
+        self.inner.new_texture_from_rgba8(width, height, bytes)
+    }
+
+    fn set_texture_filter(&mut self, texture: TextureId, filter: FilterMode) {
+        self.inner.set_texture_filter(texture, filter);
+    }
+
+    fn set_texture_wrap(&mut self, texture: TextureId, wrap: TextureWrap) {
+        self.inner.set_texture_wrap(texture, wrap);
+    }
+
+    fn new_shader(&mut self, vertex: &str, fragment: &str, meta: ShaderMeta) -> Result<ShaderId, ShaderError> {
+        self.inner.new_shader(vertex, fragment, meta)
+    }
+
+    fn new_pipeline(&mut self, shader: ShaderId, blend_mode: BlendMode) -> Pipeline {
+        self.inner.new_pipeline(shader, blend_mode)
+    }
+
+    fn set_pipeline_blend_mode(&mut self, pipeline: &Pipeline, blend_mode: BlendMode) {
+        if self.current_pipeline == Some(*pipeline) {
+            self.current_pipeline = None;
+        }
+        self.inner.set_pipeline_blend_mode(pipeline, blend_mode);
+    }
+
+    fn apply_pipeline(&mut self, pipeline: &Pipeline) {
+        if self.current_pipeline != Some(*pipeline) {
+            self.counters.pipeline_switches += 1;
+            self.current_pipeline = Some(*pipeline);
+        }
+        self.inner.apply_pipeline(pipeline);
+    }
+
+    fn apply_bindings(&mut self, bindings: &Bindings) {
+        if self.current_bindings.as_ref() != Some(bindings) {
+            self.counters.binding_switches += 1;
+            self.current_bindings = Some(bindings.clone());
+        }
+        self.inner.apply_bindings(bindings);
+    }
+
+    fn apply_uniforms_from_bytes(&mut self, bytes: &[u8]) {
+        self.inner.apply_uniforms_from_bytes(bytes);
+    }
+
+    fn begin_default_pass(&mut self, action: PassAction) {
+        // Nothing carries over into a new pass.
+        self.current_pipeline = None;
+        self.current_bindings = None;
+        self.inner.begin_default_pass(action);
+    }
+
+    fn apply_viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.inner.apply_viewport(x, y, width, height);
+    }
+
+    fn draw(&mut self, base_element: i32, num_elements: i32, num_instances: i32) {
+        self.counters.draw_calls += 1;
+        self.counters.vertices += (num_elements.max(0) * num_instances.max(0)) as u32;
+        self.inner.draw(base_element, num_elements, num_instances);
+    }
+
+    fn end_render_pass(&mut self) {
+        self.inner.end_render_pass();
+    }
+
+    fn commit_frame(&mut self) {
+        self.inner.commit_frame();
+    }
+}
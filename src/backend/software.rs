@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+use miniquad::{BufferUsage, FilterMode, PassAction, ShaderError, ShaderMeta, TextureWrap};
+use crate::backend::drawing_context::{
+    Bindings, BlendMode, BufferId, DrawingContext, Pipeline, ShaderId, TextureId
+};
+use crate::backend::framebuffer::{to_rgba8, Framebuffer};
+use crate::shapes::gradient::{self, MAX_COLOR_STOPS};
+use crate::shapes::matrix::Mat4;
+use crate::shapes::shared_c_resources::Vertex;
+
+// CPU rasterizer for machines without a GPU (CI).
+// GLSL is not compiled, every shader is evaluated as
+// default_shader, reading its uniforms by name from the shader meta.
+pub struct SoftwareBackend {
+    framebuffer: Framebuffer,
+    buffers: Vec<SoftwareBuffer>,
+    textures: Vec<SoftwareTexture>,
+    shaders: Vec<ShaderMeta>,
+    pipelines: Vec<(ShaderId, BlendMode)>,
+    current_pipeline: Option<Pipeline>,
+    current_bindings: Option<Bindings>,
+    uniforms: HashMap<String, Vec<f32>>,
+    // x, y, width, height in pixels, origin top left. May reach
+    // past the framebuffer, drawing is clipped to both.
+    viewport: (i32, i32, i32, i32)
+}
+
+enum SoftwareBuffer {
+    Vertices(Vec<Vertex>),
+    Indices(Vec<u16>),
+    Deleted
+}
+
+struct SoftwareTexture {
+    // Premultiplied like every texture the shapes upload.
+    image: Framebuffer,
+    filter: FilterMode,
+    wrap: TextureWrap
+}
+
+// Sub-pixel precision vertices are snapped to, like GPUs do, so
+// edge functions are exact and shared edges agree between triangles.
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_SCALE: f32 = (1 << SUBPIXEL_BITS) as f32;
+const HALF_PIXEL: i64 = 1 << (SUBPIXEL_BITS - 1);
+// Projected positions are clamped this many pixels out before the
+// fixed point conversion, so the edge function products stay well
+// inside i64 however large a shape is zoomed.
+const GUARD_BAND: f32 = (1 << 21) as f32;
+
+// Position in fixed point pixels.
+struct ScreenVertex {
+    x: i64,
+    y: i64,
+    uv: (f32, f32)
+}
+
+impl SoftwareBackend {
+
+    pub fn new(width: u32, height: u32) -> SoftwareBackend {
+        SoftwareBackend {
+            framebuffer: Framebuffer::new(width, height),
+            buffers: vec![],
+            textures: vec![],
+            shaders: vec![],
+            pipelines: vec![],
+            current_pipeline: None,
+            current_bindings: None,
+            uniforms: HashMap::new(),
+            viewport: (0, 0, width as i32, height as i32)
+        }
+    }
+
+    pub fn get_framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    // For when nothing more will be drawn, saves copying the pixels.
+    pub fn into_framebuffer(self) -> Framebuffer {
+        self.framebuffer
+    }
+
+    // Missing uniforms read as zero, the same as an unset GL uniform.
+    fn uniform(&self, name: &str, index: usize) -> f32 {
+        self.uniforms
+            .get(name)
+            .and_then(|values| values.get(index))
+            .copied()
+            .unwrap_or(0.)
+    }
+
+    // Falls back to identity when the shader has no matrix uniform.
+    fn matrix(&self, name: &str) -> Mat4 {
+        match self.uniforms.get(name) {
+            Some(values) if values.len() == 16 =>
+                Mat4::from_affine(values[0], values[1], values[4], values[5], values[12], values[13]),
+            _ => Mat4::identity()
+        }
+    }
+
+    // Mirrors default_shader::VERTEX, NDC into pixel space.
+    fn shade_vertex(&self, vertex: &Vertex) -> ScreenVertex {
+        let model_view_projection = self.matrix("projection").multiply(&self.matrix("model"));
+        let (x, y) = model_view_projection.transform_point(vertex.pos.x, vertex.pos.y);
+
+        let (viewport_x, viewport_y, viewport_width, viewport_height) = self.viewport;
+        let pixel_x = viewport_x as f32 + (x + 1.) * 0.5 * viewport_width as f32;
+        let pixel_y = viewport_y as f32 + (1. - y) * 0.5 * viewport_height as f32;
+        let fixed = |pixel: f32| (pixel.clamp(-GUARD_BAND, GUARD_BAND) * SUBPIXEL_SCALE).round() as i64;
+        ScreenVertex {
+            x: fixed(pixel_x),
+            y: fixed(pixel_y),
+            uv: (vertex.uv.x, vertex.uv.y)
+        }
+    }
+
+    // Mirrors default_shader::FRAGMENT, colour arrives premultiplied.
+    fn shade_fragment(&self, uv: (f32, f32)) -> [f32; 4] {
+        let texel = self.sample_texture(uv);
+        let color = self.fill_color(uv);
+        [0, 1, 2, 3].map(|channel| texel[channel] * color[channel])
+    }
+
+    fn fill_color(&self, uv: (f32, f32)) -> [f32; 4] {
+        let gradient_kind = self.uniform("gradient_kind", 0);
+        if gradient_kind >= 0.5 {
+            let stop_count = (self.uniform("stop_count", 0) as usize).min(MAX_COLOR_STOPS);
+            let parameters = [0, 1, 2, 3].map(|index| self.uniform("gradient_parameters", index));
+            let offsets: Vec<f32> = (0..stop_count).map(|index| self.uniform("stop_offsets", index)).collect();
+            let colors: Vec<[f32; 4]> = (0..stop_count)
+                .map(|index| [0, 1, 2, 3].map(|channel| self.uniform("stop_colors", index * 4 + channel)))
+                .collect();
+            return gradient::sample(gradient_kind, parameters, &offsets, &colors, uv.0, uv.1);
+        }
+
+        [
+            self.uniform("red", 0),
+            self.uniform("green", 0),
+            self.uniform("blue", 0),
+            self.uniform("alpha", 0)
+        ]
+    }
+
+    // texture2D on the first bound image, GL's sampling rules
+    // without mipmaps. Nothing bound samples opaque white.
+    fn sample_texture(&self, (u, v): (f32, f32)) -> [f32; 4] {
+        let Some(texture) = self.current_bindings
+            .as_ref()
+            .and_then(|bindings| bindings.images.first())
+            .map(|image| &self.textures[image.0]) else {
+            return [1.; 4];
+        };
+        let (width, height) = (texture.image.get_width() as i64, texture.image.get_height() as i64);
+        if width == 0 || height == 0 {
+            return [0.; 4];
+        }
+
+        let texel = |x: i64, y: i64| {
+            let x = wrap(x, width, texture.wrap);
+            let y = wrap(y, height, texture.wrap);
+            texture.image.get_pixel(x as u32, y as u32).map(|channel| channel as f32 / 255.)
+        };
+
+        match texture.filter {
+            FilterMode::Nearest =>
+                texel((u * width as f32).floor() as i64, (v * height as f32).floor() as i64),
+            FilterMode::Linear => {
+                let x = u * width as f32 - 0.5;
+                let y = v * height as f32 - 0.5;
+                let (left, top) = (x.floor(), y.floor());
+                let (fraction_x, fraction_y) = (x - left, y - top);
+                let (left, top) = (left as i64, top as i64);
+
+                let (top_left, top_right) = (texel(left, top), texel(left + 1, top));
+                let (bottom_left, bottom_right) = (texel(left, top + 1), texel(left + 1, top + 1));
+                [0, 1, 2, 3].map(|channel| {
+                    let upper = top_left[channel] + (top_right[channel] - top_left[channel]) * fraction_x;
+                    let lower = bottom_left[channel] + (bottom_right[channel] - bottom_left[channel]) * fraction_x;
+                    upper + (lower - upper) * fraction_y
+                })
+            }
+        }
+    }
+
+    // Same factors MiniquadBackend hands to the GPU blender.
+    fn blend(&self, source: [f32; 4], destination: [u8; 4]) -> [u8; 4] {
+        let blend_mode = self.current_pipeline
+            .map(|pipeline| self.pipelines[pipeline.0].1)
+            .unwrap_or(BlendMode::Alpha);
+        let destination = destination.map(|channel| channel as f32 / 255.);
+        let source_alpha = source[3];
+
+        let color = |channel: usize| {
+            let (src, dst) = (source[channel], destination[channel]);
+            match blend_mode {
+                BlendMode::Alpha | BlendMode::Premultiplied => src + dst * (1. - source_alpha),
+                BlendMode::Additive => src + dst,
+                BlendMode::Multiply => src * dst + dst * (1. - source_alpha),
+                BlendMode::Screen => src + dst * (1. - src),
+            }
+        };
+
+        to_rgba8(
+            color(0),
+            color(1),
+            color(2),
+            source_alpha + destination[3] * (1. - source_alpha)
+        )
+    }
+
+    fn rasterize_triangle(&mut self, a: ScreenVertex, b: ScreenVertex, c: ScreenVertex) {
+        let area = edge(&a, &b, c.x, c.y);
+        if area == 0 {
+            return;
+        }
+
+        // Wind every triangle the same way so one inside test works.
+        let (a, b, c) = if area < 0 { (a, c, b) } else { (a, b, c) };
+        let area = area.abs() as f32;
+
+        // Clip to the viewport, GL never draws outside of it either.
+        let (viewport_x, viewport_y, viewport_width, viewport_height) = self.viewport;
+        let clip_left = viewport_x.max(0) as i64;
+        let clip_top = viewport_y.max(0) as i64;
+        let clip_right = (viewport_x as i64 + viewport_width as i64).min(self.framebuffer.get_width() as i64);
+        let clip_bottom = (viewport_y as i64 + viewport_height as i64).min(self.framebuffer.get_height() as i64);
+
+        let pixel_floor = |value: i64| value >> SUBPIXEL_BITS;
+        let pixel_ceil = |value: i64| (value + (1 << SUBPIXEL_BITS) - 1) >> SUBPIXEL_BITS;
+        let min_x = pixel_floor(a.x.min(b.x).min(c.x)).max(clip_left);
+        let min_y = pixel_floor(a.y.min(b.y).min(c.y)).max(clip_top);
+        let max_x = pixel_ceil(a.x.max(b.x).max(c.x)).min(clip_right);
+        let max_y = pixel_ceil(a.y.max(b.y).max(c.y)).min(clip_bottom);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                // Sample at pixel centres like GL does.
+                let (px, py) = ((x << SUBPIXEL_BITS) + HALF_PIXEL, (y << SUBPIXEL_BITS) + HALF_PIXEL);
+                let weight_a = edge(&b, &c, px, py);
+                let weight_b = edge(&c, &a, px, py);
+                let weight_c = edge(&a, &b, px, py);
+
+                if !covers(weight_a, &b, &c) || !covers(weight_b, &c, &a) || !covers(weight_c, &a, &b) {
+                    continue;
+                }
+
+                let (weight_a, weight_b, weight_c) = (weight_a as f32, weight_b as f32, weight_c as f32);
+                let uv = (
+                    (weight_a * a.uv.0 + weight_b * b.uv.0 + weight_c * c.uv.0) / area,
+                    (weight_a * a.uv.1 + weight_b * b.uv.1 + weight_c * c.uv.1) / area,
+                );
+                let (x, y) = (x as u32, y as u32);
+                let rgba = self.blend(self.shade_fragment(uv), self.framebuffer.get_pixel(x, y));
+                self.framebuffer.set_pixel(x, y, rgba);
+            }
+        }
+    }
+}
+
+// Exact, so a point on an edge shared by two triangles gets weights
+// of opposite sign in each whichever vertex the edge started from.
+fn edge(from: &ScreenVertex, to: &ScreenVertex, x: i64, y: i64) -> i64 {
+    (to.x - from.x) * (y - from.y) - (to.y - from.y) * (x - from.x)
+}
+
+// Top-left fill rule, pixels exactly on a shared edge are only
+// drawn by one of the two triangles (e.g. a Square's diagonal).
+// The two triangles walk the edge in opposite directions, and
+// exactly one of the two directions counts as top or left.
+fn covers(weight: i64, from: &ScreenVertex, to: &ScreenVertex) -> bool {
+    if weight != 0 {
+        return weight > 0;
+    }
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    (dy == 0 && dx > 0) || dy < 0
+}
+
+impl DrawingContext for SoftwareBackend {
+
+    fn new_vertex_buffer(&mut self, _usage: BufferUsage, vertices: &[Vertex]) -> BufferId {
+        self.buffers.push(SoftwareBuffer::Vertices(vertices.to_vec()));
+        BufferId(self.buffers.len() - 1)
+    }
+
+    fn new_index_buffer(&mut self, _usage: BufferUsage, indices: &[u16]) -> BufferId {
+        self.buffers.push(SoftwareBuffer::Indices(indices.to_vec()));
+        BufferId(self.buffers.len() - 1)
+    }
+
+    fn delete_buffer(&mut self, buffer: BufferId) {
+        self.buffers[buffer.0] = SoftwareBuffer::Deleted;
+    }
+
+    fn new_texture_from_rgba8(&mut self, width: u16, height: u16, bytes: &[u8]) -> TextureId {
+        self.textures.push(SoftwareTexture {
+            image: Framebuffer::from_rgba8(width as u32, height as u32, bytes.to_vec()),
+            filter: FilterMode::Linear,
+            wrap: TextureWrap::Clamp
+        });
+        TextureId(self.textures.len() - 1)
+    }
+
+    fn set_texture_filter(&mut self, texture: TextureId, filter: FilterMode) {
+        self.textures[texture.0].filter = filter;
+    }
+
+    fn set_texture_wrap(&mut self, texture: TextureId, wrap: TextureWrap) {
+        self.textures[texture.0].wrap = wrap;
+    }
+
+    fn new_shader(&mut self, _vertex: &str, _fragment: &str, meta: ShaderMeta) -> Result<ShaderId, ShaderError> {
+        self.shaders.push(meta);
+        Ok(ShaderId(self.shaders.len() - 1))
+    }
+
+    fn new_pipeline(&mut self, shader: ShaderId, blend_mode: BlendMode) -> Pipeline {
+        self.pipelines.push((shader, blend_mode));
+        Pipeline(self.pipelines.len() - 1)
+    }
+
+    fn set_pipeline_blend_mode(&mut self, pipeline: &Pipeline, blend_mode: BlendMode) {
+        self.pipelines[pipeline.0].1 = blend_mode;
+    }
+
+    fn apply_pipeline(&mut self, pipeline: &Pipeline) {
+        self.current_pipeline = Some(*pipeline);
+        self.uniforms.clear();
+    }
+
+    fn apply_bindings(&mut self, bindings: &Bindings) {
+        self.current_bindings = Some(bindings.clone());
+    }
+
+    fn apply_uniforms_from_bytes(&mut self, bytes: &[u8]) {
+        let Some(pipeline) = self.current_pipeline else {
+            return;
+        };
+        let meta = &self.shaders[self.pipelines[pipeline.0].0.0];
+
+        // Uniforms are packed back to back with no padding, the
+        // same walk miniquad's GL backend does.
+        let mut offset = 0;
+        self.uniforms.clear();
+        for desc in meta.uniforms.uniforms.iter() {
+            let size = desc.uniform_type.size() * desc.array_count;
+            if offset + size > bytes.len() {
+                break;
+            }
+            let values = bytes[offset..offset + size]
+                .chunks_exact(4)
+                .map(|float| f32::from_ne_bytes([float[0], float[1], float[2], float[3]]))
+                .collect();
+            self.uniforms.insert(desc.name.clone(), values);
+            offset += size;
+        }
+    }
+
+    fn begin_default_pass(&mut self, action: PassAction) {
+        self.viewport = (0, 0, self.framebuffer.get_width() as i32, self.framebuffer.get_height() as i32);
+        if let PassAction::Clear { color: Some((red, green, blue, alpha)), .. } = action {
+            self.framebuffer.clear(to_rgba8(red, green, blue, alpha));
+        }
+    }
+
+    // Kept as given so a viewport partly off screen is clipped
+    // rather than moved.
+    fn apply_viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.viewport = (x, y, width.max(0), height.max(0));
+    }
+
+    fn draw(&mut self, base_element: i32, num_elements: i32, _num_instances: i32) {
+        let Some(bindings) = self.current_bindings.as_ref() else {
+            return;
+        };
+        let (Some(SoftwareBuffer::Vertices(vertices)), Some(SoftwareBuffer::Indices(indices))) = (
+            bindings.vertex_buffers.first().map(|buffer| &self.buffers[buffer.0]),
+            Some(&self.buffers[bindings.index_buffer.0])
+        ) else {
+            return;
+        };
+
+        let start = (base_element.max(0) as usize).min(indices.len());
+        let end = (start + num_elements.max(0) as usize).min(indices.len());
+
+        // A triangle indexing past the vertex buffer is skipped, GL
+        // would read garbage or nothing for it.
+        let triangles: Vec<[ScreenVertex; 3]> = indices[start..end]
+            .chunks_exact(3)
+            .filter_map(|triangle| Some([
+                self.shade_vertex(vertices.get(triangle[0] as usize)?),
+                self.shade_vertex(vertices.get(triangle[1] as usize)?),
+                self.shade_vertex(vertices.get(triangle[2] as usize)?),
+            ]))
+            .collect();
+
+        for [a, b, c] in triangles {
+            self.rasterize_triangle(a, b, c);
+        }
+    }
+
+    fn end_render_pass(&mut self) {}
+
+    fn commit_frame(&mut self) {}
+}
+
+// Texel index for a wrap mode, size is never zero.
+fn wrap(index: i64, size: i64, mode: TextureWrap) -> i64 {
+    match mode {
+        TextureWrap::Clamp => index.clamp(0, size - 1),
+        TextureWrap::Repeat => index.rem_euclid(size),
+        TextureWrap::Mirror => {
+            let index = index.rem_euclid(size * 2);
+            if index < size { index } else { size * 2 - 1 - index }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::draw_parameters::DrawParameters;
+    use crate::shapes::shape::Shape;
+    use crate::shapes::triangle::Triangle;
+
+    #[test]
+    fn huge_triangles_are_clamped_to_the_guard_band() {
+        let mut context = SoftwareBackend::new(32, 32);
+        let mut triangle = Triangle::new(&mut context, 0., 0., 1e30, 1e30, 1., 0., 0.);
+
+        context.begin_default_pass(PassAction::default());
+        triangle.draw(&mut context, &DrawParameters::screen(), true);
+        context.end_render_pass();
+
+        for (x, y) in [(0, 0), (31, 0), (16, 16), (0, 31), (31, 31)] {
+            assert_eq!(context.get_framebuffer().get_pixel(x, y)[0], 255, "({}, {})", x, y);
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use std::path::Path;
+use crate::backend::atlas::{AtlasBuilder, AtlasError};
+use crate::backend::texture::TextureError;
+use crate::formats::json::{self, JsonValue};
+
+// How a tagged run of frames plays, from Aseprite's frameTags.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TagDirection {
+    Forward,
+    Reverse,
+    PingPong
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SheetFrame {
+    // Region name in the atlas.
+    pub name: String,
+    // Seconds, Aseprite sheets carry one per frame.
+    pub duration: Option<f32>
+}
+
+// Frames from..=to, indexes into SpriteSheet::get_frames.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub direction: TagDirection
+}
+
+// What a sprite sheet descriptor said about its frames. The pixels
+// themselves were cut out and handed to the AtlasBuilder to repack.
+pub struct SpriteSheet {
+    frames: Vec<SheetFrame>,
+    tags: Vec<FrameTag>
+}
+
+impl SpriteSheet {
+
+    // TexturePacker or Aseprite JSON, hash or array flavour. The
+    // image named in meta.image is read relative to the JSON file.
+    pub fn load(builder: &mut AtlasBuilder, path: impl AsRef<Path>) -> Result<SpriteSheet, AtlasError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| TextureError::Io(path.to_path_buf(), error.to_string()))?;
+        let descriptor = parse_descriptor(&text)?;
+
+        let image_name = descriptor
+            .get("meta")
+            .and_then(|meta| meta.get("image"))
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| sheet_error("meta.image is missing"))?;
+        let image_path = path.parent().unwrap_or(Path::new("")).join(image_name);
+        let image_bytes = std::fs::read(&image_path)
+            .map_err(|error| TextureError::Io(image_path.clone(), error.to_string()))?;
+
+        SpriteSheet::from_descriptor(builder, &descriptor, &image_bytes)
+    }
+
+    // For sheets embedded with include_str!/include_bytes!.
+    pub fn from_json(builder: &mut AtlasBuilder, json: &str, image_bytes: &[u8]) -> Result<SpriteSheet, AtlasError> {
+        SpriteSheet::from_descriptor(builder, &parse_descriptor(json)?, image_bytes)
+    }
+
+    pub fn get_frames(&self) -> &[SheetFrame] {
+        &self.frames
+    }
+
+    pub fn get_tags(&self) -> &[FrameTag] {
+        &self.tags
+    }
+
+    pub fn get_tag(&self, name: &str) -> Option<&FrameTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    fn from_descriptor(builder: &mut AtlasBuilder,
+                       descriptor: &JsonValue,
+                       image_bytes: &[u8]) -> Result<SpriteSheet, AtlasError> {
+
+        let sheet = image::load_from_memory(image_bytes)
+            .map_err(|error| TextureError::Decode(error.to_string()))?
+            .to_rgba8();
+
+        // Hash: {"name": {...}}, array: [{"filename": "name", ...}].
+        let entries: Vec<(String, &JsonValue)> = match descriptor.get("frames") {
+            Some(JsonValue::Object(members)) =>
+                members.iter().map(|(name, frame)| (name.clone(), frame)).collect(),
+            Some(JsonValue::Array(frames)) => frames
+                .iter()
+                .map(|frame| {
+                    frame.get("filename")
+                        .and_then(JsonValue::as_str)
+                        .map(|name| (name.to_string(), frame))
+                        .ok_or_else(|| sheet_error("a frame has no filename"))
+                })
+                .collect::<Result<_, _>>()?,
+            _ => return Err(sheet_error("frames is missing"))
+        };
+
+        let mut frames = vec![];
+        for (name, frame) in entries {
+            let (width, height, pixels) = cut_frame(&sheet, &name, frame)?;
+            builder.add_rgba8(&name, width, height, pixels)?;
+            frames.push(SheetFrame {
+                name,
+                duration: frame.get("duration").and_then(JsonValue::as_f64).map(|milliseconds| milliseconds as f32 / 1000.)
+            });
+        }
+
+        let mut tags = vec![];
+        let frame_tags = descriptor
+            .get("meta")
+            .and_then(|meta| meta.get("frameTags"))
+            .and_then(JsonValue::as_array)
+            .unwrap_or(&[]);
+        for tag in frame_tags {
+            let name = tag.get("name").and_then(JsonValue::as_str).ok_or_else(|| sheet_error("a frame tag has no name"))?;
+            let index = |key: &str| {
+                tag.get(key)
+                    .and_then(JsonValue::as_f64)
+                    .map(|index| index as usize)
+                    .filter(|index| *index < frames.len())
+                    .ok_or_else(|| sheet_error(&format!("frame tag '{}' has no valid {}", name, key)))
+            };
+            let direction = match tag.get("direction").and_then(JsonValue::as_str).unwrap_or("forward") {
+                "reverse" => TagDirection::Reverse,
+                "pingpong" | "pingpong_reverse" => TagDirection::PingPong,
+                _ => TagDirection::Forward
+            };
+            tags.push(FrameTag {
+                name: name.to_string(),
+                from: index("from")?,
+                to: index("to")?,
+                direction
+            });
+        }
+
+        Ok(SpriteSheet {
+            frames,
+            tags
+        })
+    }
+}
+
+fn sheet_error(message: &str) -> AtlasError {
+    AtlasError::SpriteSheet(format!("sprite sheet: {}", message))
+}
+
+fn parse_descriptor(text: &str) -> Result<JsonValue, AtlasError> {
+    json::parse(text).map_err(|error| sheet_error(&error.to_string()))
+}
+
+fn rectangle(value: Option<&JsonValue>) -> Option<(u32, u32, u32, u32)> {
+    let value = value?;
+    let field = |key: &str| value.get(key).and_then(JsonValue::as_f64).map(|number| number as u32);
+    Some((field("x").unwrap_or(0), field("y").unwrap_or(0), field("w")?, field("h")?))
+}
+
+// Copies one frame out of the sheet as straight RGBA8, undoing
+// TexturePacker's rotation and trimming.
+fn cut_frame(sheet: &image::RgbaImage, name: &str, frame: &JsonValue) -> Result<(u32, u32, Vec<u8>), AtlasError> {
+    let (frame_x, frame_y, width, height) = rectangle(frame.get("frame"))
+        .ok_or_else(|| sheet_error(&format!("frame '{}' has no frame rectangle", name)))?;
+    // Rotated frames are stored 90 degrees clockwise, frame keeps
+    // the unrotated size.
+    let rotated = frame.get("rotated").and_then(JsonValue::as_bool).unwrap_or(false);
+    let (stored_width, stored_height) = if rotated { (height, width) } else { (width, height) };
+    if frame_x + stored_width > sheet.width() || frame_y + stored_height > sheet.height() {
+        return Err(sheet_error(&format!("frame '{}' lies outside the image", name)));
+    }
+
+    // Trimmed frames go back into their untrimmed size.
+    let trimmed = frame.get("trimmed").and_then(JsonValue::as_bool).unwrap_or(false);
+    let (offset_x, offset_y, source_width, source_height) = match (trimmed, frame.get("sourceSize")) {
+        (true, Some(source_size)) => {
+            let (offset_x, offset_y, _, _) = rectangle(frame.get("spriteSourceSize")).unwrap_or((0, 0, width, height));
+            let size = |key: &str| source_size.get(key).and_then(JsonValue::as_f64).map(|number| number as u32);
+            (offset_x, offset_y, size("w").unwrap_or(width), size("h").unwrap_or(height))
+        }
+        _ => (0, 0, width, height)
+    };
+    if offset_x + width > source_width || offset_y + height > source_height {
+        return Err(sheet_error(&format!("frame '{}' is bigger than its source size", name)));
+    }
+
+    let mut pixels = vec![0u8; (source_width * source_height * 4) as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let (sheet_x, sheet_y) = if rotated {
+                (frame_x + height - 1 - y, frame_y + x)
+            } else {
+                (frame_x + x, frame_y + y)
+            };
+            let destination = (((offset_y + y) * source_width + offset_x + x) * 4) as usize;
+            pixels[destination..destination + 4].copy_from_slice(&sheet.get_pixel(sheet_x, sheet_y).0);
+        }
+    }
+    Ok((source_width, source_height, pixels))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One distinct grey per pixel, row by row.
+    fn sheet(width: u32, height: u32, values: &[u8]) -> image::RgbaImage {
+        image::RgbaImage::from_fn(width, height, |x, y| image::Rgba([values[(y * width + x) as usize]; 4]))
+    }
+
+    fn greys(pixels: &[u8]) -> Vec<u8> {
+        pixels.chunks(4).map(|pixel| pixel[0]).collect()
+    }
+
+    #[test]
+    fn rotated_frames_are_turned_back() {
+        // The 2x3 frame 1 2 / 3 4 / 5 6, stored 90 degrees clockwise.
+        let sheet = sheet(3, 2, &[5, 3, 1, 6, 4, 2]);
+        let frame = json::parse(r#"{"frame": {"x": 0, "y": 0, "w": 2, "h": 3}, "rotated": true}"#).unwrap();
+        let (width, height, pixels) = cut_frame(&sheet, "frame", &frame).unwrap();
+        assert_eq!((width, height), (2, 3));
+        assert_eq!(greys(&pixels), [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn trimmed_frames_are_restored_to_their_source_size() {
+        let sheet = sheet(3, 2, &[9, 1, 2, 9, 3, 4]);
+        let frame = json::parse(r#"{
+            "frame": {"x": 1, "y": 0, "w": 2, "h": 2},
+            "trimmed": true,
+            "spriteSourceSize": {"x": 1, "y": 2, "w": 2, "h": 2},
+            "sourceSize": {"w": 4, "h": 4}
+        }"#).unwrap();
+        let (width, height, pixels) = cut_frame(&sheet, "frame", &frame).unwrap();
+        assert_eq!((width, height), (4, 4));
+        assert_eq!(greys(&pixels), [
+            0, 0, 0, 0,
+            0, 0, 0, 0,
+            0, 1, 2, 0,
+            0, 3, 4, 0
+        ]);
+
+        let frame = json::parse(r#"{
+            "frame": {"x": 0, "y": 0, "w": 2, "h": 2},
+            "trimmed": true,
+            "spriteSourceSize": {"x": 3, "y": 0, "w": 2, "h": 2},
+            "sourceSize": {"w": 4, "h": 4}
+        }"#).unwrap();
+        assert!(cut_frame(&sheet, "frame", &frame).is_err());
+    }
+
+    fn load(tags: &str) -> Result<SpriteSheet, AtlasError> {
+        let mut png = vec![];
+        image::DynamicImage::ImageRgba8(sheet(3, 1, &[1, 2, 3]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        let json = format!(r#"{{
+            "frames": [
+                {{"filename": "a", "frame": {{"x": 0, "y": 0, "w": 1, "h": 1}}, "duration": 100}},
+                {{"filename": "b", "frame": {{"x": 1, "y": 0, "w": 1, "h": 1}}, "duration": 250}},
+                {{"filename": "c", "frame": {{"x": 2, "y": 0, "w": 1, "h": 1}}}}
+            ],
+            "meta": {{"frameTags": [{}]}}
+        }}"#, tags);
+        SpriteSheet::from_json(&mut AtlasBuilder::new(), &json, &png)
+    }
+
+    #[test]
+    fn frame_tags_and_their_directions() {
+        let sheet = load(r#"
+            {"name": "idle", "from": 0, "to": 2},
+            {"name": "back", "from": 0, "to": 1, "direction": "reverse"},
+            {"name": "bounce", "from": 1, "to": 2, "direction": "pingpong"},
+            {"name": "bounce back", "from": 0, "to": 2, "direction": "pingpong_reverse"}
+        "#).unwrap();
+
+        let durations: Vec<Option<f32>> = sheet.get_frames().iter().map(|frame| frame.duration).collect();
+        assert_eq!(durations, [Some(0.1), Some(0.25), None]);
+
+        let tags: Vec<(&str, usize, usize, TagDirection)> = sheet
+            .get_tags()
+            .iter()
+            .map(|tag| (tag.name.as_str(), tag.from, tag.to, tag.direction))
+            .collect();
+        assert_eq!(tags, [
+            ("idle", 0, 2, TagDirection::Forward),
+            ("back", 0, 1, TagDirection::Reverse),
+            ("bounce", 1, 2, TagDirection::PingPong),
+            ("bounce back", 0, 2, TagDirection::PingPong)
+        ]);
+        assert_eq!(sheet.get_tag("back").map(|tag| tag.direction), Some(TagDirection::Reverse));
+        assert!(sheet.get_tag("run").is_none());
+
+        assert!(load(r#"{"name": "past the end", "from": 1, "to": 3}"#).is_err());
+        assert!(load(r#"{"from": 0, "to": 1}"#).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use miniquad::{FilterMode, TextureWrap};
+use crate::backend::drawing_context::{DrawingContext, TextureId};
+
+// An uploaded image. Pixels are premultiplied by alpha on upload so
+// linear filtering does not bleed the colour of transparent texels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Texture {
+    id: TextureId,
+    width: u32,
+    height: u32
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(PathBuf, String),
+    Decode(String),
+    // Texture sizes are u16 in the rendering backend.
+    TooLarge { width: u32, height: u32 },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Io(path, error) =>
+                write!(f, "{}: {}", path.display(), error),
+            TextureError::Decode(error) =>
+                write!(f, "could not decode image: {}", error),
+            TextureError::TooLarge { width, height } =>
+                write!(f, "{}x{} image is larger than {}x{}", width, height, u16::MAX, u16::MAX),
+        }
+    }
+}
+
+impl Texture {
+
+    // PNG, JPEG, BMP and anything else the image crate was built
+    // with, picked from the file contents rather than the extension.
+    pub fn load(context: &mut dyn DrawingContext, path: impl AsRef<Path>) -> Result<Texture, TextureError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|error| TextureError::Io(path.to_path_buf(), error.to_string()))?;
+        Texture::from_bytes(context, &bytes)
+    }
+
+    // Encoded file contents, e.g. from include_bytes!.
+    pub fn from_bytes(context: &mut dyn DrawingContext, bytes: &[u8]) -> Result<Texture, TextureError> {
+        let image = image::load_from_memory(bytes)
+            .map_err(|error| TextureError::Decode(error.to_string()))?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
+        Texture::from_rgba8(context, width, height, image.into_raw())
+    }
+
+    // Straight alpha RGBA8, row 0 is the top of the image.
+    pub fn from_rgba8(context: &mut dyn DrawingContext,
+                      width: u32,
+                      height: u32,
+                      mut pixels: Vec<u8>) -> Result<Texture, TextureError> {
+
+        if width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(TextureError::TooLarge { width, height });
+        }
+
+        pixels.chunks_exact_mut(4).for_each(|pixel| {
+            let alpha = pixel[3] as u32;
+            for channel in &mut pixel[..3] {
+                *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+            }
+        });
+
+        Ok(Texture {
+            id: context.new_texture_from_rgba8(width as u16, height as u16, &pixels),
+            width,
+            height
+        })
+    }
+
+    pub fn get_id(&self) -> TextureId {
+        self.id
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    // Nearest keeps pixel art crisp, Linear smooths scaled images.
+    pub fn set_filter(&self, context: &mut dyn DrawingContext, filter: FilterMode) {
+        context.set_texture_filter(self.id, filter);
+    }
+
+    // Only visible when uvs leave 0..1, e.g. a tiled Sprite region.
+    // GLES2 devices only repeat power of two textures.
+    pub fn set_wrap(&self, context: &mut dyn DrawingContext, wrap: TextureWrap) {
+        context.set_texture_wrap(self.id, wrap);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// Just enough JSON for sprite sheet descriptors and config files.
+// Objects keep their key order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>)
+}
+
+// Line and column are 1 based and count characters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl JsonValue {
+
+    // First value stored under key, None if this is not an object.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            JsonValue::Object(members) => Some(members),
+            _ => None
+        }
+    }
+}
+
+// Compact JSON, {:#} indents nested values by two spaces.
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self, if f.alternate() { Some(0) } else { None })
+    }
+}
+
+// Where each value of a parsed document starts, keyed by paths like
+// "shapes[2].color" with "" for the document itself. Lets loaders
+// point at the value they could not use, not just at syntax errors.
+pub struct JsonPositions {
+    characters: Vec<char>,
+    offsets: HashMap<String, usize>
+}
+
+impl JsonPositions {
+
+    // An error at the value under path, or at the nearest value
+    // holding it when there is none, e.g. for a missing key.
+    pub fn error(&self, path: &str, message: &str) -> JsonError {
+        let mut path = path;
+        let offset = loop {
+            if let Some(offset) = self.offsets.get(path) {
+                break *offset;
+            }
+            match path.rfind(['.', '[']) {
+                Some(end) => path = &path[..end],
+                None => break self.offsets.get("").copied().unwrap_or(0)
+            }
+        };
+        error_at(&self.characters, offset, message)
+    }
+}
+
+pub fn parse(input: &str) -> Result<JsonValue, JsonError> {
+    let mut parser = Parser {
+        characters: input.chars().collect(),
+        position: 0,
+        path: String::new(),
+        offsets: None
+    };
+    parser.parse_document()
+}
+
+pub fn parse_with_positions(input: &str) -> Result<(JsonValue, JsonPositions), JsonError> {
+    let mut parser = Parser {
+        characters: input.chars().collect(),
+        position: 0,
+        path: String::new(),
+        offsets: Some(HashMap::new())
+    };
+    let value = parser.parse_document()?;
+    let positions = JsonPositions {
+        characters: parser.characters,
+        offsets: parser.offsets.unwrap_or_default()
+    };
+    Ok((value, positions))
+}
+
+fn error_at(characters: &[char], position: usize, message: &str) -> JsonError {
+    let before = &characters[..position.min(characters.len())];
+    let line = before.iter().filter(|character| **character == '\n').count() + 1;
+    let column = before.iter().rev().take_while(|character| **character != '\n').count() + 1;
+    JsonError {
+        line,
+        column,
+        message: message.to_string()
+    }
+}
+
+struct Parser {
+    characters: Vec<char>,
+    position: usize,
+    // Path of the value being parsed, only kept up when recording
+    // offsets.
+    path: String,
+    offsets: Option<HashMap<String, usize>>
+}
+
+impl Parser {
+
+    fn parse_document(&mut self) -> Result<JsonValue, JsonError> {
+        self.skip_whitespace();
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.position < self.characters.len() {
+            return Err(self.error("unexpected characters after the value"));
+        }
+        Ok(value)
+    }
+
+    fn error(&self, message: &str) -> JsonError {
+        error_at(&self.characters, self.position, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.characters.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", expected)))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, JsonError> {
+        if let Some(offsets) = self.offsets.as_mut() {
+            offsets.insert(self.path.clone(), self.position);
+        }
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some(_) => self.parse_literal(),
+            None => Err(self.error("unexpected end of input"))
+        }
+    }
+
+    fn parse_literal(&mut self) -> Result<JsonValue, JsonError> {
+        for (word, value) in [("true", JsonValue::Bool(true)), ("false", JsonValue::Bool(false)), ("null", JsonValue::Null)] {
+            let end = self.position + word.len();
+            if end <= self.characters.len() && self.characters[self.position..end].iter().copied().eq(word.chars()) {
+                self.position = end;
+                return Ok(value);
+            }
+        }
+        Err(self.error("expected a value"))
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.position;
+        while matches!(self.peek(), Some('-' | '+' | '.' | 'e' | 'E' | '0'..='9')) {
+            self.position += 1;
+        }
+        let text: String = self.characters[start..self.position].iter().collect();
+        text.parse::<f64>().map(JsonValue::Number).map_err(|_| {
+            self.position = start;
+            self.error(&format!("'{}' is not a number", text))
+        })
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut string = String::new();
+
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => {
+                    self.position += 1;
+                    return Ok(string);
+                }
+                Some('\\') => {
+                    self.position += 1;
+                    let escape = self.peek();
+                    self.position += 1;
+                    let escaped = match escape {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.parse_unicode_escape()?,
+                        _ => {
+                            self.position -= 1;
+                            return Err(self.error("invalid escape"));
+                        }
+                    };
+                    string.push(escaped);
+                }
+                Some(character) => {
+                    string.push(character);
+                    self.position += 1;
+                }
+            }
+        }
+    }
+
+    // Four hex digits, surrogate pairs are combined.
+    fn parse_unicode_escape(&mut self) -> Result<char, JsonError> {
+        let first = self.parse_hex_digits()?;
+        let code = if (0xD800..0xDC00).contains(&first) {
+            if self.peek() != Some('\\') || self.characters.get(self.position + 1) != Some(&'u') {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.position += 2;
+            let second = self.parse_hex_digits()?;
+            0x10000 + ((first - 0xD800) << 10) + (second.wrapping_sub(0xDC00) & 0x3FF)
+        } else {
+            first
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn parse_hex_digits(&mut self) -> Result<u32, JsonError> {
+        let end = self.position + 4;
+        if end > self.characters.len() {
+            return Err(self.error("expected four hex digits"));
+        }
+        let digits: String = self.characters[self.position..end].iter().collect();
+        let value = u32::from_str_radix(&digits, 16).map_err(|_| self.error("expected four hex digits"))?;
+        self.position = end;
+        Ok(value)
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect('[')?;
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(JsonValue::Array(values));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let parent = self.enter(|path| path.push_str(&format!("[{}]", values.len())));
+            values.push(self.parse_value()?);
+            self.path.truncate(parent);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']'"))
+            }
+        }
+    }
+
+    // Extends the path for a nested value, returning the length to
+    // truncate back to afterwards.
+    fn enter(&mut self, extend: impl FnOnce(&mut String)) -> usize {
+        let parent = self.path.len();
+        if self.offsets.is_some() {
+            extend(&mut self.path);
+        }
+        parent
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect('{')?;
+        let mut members = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(JsonValue::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            let parent = self.enter(|path| {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&key);
+            });
+            members.push((key, self.parse_value()?));
+            self.path.truncate(parent);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'"))
+            }
+        }
+    }
+}
+
+fn write_value(f: &mut fmt::Formatter<'_>, value: &JsonValue, indent: Option<usize>) -> fmt::Result {
+    let newline = |f: &mut fmt::Formatter<'_>, depth: usize| match indent {
+        Some(_) => write!(f, "\n{:width$}", "", width = depth * 2),
+        None => Ok(())
+    };
+    let depth = indent.unwrap_or(0);
+    let inner = indent.map(|depth| depth + 1);
+
+    match value {
+        JsonValue::Null => write!(f, "null"),
+        JsonValue::Bool(value) => write!(f, "{}", value),
+        // JSON has no NaN or infinity.
+        JsonValue::Number(value) if !value.is_finite() => write!(f, "null"),
+        JsonValue::Number(value) => write!(f, "{}", value),
+        JsonValue::String(value) => write_string(f, value),
+        JsonValue::Array(values) => {
+            write!(f, "[")?;
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ",")?;
+                }
+                newline(f, depth + 1)?;
+                write_value(f, value, inner)?;
+            }
+            if !values.is_empty() {
+                newline(f, depth)?;
+            }
+            write!(f, "]")
+        }
+        JsonValue::Object(members) => {
+            write!(f, "{{")?;
+            for (index, (name, value)) in members.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ",")?;
+                }
+                newline(f, depth + 1)?;
+                write_string(f, name)?;
+                write!(f, "{}", if indent.is_some() { ": " } else { ":" })?;
+                write_value(f, value, inner)?;
+            }
+            if !members.is_empty() {
+                newline(f, depth)?;
+            }
+            write!(f, "}}")
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for character in value.chars() {
+        match character {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            character if (character as u32) < 0x20 => write!(f, "\\u{:04x}", character as u32)?,
+            character => write!(f, "{}", character)?
+        }
+    }
+    write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(error: JsonError) -> (usize, usize) {
+        (error.line, error.column)
+    }
+
+    #[test]
+    fn error_at_counts_characters() {
+        let characters: Vec<char> = "ab\ncdé\nf".chars().collect();
+        assert_eq!(position(error_at(&characters, 0, "")), (1, 1));
+        assert_eq!(position(error_at(&characters, 2, "")), (1, 3));
+        assert_eq!(position(error_at(&characters, 3, "")), (2, 1));
+        assert_eq!(position(error_at(&characters, 6, "")), (2, 4));
+        assert_eq!(position(error_at(&characters, 7, "")), (3, 1));
+        // Past the end is the end.
+        assert_eq!(position(error_at(&characters, 100, "")), (3, 2));
+    }
+
+    #[test]
+    fn syntax_errors_have_positions() {
+        assert_eq!(position(parse("{\n  \"a\": [1, 2,]\n}").unwrap_err()), (2, 14));
+        assert_eq!(position(parse("[\"é\", tru]").unwrap_err()), (1, 7));
+        assert_eq!(position(parse("{} x").unwrap_err()), (1, 4));
+    }
+
+    #[test]
+    fn positions_find_values_and_their_parents() {
+        let (_, positions) = parse_with_positions("{\n  \"list\": [1,\n    { \"key\": true }]\n}").unwrap();
+        assert_eq!(position(positions.error("", "")), (1, 1));
+        assert_eq!(position(positions.error("list", "")), (2, 11));
+        assert_eq!(position(positions.error("list[1].key", "")), (3, 14));
+        // Missing values fall back to the nearest one holding them.
+        assert_eq!(position(positions.error("list[1].missing", "")), (3, 5));
+        assert_eq!(position(positions.error("other[3]", "")), (1, 1));
+    }
+
+    #[test]
+    fn round_trips_through_text() {
+        let text = r#"{"a":[1,2.5,-3e-7],"b":{"c":"d\"\n","e":null},"f":true}"#;
+        let value = parse(text).unwrap();
+        assert_eq!(parse(&value.to_string()).unwrap(), value);
+        assert_eq!(parse(&format!("{:#}", value)).unwrap(), value);
+    }
+}
+
@@ -0,0 +1,5 @@
+pub mod action_map;
+pub mod input_state;
+pub mod key_names;
+pub mod pointer;
+pub mod recording;
@@ -0,0 +1,491 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use miniquad::{KeyCode, KeyMods, MouseButton};
+use crate::formats::json::{self, JsonError, JsonValue};
+use crate::input::key_names::KEY_NAMES;
+
+// A key, optionally with modifiers that have to be held with it, or
+// a mouse button. Written "Ctrl+Shift+S" or "MouseLeft" in config
+// files. Modifiers not named are ignored, so "S" still fires while
+// Shift is held.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputBinding {
+    Key { key: KeyCode, modifiers: KeyMods },
+    Mouse(MouseButton)
+}
+
+impl InputBinding {
+
+    pub fn key(key: KeyCode) -> InputBinding {
+        InputBinding::Key { key, modifiers: KeyMods::default() }
+    }
+
+    // Names are matched ignoring case.
+    pub fn parse(text: &str) -> Option<InputBinding> {
+        let mut modifiers = KeyMods::default();
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let last = parts.pop()?;
+
+        for part in parts {
+            match part.to_ascii_lowercase().as_str() {
+                "shift" => modifiers.shift = true,
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "logo" | "super" | "cmd" => modifiers.logo = true,
+                _ => return None
+            }
+        }
+
+        let mouse_button = match last.to_ascii_lowercase().as_str() {
+            "mouseleft" => Some(MouseButton::Left),
+            "mouseright" => Some(MouseButton::Right),
+            "mousemiddle" => Some(MouseButton::Middle),
+            _ => None
+        };
+        if let Some(button) = mouse_button {
+            // Mouse bindings do not take modifiers.
+            return if modifiers == KeyMods::default() { Some(InputBinding::Mouse(button)) } else { None };
+        }
+
+        KEY_NAMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(last))
+            .map(|(_, key)| InputBinding::Key { key: *key, modifiers })
+    }
+
+    pub fn is_held(&self, keys_down: &HashSet<KeyCode>, buttons_down: &HashSet<MouseButton>, modifiers: &KeyMods) -> bool {
+        match self {
+            InputBinding::Key { key, modifiers: required } =>
+                keys_down.contains(key)
+                    && (!required.shift || modifiers.shift)
+                    && (!required.ctrl || modifiers.ctrl)
+                    && (!required.alt || modifiers.alt)
+                    && (!required.logo || modifiers.logo),
+            InputBinding::Mouse(button) => buttons_down.contains(button)
+        }
+    }
+}
+
+impl fmt::Display for InputBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputBinding::Key { key, modifiers } => {
+                let names = [(modifiers.ctrl, "Ctrl"), (modifiers.alt, "Alt"), (modifiers.shift, "Shift"), (modifiers.logo, "Logo")];
+                for (held, name) in names {
+                    if held {
+                        write!(f, "{}+", name)?;
+                    }
+                }
+                write!(f, "{:?}", key)
+            }
+            InputBinding::Mouse(button) => write!(f, "Mouse{:?}", button)
+        }
+    }
+}
+
+// Two bindings pulling an axis towards -1 and 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AxisBinding {
+    pub negative: InputBinding,
+    pub positive: InputBinding
+}
+
+#[derive(Debug)]
+pub enum ActionMapError {
+    Io(PathBuf, String),
+    Json(JsonError),
+    // Where in the file and what was wrong.
+    Invalid(String)
+}
+
+impl fmt::Display for ActionMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionMapError::Io(path, message) => write!(f, "{}: {}", path.display(), message),
+            ActionMapError::Json(error) => write!(f, "input config: {}", error),
+            ActionMapError::Invalid(message) => write!(f, "input config: {}", message)
+        }
+    }
+}
+
+// Named actions ("jump") and axes ("move_x") bound to keys and mouse
+// buttons, so shapes ask what the player wants rather than which key
+// is down. Only the bindings live here, InputState answers the
+// queries with what is held.
+//
+// Config files look like
+// {
+//   "actions": { "jump": ["Space", "MouseLeft"] },
+//   "axes": { "move_x": [["Left", "Right"], ["A", "D"]] }
+// }
+// with each axis binding written [negative, positive].
+#[derive(Clone)]
+pub struct ActionMap {
+    actions: Vec<(String, Vec<InputBinding>)>,
+    axes: Vec<(String, Vec<AxisBinding>)>,
+    // Action whose bindings the next press replaces.
+    rebinding: Option<String>
+}
+
+impl Default for ActionMap {
+    fn default() -> ActionMap {
+        ActionMap::new()
+    }
+}
+
+impl ActionMap {
+
+    pub fn new() -> ActionMap {
+        ActionMap {
+            actions: vec![],
+            axes: vec![],
+            rebinding: None
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<ActionMap, ActionMapError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| ActionMapError::Io(path.to_path_buf(), error.to_string()))?;
+        ActionMap::from_json(&text)
+    }
+
+    pub fn from_json(text: &str) -> Result<ActionMap, ActionMapError> {
+        ActionMap::from_value(&json::parse(text).map_err(ActionMapError::Json)?)
+    }
+
+    // The object to_json gives, also how recordings carry their map.
+    pub fn from_value(config: &JsonValue) -> Result<ActionMap, ActionMapError> {
+        let mut map = ActionMap::new();
+
+        for (name, bindings) in members(config, "actions")? {
+            let bindings = bindings
+                .as_array()
+                .ok_or_else(|| invalid(&format!("actions.{} should be a list of bindings", name)))?;
+            for (index, binding) in bindings.iter().enumerate() {
+                let binding = parse_binding(binding, &format!("actions.{}[{}]", name, index))?;
+                map.bind_action(name, binding);
+            }
+        }
+
+        for (name, bindings) in members(config, "axes")? {
+            let bindings = bindings
+                .as_array()
+                .ok_or_else(|| invalid(&format!("axes.{} should be a list of [negative, positive] pairs", name)))?;
+            for (index, pair) in bindings.iter().enumerate() {
+                let location = format!("axes.{}[{}]", name, index);
+                match pair.as_array() {
+                    Some([negative, positive]) => map.bind_axis(
+                        name,
+                        parse_binding(negative, &location)?,
+                        parse_binding(positive, &location)?
+                    ),
+                    _ => return Err(invalid(&format!("{} should be [negative, positive]", location)))
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    // Same layout from_json reads, for keeping rebinds.
+    pub fn to_json(&self) -> JsonValue {
+        let binding = |binding: &InputBinding| JsonValue::String(binding.to_string());
+        JsonValue::Object(vec![
+            ("actions".to_string(), JsonValue::Object(self.actions
+                .iter()
+                .map(|(name, bindings)| (name.clone(), JsonValue::Array(bindings.iter().map(binding).collect())))
+                .collect())),
+            ("axes".to_string(), JsonValue::Object(self.axes
+                .iter()
+                .map(|(name, bindings)| (name.clone(), JsonValue::Array(bindings
+                    .iter()
+                    .map(|axis| JsonValue::Array(vec![binding(&axis.negative), binding(&axis.positive)]))
+                    .collect())))
+                .collect()))
+        ])
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ActionMapError> {
+        let path = path.as_ref();
+        std::fs::write(path, format!("{:#}\n", self.to_json()))
+            .map_err(|error| ActionMapError::Io(path.to_path_buf(), error.to_string()))
+    }
+
+    pub fn get_action_names(&self) -> impl Iterator<Item = &str> {
+        self.actions.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn get_axis_names(&self) -> impl Iterator<Item = &str> {
+        self.axes.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn get_action_bindings(&self, action: &str) -> &[InputBinding] {
+        self.actions.iter().find(|(name, _)| name == action).map_or(&[], |(_, bindings)| bindings)
+    }
+
+    pub fn get_axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.iter().find(|(name, _)| name == axis).map_or(&[], |(_, bindings)| bindings)
+    }
+
+    // Adds to the action's bindings, creating it if needed.
+    pub fn bind_action(&mut self, action: &str, binding: InputBinding) {
+        let bindings = entry(&mut self.actions, action);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn set_action_bindings(&mut self, action: &str, bindings: Vec<InputBinding>) {
+        *entry(&mut self.actions, action) = bindings;
+    }
+
+    pub fn unbind_action(&mut self, action: &str) {
+        self.actions.retain(|(name, _)| name != action);
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, negative: InputBinding, positive: InputBinding) {
+        let binding = AxisBinding { negative, positive };
+        let bindings = entry(&mut self.axes, axis);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn set_axis_bindings(&mut self, axis: &str, bindings: Vec<AxisBinding>) {
+        *entry(&mut self.axes, axis) = bindings;
+    }
+
+    pub fn unbind_axis(&mut self, axis: &str) {
+        self.axes.retain(|(name, _)| name != axis);
+    }
+
+    // The next key (with whatever modifiers are held) or mouse button
+    // pressed becomes the action's only binding. Escape cancels.
+    pub fn rebind_next(&mut self, action: &str) {
+        self.rebinding = Some(action.to_string());
+    }
+
+    pub fn get_rebinding(&self) -> Option<&str> {
+        self.rebinding.as_deref()
+    }
+
+    pub fn cancel_rebind(&mut self) {
+        self.rebinding = None;
+    }
+
+    // is_held tells whether a single binding is held.
+    pub fn is_action_down(&self, action: &str, is_held: impl Fn(&InputBinding) -> bool) -> bool {
+        self.get_action_bindings(action).iter().any(is_held)
+    }
+
+    // -1..1, opposing bindings cancel out.
+    pub fn get_axis(&self, axis: &str, is_held: impl Fn(&InputBinding) -> bool) -> f32 {
+        let value: f32 = self.get_axis_bindings(axis)
+            .iter()
+            .map(|binding| {
+                let negative = if is_held(&binding.negative) { 1. } else { 0. };
+                let positive = if is_held(&binding.positive) { 1. } else { 0. };
+                positive - negative
+            })
+            .sum();
+        value.clamp(-1., 1.)
+    }
+
+    // Takes the press for rebind_next if it is waiting for one, true
+    // when it did and the press should go no further.
+    pub fn capture_key(&mut self, key: KeyCode, modifiers: KeyMods) -> bool {
+        if self.rebinding.is_none() || is_modifier(key) {
+            return false;
+        }
+        if let Some(action) = self.rebinding.take() {
+            if key != KeyCode::Escape {
+                self.set_action_bindings(&action, vec![InputBinding::Key { key, modifiers }]);
+            }
+        }
+        true
+    }
+
+    pub fn capture_mouse_button(&mut self, button: MouseButton) -> bool {
+        let Some(action) = self.rebinding.take() else {
+            return false;
+        };
+        self.set_action_bindings(&action, vec![InputBinding::Mouse(button)]);
+        true
+    }
+}
+
+fn entry<'a, T>(list: &'a mut Vec<(String, Vec<T>)>, name: &str) -> &'a mut Vec<T> {
+    let index = match list.iter().position(|(existing, _)| existing == name) {
+        Some(index) => index,
+        None => {
+            list.push((name.to_string(), vec![]));
+            list.len() - 1
+        }
+    };
+    &mut list[index].1
+}
+
+fn is_modifier(key: KeyCode) -> bool {
+    matches!(key,
+        KeyCode::LeftShift | KeyCode::RightShift |
+        KeyCode::LeftControl | KeyCode::RightControl |
+        KeyCode::LeftAlt | KeyCode::RightAlt |
+        KeyCode::LeftSuper | KeyCode::RightSuper)
+}
+
+fn invalid(message: &str) -> ActionMapError {
+    ActionMapError::Invalid(message.to_string())
+}
+
+// A missing section is the same as an empty one.
+fn members<'a>(config: &'a JsonValue, section: &str) -> Result<&'a [(String, JsonValue)], ActionMapError> {
+    match config.get(section) {
+        None => Ok(&[]),
+        Some(value) => value.as_object().ok_or_else(|| invalid(&format!("{} should be an object", section)))
+    }
+}
+
+fn parse_binding(value: &JsonValue, location: &str) -> Result<InputBinding, ActionMapError> {
+    let text = value
+        .as_str()
+        .ok_or_else(|| invalid(&format!("{} should be a string", location)))?;
+    InputBinding::parse(text).ok_or_else(|| invalid(&format!("{}: unknown binding '{}'", location, text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "actions": { "jump": ["Space", "MouseLeft"], "save": ["ctrl+shift+s"] },
+        "axes": { "move_x": [["Left", "Right"], ["A", "D"]] }
+    }"#;
+
+    fn shift() -> KeyMods {
+        KeyMods { shift: true, ..KeyMods::default() }
+    }
+
+    #[test]
+    fn loads_config() {
+        let map = ActionMap::from_json(CONFIG).unwrap();
+        assert_eq!(map.get_action_bindings("jump"), &[InputBinding::key(KeyCode::Space), InputBinding::Mouse(MouseButton::Left)]);
+        assert_eq!(map.get_action_bindings("save"), &[InputBinding::Key {
+            key: KeyCode::S,
+            modifiers: KeyMods { ctrl: true, shift: true, ..KeyMods::default() }
+        }]);
+        assert_eq!(map.get_axis_bindings("move_x").len(), 2);
+        assert_eq!(map.get_action_bindings("missing"), &[]);
+    }
+
+    #[test]
+    fn missing_sections_are_empty() {
+        let map = ActionMap::from_json("{}").unwrap();
+        assert_eq!(map.get_action_names().count(), 0);
+        assert_eq!(map.get_axis_names().count(), 0);
+    }
+
+    #[test]
+    fn rejects_bad_config() {
+        let message = |text: &str| match ActionMap::from_json(text) {
+            Err(ActionMapError::Invalid(message)) => message,
+            other => panic!("expected Invalid, got {:?}", other.map(|_| ()))
+        };
+        assert_eq!(message(r#"{ "actions": { "jump": ["Spacebar"] } }"#), "actions.jump[0]: unknown binding 'Spacebar'");
+        assert_eq!(message(r#"{ "axes": { "move_x": [["Left"]] } }"#), "axes.move_x[0] should be [negative, positive]");
+        assert_eq!(message(r#"{ "actions": [] }"#), "actions should be an object");
+        assert!(matches!(ActionMap::from_json("{"), Err(ActionMapError::Json(_))));
+    }
+
+    #[test]
+    fn parses_bindings() {
+        assert_eq!(InputBinding::parse("mouseright"), Some(InputBinding::Mouse(MouseButton::Right)));
+        assert_eq!(InputBinding::parse("Cmd + Q"), Some(InputBinding::Key {
+            key: KeyCode::Q,
+            modifiers: KeyMods { logo: true, ..KeyMods::default() }
+        }));
+        assert_eq!(InputBinding::parse("Shift+MouseLeft"), None);
+        assert_eq!(InputBinding::parse("Hyper+A"), None);
+    }
+
+    #[test]
+    fn modifiers_must_be_held_but_extra_ones_are_ignored() {
+        let keys: HashSet<KeyCode> = [KeyCode::S].into_iter().collect();
+        let buttons = HashSet::new();
+        let plain = InputBinding::key(KeyCode::S);
+        let shifted = InputBinding::parse("Shift+S").unwrap();
+
+        assert!(plain.is_held(&keys, &buttons, &shift()));
+        assert!(shifted.is_held(&keys, &buttons, &shift()));
+        assert!(!shifted.is_held(&keys, &buttons, &KeyMods::default()));
+    }
+
+    #[test]
+    fn resolves_axes() {
+        let map = ActionMap::from_json(CONFIG).unwrap();
+        let held = |keys: &'static [KeyCode]| move |binding: &InputBinding| match binding {
+            InputBinding::Key { key, .. } => keys.contains(key),
+            InputBinding::Mouse(_) => false
+        };
+
+        assert_eq!(map.get_axis("move_x", held(&[])), 0.);
+        assert_eq!(map.get_axis("move_x", held(&[KeyCode::Right])), 1.);
+        assert_eq!(map.get_axis("move_x", held(&[KeyCode::A])), -1.);
+        // Opposing bindings cancel, matching ones do not add up past 1.
+        assert_eq!(map.get_axis("move_x", held(&[KeyCode::Left, KeyCode::D])), 0.);
+        assert_eq!(map.get_axis("move_x", held(&[KeyCode::Right, KeyCode::D])), 1.);
+        assert_eq!(map.get_axis("missing", held(&[KeyCode::Right])), 0.);
+        assert!(map.is_action_down("jump", held(&[KeyCode::Space])));
+    }
+
+    #[test]
+    fn rebinds_the_next_press() {
+        let mut map = ActionMap::from_json(CONFIG).unwrap();
+        assert!(!map.capture_key(KeyCode::J, KeyMods::default()));
+
+        map.rebind_next("jump");
+        assert_eq!(map.get_rebinding(), Some("jump"));
+        // Modifiers on their own wait for the key they go with.
+        assert!(!map.capture_key(KeyCode::LeftShift, shift()));
+        assert!(map.capture_key(KeyCode::J, shift()));
+        assert_eq!(map.get_action_bindings("jump"), &[InputBinding::Key { key: KeyCode::J, modifiers: shift() }]);
+        assert_eq!(map.get_rebinding(), None);
+
+        map.rebind_next("jump");
+        assert!(map.capture_mouse_button(MouseButton::Middle));
+        assert_eq!(map.get_action_bindings("jump"), &[InputBinding::Mouse(MouseButton::Middle)]);
+    }
+
+    #[test]
+    fn escape_cancels_a_rebind() {
+        let mut map = ActionMap::from_json(CONFIG).unwrap();
+        map.rebind_next("jump");
+        assert!(map.capture_key(KeyCode::Escape, KeyMods::default()));
+        assert_eq!(map.get_action_bindings("jump").len(), 2);
+        assert_eq!(map.get_rebinding(), None);
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let map = ActionMap::from_json(CONFIG).unwrap();
+        let reloaded = ActionMap::from_json(&map.to_json().to_string()).unwrap();
+        assert_eq!(reloaded.to_json(), map.to_json());
+        assert_eq!(reloaded.get_action_bindings("save"), map.get_action_bindings("save"));
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let path = std::env::temp_dir().join(format!("action_map_{}.json", std::process::id()));
+        let mut map = ActionMap::from_json(CONFIG).unwrap();
+        map.set_action_bindings("jump", vec![InputBinding::parse("Alt+Enter").unwrap()]);
+        map.save(&path).unwrap();
+
+        let loaded = ActionMap::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.get_action_bindings("jump"), map.get_action_bindings("jump"));
+        assert_eq!(loaded.get_axis_bindings("move_x"), map.get_axis_bindings("move_x"));
+        assert!(matches!(ActionMap::load(&path), Err(ActionMapError::Io(..))));
+    }
+}
+
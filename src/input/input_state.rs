@@ -0,0 +1,378 @@
+use std::collections::{HashMap, HashSet};
+use miniquad::{KeyCode, KeyMods, MouseButton};
+use crate::input::action_map::{ActionMap, InputBinding};
+use crate::shapes::shared_c_resources::Vec2;
+
+// Everything the keyboard and mouse are doing, shared by every shape
+// through Shape::update. Stage feeds it events as they arrive and
+// calls update before each simulation step. Queries answer for that
+// step, so a press is just_pressed for exactly one step even when a
+// frame runs several fixed steps or none.
+pub struct InputState {
+    actions: ActionMap,
+    // Live, changed as events arrive.
+    keys_held: HashSet<KeyCode>,
+    buttons_held: HashSet<MouseButton>,
+    modifiers: KeyMods,
+    mouse_position: Vec2,
+    // Gathered since the last update.
+    pending_pressed_keys: HashSet<KeyCode>,
+    pending_released_keys: HashSet<KeyCode>,
+    pending_repeated_keys: HashSet<KeyCode>,
+    pending_pressed_buttons: HashSet<MouseButton>,
+    pending_released_buttons: HashSet<MouseButton>,
+    pending_text: String,
+    pending_wheel: Vec2,
+    // What the current step sees.
+    keys_down: HashSet<KeyCode>,
+    pressed_keys: HashSet<KeyCode>,
+    released_keys: HashSet<KeyCode>,
+    repeated_keys: HashSet<KeyCode>,
+    buttons_down: HashSet<MouseButton>,
+    pressed_buttons: HashSet<MouseButton>,
+    released_buttons: HashSet<MouseButton>,
+    step_modifiers: KeyMods,
+    text: String,
+    wheel: Vec2,
+    // The previous step, for actions released this step.
+    previous_keys_down: HashSet<KeyCode>,
+    previous_buttons_down: HashSet<MouseButton>,
+    previous_modifiers: KeyMods,
+    // Seconds of simulation each key and button has been down.
+    key_durations: HashMap<KeyCode, f32>,
+    button_durations: HashMap<MouseButton, f32>
+}
+
+impl InputState {
+
+    pub fn new(actions: ActionMap) -> InputState {
+        InputState {
+            actions,
+            keys_held: HashSet::new(),
+            buttons_held: HashSet::new(),
+            modifiers: KeyMods::default(),
+            mouse_position: Vec2 { x: 0., y: 0. },
+            pending_pressed_keys: HashSet::new(),
+            pending_released_keys: HashSet::new(),
+            pending_repeated_keys: HashSet::new(),
+            pending_pressed_buttons: HashSet::new(),
+            pending_released_buttons: HashSet::new(),
+            pending_text: String::new(),
+            pending_wheel: Vec2 { x: 0., y: 0. },
+            keys_down: HashSet::new(),
+            pressed_keys: HashSet::new(),
+            released_keys: HashSet::new(),
+            repeated_keys: HashSet::new(),
+            buttons_down: HashSet::new(),
+            pressed_buttons: HashSet::new(),
+            released_buttons: HashSet::new(),
+            step_modifiers: KeyMods::default(),
+            text: String::new(),
+            wheel: Vec2 { x: 0., y: 0. },
+            previous_keys_down: HashSet::new(),
+            previous_buttons_down: HashSet::new(),
+            previous_modifiers: KeyMods::default(),
+            key_durations: HashMap::new(),
+            button_durations: HashMap::new()
+        }
+    }
+
+    pub fn get_actions(&self) -> &ActionMap {
+        &self.actions
+    }
+
+    // For rebinding while running.
+    pub fn get_actions_mut(&mut self) -> &mut ActionMap {
+        &mut self.actions
+    }
+
+    // Moves everything gathered since the last call into view and
+    // ages what is held by dt.
+    pub fn update(&mut self, dt: f32) {
+        self.previous_keys_down = std::mem::replace(&mut self.keys_down, self.keys_held.clone());
+        self.previous_buttons_down = std::mem::replace(&mut self.buttons_down, self.buttons_held.clone());
+        self.previous_modifiers = self.step_modifiers;
+        self.step_modifiers = self.modifiers;
+
+        self.pressed_keys = std::mem::take(&mut self.pending_pressed_keys);
+        self.released_keys = std::mem::take(&mut self.pending_released_keys);
+        self.repeated_keys = std::mem::take(&mut self.pending_repeated_keys);
+        self.pressed_buttons = std::mem::take(&mut self.pending_pressed_buttons);
+        self.released_buttons = std::mem::take(&mut self.pending_released_buttons);
+        self.text = std::mem::take(&mut self.pending_text);
+        self.wheel = std::mem::replace(&mut self.pending_wheel, Vec2 { x: 0., y: 0. });
+
+        // A key pressed this step has been held for 0 seconds.
+        let keys_down = &self.keys_down;
+        self.key_durations.retain(|key, _| keys_down.contains(key));
+        for key in self.keys_down.iter() {
+            let duration = self.key_durations.entry(*key).or_insert(-dt);
+            *duration += dt;
+        }
+        let buttons_down = &self.buttons_down;
+        self.button_durations.retain(|button, _| buttons_down.contains(button));
+        for button in self.buttons_down.iter() {
+            let duration = self.button_durations.entry(*button).or_insert(-dt);
+            *duration += dt;
+        }
+    }
+
+    // True when the press was taken by ActionMap::rebind_next.
+    pub fn key_down(&mut self, key: KeyCode, modifiers: KeyMods, repeat: bool) -> bool {
+        self.modifiers = modifiers;
+        if repeat {
+            self.pending_repeated_keys.insert(key);
+            return false;
+        }
+        if self.actions.capture_key(key, modifiers) {
+            return true;
+        }
+        if self.keys_held.insert(key) {
+            self.pending_pressed_keys.insert(key);
+        }
+        false
+    }
+
+    pub fn key_up(&mut self, key: KeyCode, modifiers: KeyMods) {
+        self.modifiers = modifiers;
+        if self.keys_held.remove(&key) {
+            self.pending_released_keys.insert(key);
+        }
+    }
+
+    // Typed characters, control characters such as backspace are
+    // left to the key queries.
+    pub fn character(&mut self, character: char, modifiers: KeyMods) {
+        self.modifiers = modifiers;
+        if !character.is_control() {
+            self.pending_text.push(character);
+        }
+    }
+
+    // True when the press was taken by ActionMap::rebind_next.
+    pub fn mouse_button_down(&mut self, button: MouseButton) -> bool {
+        if self.actions.capture_mouse_button(button) {
+            return true;
+        }
+        if self.buttons_held.insert(button) {
+            self.pending_pressed_buttons.insert(button);
+        }
+        false
+    }
+
+    pub fn mouse_button_up(&mut self, button: MouseButton) {
+        if self.buttons_held.remove(&button) {
+            self.pending_released_buttons.insert(button);
+        }
+    }
+
+    // Window pixels, origin top left.
+    pub fn mouse_motion(&mut self, x: f32, y: f32) {
+        self.mouse_position = Vec2 { x, y };
+    }
+
+    pub fn mouse_wheel(&mut self, x: f32, y: f32) {
+        self.pending_wheel.x += x;
+        self.pending_wheel.y += y;
+    }
+
+    // Releases everything held, e.g. when the window is minimized
+    // and the releases will never arrive.
+    pub fn release_all(&mut self) {
+        self.pending_released_keys.extend(self.keys_held.drain());
+        self.pending_released_buttons.extend(self.buttons_held.drain());
+        self.modifiers = KeyMods::default();
+    }
+
+    pub fn is_down(&self, key: KeyCode) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    // Also true when the key was let go again within the same step.
+    pub fn just_pressed(&self, key: KeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    pub fn just_released(&self, key: KeyCode) -> bool {
+        self.released_keys.contains(&key)
+    }
+
+    // The OS sent a key repeat for it this step, for text fields and
+    // menus that scroll while a key is held.
+    pub fn is_repeated(&self, key: KeyCode) -> bool {
+        self.repeated_keys.contains(&key)
+    }
+
+    // Seconds, 0 when the key is up.
+    pub fn get_held_duration(&self, key: KeyCode) -> f32 {
+        self.key_durations.get(&key).copied().unwrap_or(0.)
+    }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.released_buttons.contains(&button)
+    }
+
+    pub fn get_mouse_held_duration(&self, button: MouseButton) -> f32 {
+        self.button_durations.get(&button).copied().unwrap_or(0.)
+    }
+
+    pub fn get_modifiers(&self) -> KeyMods {
+        self.step_modifiers
+    }
+
+    pub fn get_mouse_position(&self) -> Vec2 {
+        self.mouse_position
+    }
+
+    // Wheel movement during this step.
+    pub fn get_wheel(&self) -> Vec2 {
+        self.wheel
+    }
+
+    // Characters typed during this step, in order.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_binding_down(&self, binding: &InputBinding) -> bool {
+        binding.is_held(&self.keys_down, &self.buttons_down, &self.step_modifiers)
+    }
+
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.actions.is_action_down(action, |binding| self.is_binding_down(binding))
+    }
+
+    // Also true when it was tapped and let go within the same step.
+    pub fn is_action_just_pressed(&self, action: &str) -> bool {
+        self.was_action_tapped(action) || (self.is_action_down(action) && !self.was_action_down(action))
+    }
+
+    pub fn is_action_just_released(&self, action: &str) -> bool {
+        self.was_action_tapped(action) || (self.was_action_down(action) && !self.is_action_down(action))
+    }
+
+    // -1..1, see ActionMap::get_axis.
+    pub fn get_axis(&self, axis: &str) -> f32 {
+        self.actions.get_axis(axis, |binding| self.is_binding_down(binding))
+    }
+
+    // Pressed and released between two steps, so never seen down.
+    fn was_action_tapped(&self, action: &str) -> bool {
+        self.actions.get_action_bindings(action).iter().any(|binding| match binding {
+            InputBinding::Key { key, .. } => self.pressed_keys.contains(key) && !self.keys_down.contains(key),
+            InputBinding::Mouse(button) => self.pressed_buttons.contains(button) && !self.buttons_down.contains(button)
+        })
+    }
+
+    fn was_action_down(&self, action: &str) -> bool {
+        self.actions.is_action_down(action, |binding| {
+            binding.is_held(&self.previous_keys_down, &self.previous_buttons_down, &self.previous_modifiers)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: f32 = 0.125;
+
+    fn shift() -> KeyMods {
+        KeyMods { shift: true, ..KeyMods::default() }
+    }
+
+    fn input_with(action: &str, binding: InputBinding) -> InputState {
+        let mut actions = ActionMap::new();
+        actions.bind_action(action, binding);
+        InputState::new(actions)
+    }
+
+    #[test]
+    fn just_pressed_lasts_one_step() {
+        let mut input = InputState::new(ActionMap::new());
+        input.key_down(KeyCode::Space, KeyMods::default(), false);
+
+        // A frame that runs three fixed steps.
+        let pressed: Vec<bool> = (0..3).map(|_| {
+            input.update(STEP);
+            assert!(input.is_down(KeyCode::Space));
+            input.just_pressed(KeyCode::Space)
+        }).collect();
+        assert_eq!(pressed, vec![true, false, false]);
+
+        input.key_up(KeyCode::Space, KeyMods::default());
+        input.update(STEP);
+        assert!(input.just_released(KeyCode::Space) && !input.is_down(KeyCode::Space));
+        input.update(STEP);
+        assert!(!input.just_released(KeyCode::Space));
+    }
+
+    #[test]
+    fn taps_within_a_step_are_pressed_and_released() {
+        let mut input = input_with("jump", InputBinding::key(KeyCode::Space));
+        input.key_down(KeyCode::Space, KeyMods::default(), false);
+        input.key_up(KeyCode::Space, KeyMods::default());
+
+        input.update(STEP);
+        assert!(!input.is_action_down("jump"));
+        assert!(input.is_action_just_pressed("jump"));
+        assert!(input.is_action_just_released("jump"));
+        assert!(input.just_pressed(KeyCode::Space) && input.just_released(KeyCode::Space));
+
+        input.update(STEP);
+        assert!(!input.is_action_just_pressed("jump"));
+        assert!(!input.is_action_just_released("jump"));
+    }
+
+    #[test]
+    fn held_duration_starts_at_zero() {
+        let mut input = InputState::new(ActionMap::new());
+        input.key_down(KeyCode::W, KeyMods::default(), false);
+        input.mouse_button_down(MouseButton::Left);
+
+        let durations: Vec<f32> = (0..3).map(|_| {
+            input.update(STEP);
+            assert_eq!(input.get_mouse_held_duration(MouseButton::Left), input.get_held_duration(KeyCode::W));
+            input.get_held_duration(KeyCode::W)
+        }).collect();
+        assert_eq!(durations, vec![0., STEP, 2. * STEP]);
+
+        input.key_up(KeyCode::W, KeyMods::default());
+        input.update(STEP);
+        assert_eq!(input.get_held_duration(KeyCode::W), 0.);
+
+        // Pressed again, it starts over.
+        input.key_down(KeyCode::W, KeyMods::default(), false);
+        input.update(STEP);
+        assert_eq!(input.get_held_duration(KeyCode::W), 0.);
+    }
+
+    #[test]
+    fn modifiers_are_latched_per_step() {
+        let mut input = input_with("select", InputBinding::Key { key: KeyCode::A, modifiers: shift() });
+        input.key_down(KeyCode::A, shift(), false);
+        assert_eq!(input.get_modifiers(), KeyMods::default());
+
+        input.update(STEP);
+        assert_eq!(input.get_modifiers(), shift());
+        assert!(input.is_action_down("select"));
+
+        // Shift let go between steps, the step still sees it held
+        // until the next update.
+        input.key_up(KeyCode::LeftShift, KeyMods::default());
+        assert_eq!(input.get_modifiers(), shift());
+        input.update(STEP);
+        assert_eq!(input.get_modifiers(), KeyMods::default());
+        assert!(input.is_down(KeyCode::A));
+        assert!(!input.is_action_down("select"));
+        assert!(input.is_action_just_released("select"));
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use miniquad::{MouseButton, TouchPhase};
+use crate::scene::camera::Camera2D;
+use crate::scene::scene_graph::{NodeId, SceneGraph};
+use crate::shapes::shared_c_resources::Vec2;
+
+// Window pixels a press has to travel before it counts as a drag
+// rather than a click.
+pub const DRAG_THRESHOLD: f32 = 4.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PointerId {
+    Mouse,
+    // miniquad's touch id, stable for as long as the finger is down.
+    Touch(u64)
+}
+
+// What a shape callback is told. Positions are in scene units, or
+// clip space for shapes locked to the camera.
+#[derive(Clone, Copy)]
+pub struct PointerEvent {
+    pub pointer: PointerId,
+    pub node: NodeId,
+    // None for touches and hovering.
+    pub button: Option<MouseButton>,
+    pub position: Vec2,
+    // Same coordinates as the vertices the shape was built with.
+    pub local: Vec2,
+    // on_drag: movement since the last on_drag, on_scroll: how far
+    // the wheel turned. Zero otherwise.
+    pub delta: Vec2
+}
+
+pub type PointerHandler = Box<dyn FnMut(&PointerEvent)>;
+
+// Closures a shape runs from its pointer callbacks, e.g.
+// shape.get_pointer_handlers_mut().on_click = Some(Box::new(...)).
+#[derive(Default)]
+pub struct PointerHandlers {
+    pub on_click: Option<PointerHandler>,
+    pub on_hover_enter: Option<PointerHandler>,
+    pub on_hover_leave: Option<PointerHandler>,
+    pub on_drag: Option<PointerHandler>,
+    pub on_scroll: Option<PointerHandler>
+}
+
+struct Press {
+    // None when the press started over nothing.
+    node: Option<NodeId>,
+    button: Option<MouseButton>,
+    start: Vec2,
+    // Scene position the last on_drag was sent for.
+    last_position: Vec2,
+    dragging: bool
+}
+
+// Turns window events into shape callbacks. A shape hovered gets
+// on_hover_enter then on_hover_leave, a press released over the
+// shape it started on without moving DRAG_THRESHOLD is an on_click,
+// and moving further makes it on_drag events to that shape until
+// release, wherever the pointer goes.
+pub struct PointerRouter {
+    hovered: HashMap<PointerId, NodeId>,
+    presses: HashMap<PointerId, Press>,
+    mouse_position: Vec2
+}
+
+impl Default for PointerRouter {
+    fn default() -> PointerRouter {
+        PointerRouter::new()
+    }
+}
+
+impl PointerRouter {
+
+    pub fn new() -> PointerRouter {
+        PointerRouter {
+            hovered: HashMap::new(),
+            presses: HashMap::new(),
+            mouse_position: Vec2 { x: 0., y: 0. }
+        }
+    }
+
+    pub fn get_hovered(&self, pointer: PointerId) -> Option<NodeId> {
+        self.hovered.get(&pointer).copied()
+    }
+
+    pub fn is_dragging(&self, pointer: PointerId) -> bool {
+        self.presses.get(&pointer).is_some_and(|press| press.dragging)
+    }
+
+    pub fn mouse_motion(&mut self, scene: &mut SceneGraph, camera: &Camera2D, x: f32, y: f32) {
+        self.mouse_position = Vec2 { x, y };
+        self.pointer_moved(scene, camera, PointerId::Mouse, x, y);
+    }
+
+    pub fn mouse_button_down(&mut self, scene: &mut SceneGraph, camera: &Camera2D, button: MouseButton, x: f32, y: f32) {
+        self.mouse_position = Vec2 { x, y };
+        self.pointer_down(scene, camera, PointerId::Mouse, Some(button), x, y);
+    }
+
+    pub fn mouse_button_up(&mut self, scene: &mut SceneGraph, camera: &Camera2D, button: MouseButton, x: f32, y: f32) {
+        self.mouse_position = Vec2 { x, y };
+        let pressed_button = self.presses.get(&PointerId::Mouse).and_then(|press| press.button);
+        if pressed_button == Some(button) {
+            self.pointer_up(scene, camera, PointerId::Mouse, x, y);
+        }
+    }
+
+    // Goes to whatever is under the mouse.
+    pub fn mouse_wheel(&mut self, scene: &mut SceneGraph, camera: &Camera2D, delta_x: f32, delta_y: f32) {
+        let Vec2 { x, y } = self.mouse_position;
+        let Some(hit) = scene.pick(camera, x, y) else {
+            return;
+        };
+        let event = PointerEvent {
+            pointer: PointerId::Mouse,
+            node: hit.node,
+            button: None,
+            position: hit.position,
+            local: hit.local,
+            delta: Vec2 { x: delta_x, y: delta_y }
+        };
+        if let Some(shape) = scene.get_shape_mut(hit.node) {
+            shape.on_scroll(&event);
+        }
+    }
+
+    // A finger only hovers while it is down.
+    pub fn touch(&mut self, scene: &mut SceneGraph, camera: &Camera2D, phase: TouchPhase, id: u64, x: f32, y: f32) {
+        let pointer = PointerId::Touch(id);
+        match phase {
+            TouchPhase::Started => {
+                self.pointer_moved(scene, camera, pointer, x, y);
+                self.pointer_down(scene, camera, pointer, None, x, y);
+            }
+            TouchPhase::Moved => self.pointer_moved(scene, camera, pointer, x, y),
+            TouchPhase::Ended => {
+                self.pointer_up(scene, camera, pointer, x, y);
+                self.leave(scene, camera, pointer, x, y);
+            }
+            TouchPhase::Cancelled => {
+                self.presses.remove(&pointer);
+                self.leave(scene, camera, pointer, x, y);
+            }
+        }
+    }
+
+    fn pointer_moved(&mut self, scene: &mut SceneGraph, camera: &Camera2D, pointer: PointerId, x: f32, y: f32) {
+        let hit = scene.pick(camera, x, y);
+        if hit.map(|hit| hit.node) != self.get_hovered(pointer) {
+            self.leave(scene, camera, pointer, x, y);
+            if let Some(hit) = hit {
+                self.hovered.insert(pointer, hit.node);
+                let event = PointerEvent {
+                    pointer,
+                    node: hit.node,
+                    button: None,
+                    position: hit.position,
+                    local: hit.local,
+                    delta: Vec2 { x: 0., y: 0. }
+                };
+                if let Some(shape) = scene.get_shape_mut(hit.node) {
+                    shape.on_hover_enter(&event);
+                }
+            }
+        }
+
+        let Some(press) = self.presses.get_mut(&pointer) else {
+            return;
+        };
+        if !press.dragging {
+            let (moved_x, moved_y) = (x - press.start.x, y - press.start.y);
+            press.dragging = moved_x * moved_x + moved_y * moved_y >= DRAG_THRESHOLD * DRAG_THRESHOLD;
+        }
+        let Some(node) = press.node.filter(|node| press.dragging && scene.contains(*node)) else {
+            return;
+        };
+        let Some(point) = scene.pointer_hit(node, camera, x, y) else {
+            return;
+        };
+        let event = PointerEvent {
+            pointer,
+            node,
+            button: press.button,
+            position: point.position,
+            local: point.local,
+            delta: Vec2 {
+                x: point.position.x - press.last_position.x,
+                y: point.position.y - press.last_position.y
+            }
+        };
+        press.last_position = point.position;
+        if let Some(shape) = scene.get_shape_mut(node) {
+            shape.on_drag(&event);
+        }
+    }
+
+    fn pointer_down(&mut self, scene: &mut SceneGraph, camera: &Camera2D, pointer: PointerId, button: Option<MouseButton>, x: f32, y: f32) {
+        let hit = scene.pick(camera, x, y);
+        self.presses.insert(pointer, Press {
+            node: hit.map(|hit| hit.node),
+            button,
+            start: Vec2 { x, y },
+            last_position: hit.map_or(Vec2 { x: 0., y: 0. }, |hit| hit.position),
+            dragging: false
+        });
+    }
+
+    fn pointer_up(&mut self, scene: &mut SceneGraph, camera: &Camera2D, pointer: PointerId, x: f32, y: f32) {
+        let Some(press) = self.presses.remove(&pointer) else {
+            return;
+        };
+        if press.dragging {
+            return;
+        }
+        let Some(hit) = scene.pick(camera, x, y).filter(|hit| Some(hit.node) == press.node) else {
+            return;
+        };
+        let event = PointerEvent {
+            pointer,
+            node: hit.node,
+            button: press.button,
+            position: hit.position,
+            local: hit.local,
+            delta: Vec2 { x: 0., y: 0. }
+        };
+        if let Some(shape) = scene.get_shape_mut(hit.node) {
+            shape.on_click(&event);
+        }
+    }
+
+    fn leave(&mut self, scene: &mut SceneGraph, camera: &Camera2D, pointer: PointerId, x: f32, y: f32) {
+        let Some(node) = self.hovered.remove(&pointer).filter(|node| scene.contains(*node)) else {
+            return;
+        };
+        let Some(point) = scene.pointer_hit(node, camera, x, y) else {
+            return;
+        };
+        let event = PointerEvent {
+            pointer,
+            node,
+            button: None,
+            position: point.position,
+            local: point.local,
+            delta: Vec2 { x: 0., y: 0. }
+        };
+        if let Some(shape) = scene.get_shape_mut(node) {
+            shape.on_hover_leave(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+    use crate::backend::software::SoftwareBackend;
+    use crate::shapes::circle::Circle;
+    use crate::shapes::shape::Shape;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    // Circles named a at world (-0.5, 0) and b at (0.5, 0), logging
+    // every callback they get.
+    fn scene(log: &Log) -> SceneGraph {
+        let mut context = SoftwareBackend::new(1, 1);
+        let mut scene = SceneGraph::new();
+        for (name, x) in [("a", -0.5), ("b", 0.5)] {
+            let mut circle = Circle::new(&mut context, x, 0., 0.2, 32, 1., 1., 1.);
+            let handlers = circle.get_pointer_handlers_mut();
+            let logger = |kind: &'static str| -> Option<PointerHandler> {
+                let log = log.clone();
+                Some(Box::new(move |_| log.borrow_mut().push(format!("{} {}", kind, name))))
+            };
+            handlers.on_click = logger("click");
+            handlers.on_hover_enter = logger("enter");
+            handlers.on_hover_leave = logger("leave");
+            handlers.on_drag = logger("drag");
+            scene.add_shape(scene.get_root(), Box::new(circle));
+        }
+        scene
+    }
+
+    fn taken(log: &Log) -> Vec<String> {
+        log.borrow_mut().drain(..).collect()
+    }
+
+    #[test]
+    fn short_moves_click_and_longer_ones_drag() {
+        let (log, camera, mut router) = (Log::default(), Camera2D::new(800., 600.), PointerRouter::new());
+        let mut scene = scene(&log);
+        let a = camera.world_to_screen(-0.5, 0.);
+
+        router.mouse_button_down(&mut scene, &camera, MouseButton::Left, a.x, a.y);
+        router.mouse_motion(&mut scene, &camera, a.x + DRAG_THRESHOLD - 1., a.y);
+        assert!(!router.is_dragging(PointerId::Mouse));
+        router.mouse_button_up(&mut scene, &camera, MouseButton::Left, a.x + DRAG_THRESHOLD - 1., a.y);
+        assert_eq!(taken(&log), vec!["enter a", "click a"]);
+
+        router.mouse_button_down(&mut scene, &camera, MouseButton::Left, a.x, a.y);
+        router.mouse_motion(&mut scene, &camera, a.x, a.y + DRAG_THRESHOLD);
+        assert!(router.is_dragging(PointerId::Mouse));
+        router.mouse_button_up(&mut scene, &camera, MouseButton::Left, a.x, a.y + DRAG_THRESHOLD);
+        assert!(!router.is_dragging(PointerId::Mouse));
+        assert_eq!(taken(&log), vec!["drag a"]);
+    }
+
+    #[test]
+    fn releasing_over_another_shape_is_not_a_click() {
+        let (log, camera, mut router) = (Log::default(), Camera2D::new(800., 600.), PointerRouter::new());
+        let mut scene = scene(&log);
+        let (a, b) = (camera.world_to_screen(-0.5, 0.), camera.world_to_screen(0.5, 0.));
+
+        router.mouse_button_down(&mut scene, &camera, MouseButton::Left, a.x, a.y);
+        router.mouse_button_up(&mut scene, &camera, MouseButton::Left, b.x, b.y);
+        assert!(taken(&log).is_empty());
+    }
+
+    #[test]
+    fn hover_leaves_before_it_enters() {
+        let (log, camera, mut router) = (Log::default(), Camera2D::new(800., 600.), PointerRouter::new());
+        let mut scene = scene(&log);
+        let (a, b) = (camera.world_to_screen(-0.5, 0.), camera.world_to_screen(0.5, 0.));
+
+        router.mouse_motion(&mut scene, &camera, a.x, a.y);
+        router.mouse_motion(&mut scene, &camera, a.x + 1., a.y);
+        router.mouse_motion(&mut scene, &camera, b.x, b.y);
+        assert_eq!(router.get_hovered(PointerId::Mouse), scene.get_children(scene.get_root()).get(1).copied());
+        router.mouse_motion(&mut scene, &camera, 400., 20.);
+        assert_eq!(router.get_hovered(PointerId::Mouse), None);
+        assert_eq!(taken(&log), vec!["enter a", "leave a", "enter b", "leave b"]);
+    }
+
+    #[test]
+    fn cancelled_touches_leave_without_clicking() {
+        let (log, camera, mut router) = (Log::default(), Camera2D::new(800., 600.), PointerRouter::new());
+        let mut scene = scene(&log);
+        let a = camera.world_to_screen(-0.5, 0.);
+
+        router.touch(&mut scene, &camera, TouchPhase::Started, 7, a.x, a.y);
+        router.touch(&mut scene, &camera, TouchPhase::Cancelled, 7, a.x, a.y);
+        assert_eq!(router.get_hovered(PointerId::Touch(7)), None);
+        // The press is gone, a late end finds nothing to click.
+        router.touch(&mut scene, &camera, TouchPhase::Ended, 7, a.x, a.y);
+        assert_eq!(taken(&log), vec!["enter a", "leave a"]);
+
+        router.touch(&mut scene, &camera, TouchPhase::Started, 8, a.x, a.y);
+        router.touch(&mut scene, &camera, TouchPhase::Ended, 8, a.x, a.y);
+        assert_eq!(taken(&log), vec!["enter a", "click a", "leave a"]);
+    }
+}
@@ -0,0 +1,451 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use miniquad::{KeyCode, KeyMods, MouseButton, TouchPhase};
+use crate::formats::json::{self, JsonError, JsonValue};
+use crate::input::action_map::{ActionMap, ActionMapError};
+use crate::input::key_names::KEY_NAMES;
+
+// Everything Stage is told by the window that can change what the
+// simulation does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    KeyDown { key: KeyCode, modifiers: KeyMods, repeat: bool },
+    KeyUp { key: KeyCode, modifiers: KeyMods },
+    Character { character: char, modifiers: KeyMods },
+    MouseMotion { x: f32, y: f32 },
+    MouseWheel { x: f32, y: f32 },
+    MouseButtonDown { button: MouseButton, x: f32, y: f32 },
+    MouseButtonUp { button: MouseButton, x: f32, y: f32 },
+    Touch { phase: TouchPhase, id: u64, x: f32, y: f32 },
+    Resize { width: f32, height: f32 },
+    Minimized
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecordedEvent {
+    // Simulation steps run before it arrived, it is replayed just
+    // before step number step.
+    pub step: u64,
+    // Seconds since recording started, for reading bug reports.
+    pub time: f64,
+    // What the scene was drawn at, so pointer events replay against
+    // the shapes the tester saw.
+    pub interpolation: f32,
+    pub event: InputEvent
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(PathBuf, String),
+    Json(JsonError),
+    // Where in the file and what was wrong.
+    Invalid(String)
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::Io(path, message) => write!(f, "{}: {}", path.display(), message),
+            RecordingError::Json(error) => write!(f, "input recording: {}", error),
+            RecordingError::Invalid(message) => write!(f, "input recording: {}", message)
+        }
+    }
+}
+
+// A session's input tied to simulation steps rather than wall time,
+// so replaying it at the recorded fixed rate reproduces every step.
+// The bindings the session started with are kept too, so a replay
+// does not depend on the input config where it runs.
+//
+// Files look like
+// {
+//   "rate": 120, "window": [800, 600], "steps": 360,
+//   "actions": { "actions": {}, "axes": { "move_x": [["Left", "Right"]] } },
+//   "events": [
+//     { "step": 12, "time": 0.1, "interpolation": 0.4, "type": "key_down",
+//       "key": "Left", "modifiers": ["Shift"], "repeat": false }
+//   ]
+// }
+pub struct InputRecording {
+    // Fixed updates per second the session ran at.
+    rate: f64,
+    window_width: f32,
+    window_height: f32,
+    // Steps the session ran, replays stop after as many.
+    steps: u64,
+    actions: ActionMap,
+    events: Vec<RecordedEvent>
+}
+
+impl InputRecording {
+
+    pub fn new(rate: f64, window_width: f32, window_height: f32, actions: ActionMap) -> InputRecording {
+        InputRecording {
+            rate,
+            window_width,
+            window_height,
+            steps: 0,
+            actions,
+            events: vec![]
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<InputRecording, RecordingError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| RecordingError::Io(path.to_path_buf(), error.to_string()))?;
+        InputRecording::from_json(&text)
+    }
+
+    pub fn from_json(text: &str) -> Result<InputRecording, RecordingError> {
+        let file = json::parse(text).map_err(RecordingError::Json)?;
+
+        let rate = number(&file, "rate", "rate")?;
+        if rate <= 0. {
+            return Err(invalid("rate should be above 0"));
+        }
+        let (window_width, window_height) = match file.get("window").and_then(JsonValue::as_array) {
+            Some([width, height]) => (
+                width.as_f64().ok_or_else(|| invalid("window[0] should be a number"))?,
+                height.as_f64().ok_or_else(|| invalid("window[1] should be a number"))?
+            ),
+            _ => return Err(invalid("window should be [width, height]"))
+        };
+
+        let actions = file
+            .get("actions")
+            .ok_or_else(|| invalid("actions should be an input config object"))
+            .and_then(|actions| ActionMap::from_value(actions).map_err(|error| match error {
+                ActionMapError::Invalid(message) => invalid(&format!("actions: {}", message)),
+                other => invalid(&other.to_string())
+            }))?;
+
+        let mut recording = InputRecording::new(rate, window_width as f32, window_height as f32, actions);
+        let events = file
+            .get("events")
+            .and_then(JsonValue::as_array)
+            .ok_or_else(|| invalid("events should be a list"))?;
+        for (index, event) in events.iter().enumerate() {
+            let location = format!("events[{}]", index);
+            let step = number(event, "step", &location)? as u64;
+            if recording.events.last().is_some_and(|last| step < last.step) {
+                return Err(invalid(&format!("{}: events should be in step order", location)));
+            }
+            let time = number(event, "time", &location)?;
+            let interpolation = number(event, "interpolation", &location)? as f32;
+            recording.push(step, time, interpolation, parse_event(event, &location)?);
+        }
+
+        let steps = number(&file, "steps", "steps")? as u64;
+        recording.set_steps(steps.max(recording.steps));
+        Ok(recording)
+    }
+
+    // Same layout from_json reads.
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            ("rate".to_string(), JsonValue::Number(self.rate)),
+            ("window".to_string(), JsonValue::Array(vec![
+                JsonValue::Number(self.window_width as f64),
+                JsonValue::Number(self.window_height as f64)
+            ])),
+            ("steps".to_string(), JsonValue::Number(self.steps as f64)),
+            ("actions".to_string(), self.actions.to_json()),
+            ("events".to_string(), JsonValue::Array(self.events.iter().map(event_to_json).collect()))
+        ])
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RecordingError> {
+        let path = path.as_ref();
+        std::fs::write(path, format!("{:#}\n", self.to_json()))
+            .map_err(|error| RecordingError::Io(path.to_path_buf(), error.to_string()))
+    }
+
+    pub fn get_rate(&self) -> f64 {
+        self.rate
+    }
+
+    // Seconds each replayed step advances by.
+    pub fn get_step_time(&self) -> f32 {
+        (1. / self.rate) as f32
+    }
+
+    pub fn get_window_size(&self) -> (f32, f32) {
+        (self.window_width, self.window_height)
+    }
+
+    pub fn get_steps(&self) -> u64 {
+        self.steps
+    }
+
+    // What the bindings were when recording started, rebinds made
+    // during the session replay from the recorded presses.
+    pub fn get_actions(&self) -> &ActionMap {
+        &self.actions
+    }
+
+    pub fn get_events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    // Called with the step count when recording stops.
+    pub fn set_steps(&mut self, steps: u64) {
+        self.steps = steps;
+    }
+
+    // Events have to be pushed in the order they arrived. Also
+    // covers the step the event is for.
+    pub fn push(&mut self, step: u64, time: f64, interpolation: f32, event: InputEvent) {
+        self.steps = self.steps.max(step + 1);
+        self.events.push(RecordedEvent {
+            step,
+            time,
+            interpolation,
+            event
+        });
+    }
+
+    // Everything to feed in before running the given step.
+    pub fn get_events_before_step(&self, step: u64) -> &[RecordedEvent] {
+        let start = self.events.partition_point(|event| event.step < step);
+        let end = self.events.partition_point(|event| event.step <= step);
+        &self.events[start..end]
+    }
+}
+
+fn invalid(message: &str) -> RecordingError {
+    RecordingError::Invalid(message.to_string())
+}
+
+fn number(value: &JsonValue, key: &str, location: &str) -> Result<f64, RecordingError> {
+    value
+        .get(key)
+        .and_then(JsonValue::as_f64)
+        .ok_or_else(|| invalid(&format!("{}: {} should be a number", location, key)))
+}
+
+fn string<'a>(value: &'a JsonValue, key: &str, location: &str) -> Result<&'a str, RecordingError> {
+    value
+        .get(key)
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| invalid(&format!("{}: {} should be a string", location, key)))
+}
+
+fn event_to_json(recorded: &RecordedEvent) -> JsonValue {
+    let number = |value: f32| JsonValue::Number(value as f64);
+    let string = |value: &str| JsonValue::String(value.to_string());
+
+    let mut members = vec![
+        ("step".to_string(), JsonValue::Number(recorded.step as f64)),
+        ("time".to_string(), JsonValue::Number(recorded.time)),
+        ("interpolation".to_string(), number(recorded.interpolation))
+    ];
+    let (kind, fields) = match recorded.event {
+        InputEvent::KeyDown { key, modifiers, repeat } => ("key_down", vec![
+            ("key", string(&format!("{:?}", key))),
+            ("modifiers", modifiers_to_json(modifiers)),
+            ("repeat", JsonValue::Bool(repeat))
+        ]),
+        InputEvent::KeyUp { key, modifiers } => ("key_up", vec![
+            ("key", string(&format!("{:?}", key))),
+            ("modifiers", modifiers_to_json(modifiers))
+        ]),
+        InputEvent::Character { character, modifiers } => ("character", vec![
+            ("character", string(&character.to_string())),
+            ("modifiers", modifiers_to_json(modifiers))
+        ]),
+        InputEvent::MouseMotion { x, y } => ("mouse_motion", vec![("x", number(x)), ("y", number(y))]),
+        InputEvent::MouseWheel { x, y } => ("mouse_wheel", vec![("x", number(x)), ("y", number(y))]),
+        InputEvent::MouseButtonDown { button, x, y } => ("mouse_button_down", vec![
+            ("button", string(&format!("{:?}", button))),
+            ("x", number(x)),
+            ("y", number(y))
+        ]),
+        InputEvent::MouseButtonUp { button, x, y } => ("mouse_button_up", vec![
+            ("button", string(&format!("{:?}", button))),
+            ("x", number(x)),
+            ("y", number(y))
+        ]),
+        InputEvent::Touch { phase, id, x, y } => ("touch", vec![
+            ("phase", string(&format!("{:?}", phase))),
+            ("id", JsonValue::Number(id as f64)),
+            ("x", number(x)),
+            ("y", number(y))
+        ]),
+        InputEvent::Resize { width, height } => ("resize", vec![("width", number(width)), ("height", number(height))]),
+        InputEvent::Minimized => ("minimized", vec![])
+    };
+    members.push(("type".to_string(), string(kind)));
+    members.extend(fields.into_iter().map(|(name, value)| (name.to_string(), value)));
+    JsonValue::Object(members)
+}
+
+fn parse_event(value: &JsonValue, location: &str) -> Result<InputEvent, RecordingError> {
+    let float = |key: &str| number(value, key, location).map(|number| number as f32);
+
+    let event = match string(value, "type", location)? {
+        "key_down" => InputEvent::KeyDown {
+            key: parse_key(value, location)?,
+            modifiers: parse_modifiers(value, location)?,
+            repeat: value
+                .get("repeat")
+                .and_then(JsonValue::as_bool)
+                .ok_or_else(|| invalid(&format!("{}: repeat should be true or false", location)))?
+        },
+        "key_up" => InputEvent::KeyUp {
+            key: parse_key(value, location)?,
+            modifiers: parse_modifiers(value, location)?
+        },
+        "character" => {
+            let text = string(value, "character", location)?;
+            let mut characters = text.chars();
+            match (characters.next(), characters.next()) {
+                (Some(character), None) => InputEvent::Character {
+                    character,
+                    modifiers: parse_modifiers(value, location)?
+                },
+                _ => return Err(invalid(&format!("{}: character should be a single character", location)))
+            }
+        }
+        "mouse_motion" => InputEvent::MouseMotion { x: float("x")?, y: float("y")? },
+        "mouse_wheel" => InputEvent::MouseWheel { x: float("x")?, y: float("y")? },
+        "mouse_button_down" => InputEvent::MouseButtonDown {
+            button: parse_button(value, location)?,
+            x: float("x")?,
+            y: float("y")?
+        },
+        "mouse_button_up" => InputEvent::MouseButtonUp {
+            button: parse_button(value, location)?,
+            x: float("x")?,
+            y: float("y")?
+        },
+        "touch" => {
+            let phase = match string(value, "phase", location)? {
+                "Started" => TouchPhase::Started,
+                "Moved" => TouchPhase::Moved,
+                "Ended" => TouchPhase::Ended,
+                "Cancelled" => TouchPhase::Cancelled,
+                other => return Err(invalid(&format!("{}: unknown touch phase '{}'", location, other)))
+            };
+            InputEvent::Touch {
+                phase,
+                id: number(value, "id", location)? as u64,
+                x: float("x")?,
+                y: float("y")?
+            }
+        }
+        "resize" => InputEvent::Resize { width: float("width")?, height: float("height")? },
+        "minimized" => InputEvent::Minimized,
+        other => return Err(invalid(&format!("{}: unknown event type '{}'", location, other)))
+    };
+    Ok(event)
+}
+
+fn parse_key(value: &JsonValue, location: &str) -> Result<KeyCode, RecordingError> {
+    let name = string(value, "key", location)?;
+    KEY_NAMES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, key)| *key)
+        .ok_or_else(|| invalid(&format!("{}: unknown key '{}'", location, name)))
+}
+
+fn parse_button(value: &JsonValue, location: &str) -> Result<MouseButton, RecordingError> {
+    match string(value, "button", location)? {
+        "Left" => Ok(MouseButton::Left),
+        "Right" => Ok(MouseButton::Right),
+        "Middle" => Ok(MouseButton::Middle),
+        "Unknown" => Ok(MouseButton::Unknown),
+        other => Err(invalid(&format!("{}: unknown mouse button '{}'", location, other)))
+    }
+}
+
+const MODIFIER_NAMES: [&str; 4] = ["Shift", "Ctrl", "Alt", "Logo"];
+
+fn modifiers_to_json(modifiers: KeyMods) -> JsonValue {
+    let held = [modifiers.shift, modifiers.ctrl, modifiers.alt, modifiers.logo];
+    JsonValue::Array(MODIFIER_NAMES
+        .iter()
+        .zip(held)
+        .filter(|(_, held)| *held)
+        .map(|(name, _)| JsonValue::String(name.to_string()))
+        .collect())
+}
+
+fn parse_modifiers(value: &JsonValue, location: &str) -> Result<KeyMods, RecordingError> {
+    let names = value
+        .get("modifiers")
+        .and_then(JsonValue::as_array)
+        .ok_or_else(|| invalid(&format!("{}: modifiers should be a list", location)))?;
+
+    let mut modifiers = KeyMods::default();
+    for name in names {
+        match name.as_str() {
+            Some("Shift") => modifiers.shift = true,
+            Some("Ctrl") => modifiers.ctrl = true,
+            Some("Alt") => modifiers.alt = true,
+            Some("Logo") => modifiers.logo = true,
+            _ => return Err(invalid(&format!("{}: unknown modifier {}", location, name)))
+        }
+    }
+    Ok(modifiers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::action_map::InputBinding;
+
+    fn recording() -> InputRecording {
+        let mut actions = ActionMap::new();
+        actions.bind_action("jump", InputBinding::parse("Shift+Space").unwrap());
+        actions.bind_axis("move_x", InputBinding::key(KeyCode::Left), InputBinding::key(KeyCode::Right));
+
+        let shift = KeyMods { shift: true, ..KeyMods::default() };
+        let mut recording = InputRecording::new(120., 800., 600., actions);
+        recording.push(0, 0., 1., InputEvent::KeyDown { key: KeyCode::Left, modifiers: shift, repeat: false });
+        recording.push(3, 0.025, 0.5, InputEvent::Character { character: 'é', modifiers: shift });
+        recording.push(3, 0.026, 0.5, InputEvent::MouseButtonDown { button: MouseButton::Right, x: 10.5, y: 20. });
+        recording.push(7, 0.06, 0.25, InputEvent::Touch { phase: TouchPhase::Moved, id: 2, x: 1., y: 2. });
+        recording.push(9, 0.075, 0., InputEvent::Resize { width: 640., height: 480. });
+        recording.push(9, 0.08, 0., InputEvent::Minimized);
+        recording.set_steps(30);
+        recording
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let recording = recording();
+        let loaded = InputRecording::from_json(&format!("{:#}", recording.to_json())).unwrap();
+
+        assert_eq!(loaded.get_rate(), 120.);
+        assert_eq!(loaded.get_window_size(), (800., 600.));
+        assert_eq!(loaded.get_steps(), 30);
+        assert_eq!(loaded.get_events(), recording.get_events());
+        assert_eq!(loaded.get_actions().to_json(), recording.get_actions().to_json());
+        assert_eq!(loaded.to_json(), recording.to_json());
+    }
+
+    #[test]
+    fn groups_events_by_step() {
+        let recording = recording();
+        assert_eq!(recording.get_events_before_step(3).len(), 2);
+        assert_eq!(recording.get_events_before_step(4).len(), 0);
+    }
+
+    #[test]
+    fn rejects_bad_recordings() {
+        let message = |text: &str| match InputRecording::from_json(text) {
+            Err(RecordingError::Invalid(message)) => message,
+            other => panic!("expected Invalid, got {:?}", other.map(|_| ()))
+        };
+        assert_eq!(message(r#"{ "rate": 120, "window": [8, 6], "steps": 1, "events": [] }"#),
+                   "actions should be an input config object");
+        assert_eq!(message(r#"{ "rate": 120, "window": [8, 6], "steps": 1, "actions": { "actions": { "jump": ["Nope"] } }, "events": [] }"#),
+                   "actions: actions.jump[0]: unknown binding 'Nope'");
+        assert_eq!(message(r#"{ "rate": 120, "window": [8, 6], "steps": 1, "actions": {}, "events": [
+            { "step": 2, "time": 0, "interpolation": 1, "type": "minimized" },
+            { "step": 1, "time": 0, "interpolation": 1, "type": "minimized" }
+        ] }"#), "events[1]: events should be in step order");
+    }
+}
@@ -0,0 +1,8 @@
+pub mod backend;
+pub mod formats;
+pub mod input;
+pub mod replay;
+pub mod scene;
+pub mod shapes;
+pub mod snapshot;
+pub mod timing;
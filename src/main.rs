@@ -1,113 +1,337 @@
-mod shapes;
-
-use std::any::Any;
-use std::ops::Deref;
+use std::path::{Path, PathBuf};
 use miniquad::*;
 use miniquad::date::now;
-use crate::shapes::background::Background;
-use crate::shapes::shape::Shape;
-use crate::shapes::square::Square;
-use crate::shapes::triangle::{Triangle};
+use quad_shapes::backend::drawing_context::DrawingContext;
+use quad_shapes::backend::miniquad_backend::MiniquadBackend;
+use quad_shapes::backend::profiling_context::ProfilingContext;
+use quad_shapes::input::action_map::{ActionMap, InputBinding};
+use quad_shapes::input::input_state::InputState;
+use quad_shapes::input::recording::{InputEvent, InputRecording};
+use quad_shapes::scene::camera::Camera2D;
+use quad_shapes::scene::scene_file;
+use quad_shapes::scene::scene_graph::SceneGraph;
+use quad_shapes::scene::simulation::Simulation;
+use quad_shapes::shapes::draw_parameters::DrawParameters;
+use quad_shapes::shapes::frame_graph::FrameGraph;
+use quad_shapes::shapes::shape::Shape;
+use quad_shapes::shapes::triangle;
+use quad_shapes::timing::frame_pacer::{FramePacer, PacingMode};
+use quad_shapes::timing::frame_stats::{FrameStats, DEFAULT_WINDOW};
+use quad_shapes::timing::simulation_clock::{SimulationClock, TimestepMode};
+use quad_shapes::{replay, snapshot};
 
 struct Stage<> {
-    context: Box<dyn RenderingBackend>,
-    shapes: Vec<Box<dyn Shape>>,
-    time_since_last_frame: f64,
-    fps: u8
+    context: ProfilingContext,
+    simulation: Simulation,
+    clock: SimulationClock,
+    pacer: FramePacer,
+    stats: FrameStats,
+    time_of_last_frame: Option<f64>,
+    // F3 shows and hides it.
+    frame_graph: FrameGraph,
+    show_frame_graph: bool,
+    // Set by --record, saved when the window closes.
+    recording: Option<(PathBuf, InputRecording)>,
+    time_recording_started: f64
 }
 
-impl<'b> Stage<> {
+// Updates per second, drawing interpolates in between.
+const SIMULATION_RATE: f64 = 120.;
+// Unless `--timestep <mode>` picks another, see TimestepMode::parse.
+// Recordings replay at a fixed rate, so --record always uses this.
+const DEFAULT_TIMESTEP_MODE: TimestepMode = TimestepMode::Fixed { rate: SIMULATION_RATE };
+// Unless `--pacing <mode>` picks another, see PacingMode::parse.
+// Started without vsync, F4 cycles through PACING_MODES.
+const DEFAULT_PACING_MODE: PacingMode = PacingMode::VSync;
+const PACING_MODES: [PacingMode; 3] = [
+    PacingMode::Capped { fps: 60. },
+    PacingMode::Uncapped,
+    PacingMode::Adaptive { fps: 60. }
+];
+// Optional, default_actions is used when it is missing. Looked for
+// beside the executable unless `--input <file>` names another.
+const INPUT_CONFIG: &str = "input.json";
+// What the window shows, see scene_file for the format. Looked for
+// beside the executable, then in the working directory, unless
+// `--scene <file>` names another.
+const SCENE_FILE: &str = "scenes/stage.json";
 
-    pub fn new<>() -> Stage<> {
-        let mut context: Box<dyn RenderingBackend> = window::new_rendering_backend();
+impl Stage<> {
 
-        let background = Box::new(Background::new(&mut context, 1.000, 0.937, 0.835));
-        let green_triangle = Box::new(Triangle::new(&mut context, -0.25f32, -0.2f32, 0.05, 0.05, 0.0, 1f32, 0.0));
-        let green_square = Box::new(Square::new(&mut context, -0.4f32, -0.2f32, 0.05, 0.05, 0.0, 1f32, 0.0));
+    pub fn new<>(scene_file: &Path,
+                 actions: ActionMap,
+                 timestep_mode: TimestepMode,
+                 pacing_mode: PacingMode,
+                 record_to: Option<PathBuf>) -> Stage<> {
+        let mut context = ProfilingContext::new(
+            Box::new(MiniquadBackend::new(window::new_rendering_backend())));
+        // Window starts at the conf size, which doubles as the
+        // design resolution shapes are laid out for.
+        let (width, height) = window::screen_size();
+        let recording = record_to.map(|path| (path, InputRecording::new(SIMULATION_RATE, width, height, actions.clone())));
 
         Stage {
-            // Order is important for Z drawing.
-            shapes: vec![
-                background,
-                green_triangle,
-                green_square,
-            ],
+            simulation: Simulation::new(
+                build_scene(&mut context, scene_file),
+                Camera2D::new(width, height),
+                InputState::new(actions)
+            ),
+            frame_graph: FrameGraph::new(&mut context, DEFAULT_WINDOW, -0.7, -0.85, 0.28, 0.13),
             context,
-            time_since_last_frame: 0f64,
-            fps: 60u8
+            clock: SimulationClock::new(timestep_mode),
+            pacer: FramePacer::new(pacing_mode),
+            stats: FrameStats::new(DEFAULT_WINDOW),
+            time_of_last_frame: None,
+            show_frame_graph: false,
+            recording,
+            time_recording_started: now()
         }
     }
 
-    fn get_frame_time(&self) -> f64 {
-        now() - self.time_since_last_frame
+    // Every window event that reaches the simulation comes through
+    // here, so a recording holds everything a replay needs. True
+    // when a press was taken by rebinding.
+    fn handle_event(&mut self, event: InputEvent) -> bool {
+        if let Some((_, recording)) = self.recording.as_mut() {
+            recording.push(
+                self.simulation.get_step(),
+                now() - self.time_recording_started,
+                self.simulation.get_scene().get_interpolation(),
+                event
+            );
+        }
+        self.simulation.handle_event(&event)
     }
 
-    fn get_minimum_frame_time(&self) -> f64 {
-        1. / self.fps as f64
+    // Rolling frame times and draw counts, for benchmarks.
+    pub fn get_frame_stats(&self) -> &FrameStats {
+        &self.stats
     }
 
-    fn is_frame_time_right(&self) -> bool {
-        let frame_time = self.get_frame_time();
-        frame_time > self.get_minimum_frame_time()
+    // False for switches to or from VSync, see FramePacer::set_mode.
+    pub fn set_pacing_mode(&mut self, mode: PacingMode) -> bool {
+        self.pacer.set_mode(mode)
     }
 
-    fn sleep_until_next_frame(&self) {
-        let time_to_sleep = (self.get_minimum_frame_time() - self.get_frame_time()) * 1000.;
-        // Reportedly problems on web assembly.
-        // Need another impl for handling webpage frame limiting.
-        std::thread::sleep(std::time::Duration::from_millis(time_to_sleep as u64));
+    fn next_pacing_mode(&mut self) {
+        let current = PACING_MODES.iter().position(|mode| *mode == self.pacer.get_mode());
+        let mode = PACING_MODES[current.map_or(0, |index| (index + 1) % PACING_MODES.len())];
+        if self.set_pacing_mode(mode) {
+            println!("pacing {:?}", mode);
+        } else {
+            println!("pacing stays {:?}, restart with --pacing to change it", self.pacer.get_mode());
+        }
+    }
+
+    fn save_recording(&mut self) {
+        let Some((path, mut recording)) = self.recording.take() else {
+            return;
+        };
+        recording.set_steps(recording.get_steps().max(self.simulation.get_step()));
+        match recording.save(&path) {
+            Ok(()) => println!("Recorded {} steps to {}", recording.get_steps(), path.display()),
+            Err(error) => println!("{}", error)
+        }
     }
 }
 
 impl EventHandler for Stage<> {
     fn update(&mut self) {
-        if self.is_frame_time_right() {
-
-        } else {
-            self.sleep_until_next_frame();
+        let steps = self.clock.tick(now());
+        for _ in 0..steps.count {
+            self.simulation.step(steps.dt);
         }
     }
 
     fn draw(&mut self) {
-        if self.is_frame_time_right() {
-            // Begin opengl pass.
-            self.context.begin_default_pass(Default::default());
-
-            // Draw each opengl object.
-            // Each has its own impl.
-            &self.shapes.iter_mut().for_each(|mut a| a.draw(&mut self.context, true));
-
-            // End opengl pass.
-            self.context.end_render_pass();
-            self.context.commit_frame();
-            self.time_since_last_frame = now();
-            // println!("Frame drew!!");
-        } else {
-            self.sleep_until_next_frame();
+        let time = now();
+        if !self.pacer.begin_frame(time) {
+            return;
+        }
+        if let Some(time_of_last_frame) = self.time_of_last_frame {
+            self.stats.record_frame(time - time_of_last_frame, self.context.take_counters());
+        }
+        self.time_of_last_frame = Some(time);
+
+        // Begin opengl pass.
+        self.context.begin_default_pass(Default::default());
+
+        // Draw each opengl object, parents before children.
+        // Each has its own impl.
+        self.simulation.draw(&mut self.context, self.clock.get_alpha());
+
+        if self.show_frame_graph {
+            if let Some(budget) = self.pacer.get_target_frame_time() {
+                self.frame_graph.set_budget(budget);
+            }
+            self.frame_graph.set_frame_times(&self.stats);
+            self.frame_graph.draw(&mut self.context, &DrawParameters::screen(), true);
+        }
+
+        // End opengl pass.
+        self.context.end_render_pass();
+        self.context.commit_frame();
+        self.pacer.end_frame(now());
+    }
+
+    fn resize_event(&mut self, width: f32, height: f32) {
+        self.handle_event(InputEvent::Resize { width, height });
+    }
+
+    fn mouse_motion_event(&mut self, x: f32, y: f32) {
+        self.handle_event(InputEvent::MouseMotion { x, y });
+    }
+
+    fn mouse_wheel_event(&mut self, x: f32, y: f32) {
+        self.handle_event(InputEvent::MouseWheel { x, y });
+    }
+
+    fn mouse_button_down_event(&mut self, button: MouseButton, x: f32, y: f32) {
+        self.handle_event(InputEvent::MouseButtonDown { button, x, y });
+    }
+
+    fn mouse_button_up_event(&mut self, button: MouseButton, x: f32, y: f32) {
+        self.handle_event(InputEvent::MouseButtonUp { button, x, y });
+    }
+
+    // Replaces miniquad's default, which turns touches into mouse
+    // events, so several fingers can be tracked.
+    fn touch_event(&mut self, phase: TouchPhase, id: u64, x: f32, y: f32) {
+        self.handle_event(InputEvent::Touch { phase, id, x, y });
+    }
+
+    fn key_down_event(&mut self, keycode: KeyCode, keymods: KeyMods, repeat: bool) {
+        if self.handle_event(InputEvent::KeyDown { key: keycode, modifiers: keymods, repeat }) {
+            return;
+        }
+
+        if keycode == KeyCode::F3 && !repeat {
+            self.show_frame_graph = !self.show_frame_graph;
+            println!("{}", self.get_frame_stats().get_summary());
+        }
+        if keycode == KeyCode::F4 && !repeat {
+            self.next_pacing_mode();
         }
     }
 
-    fn key_down_event(&mut self, _keycode: KeyCode, _keymods: KeyMods, _repeat: bool) {
-        println!("Key down {:?}", _keycode);
+    fn key_up_event(&mut self, keycode: KeyCode, keymods: KeyMods) {
+        self.handle_event(InputEvent::KeyUp { key: keycode, modifiers: keymods });
+    }
 
+    fn char_event(&mut self, character: char, keymods: KeyMods, _repeat: bool) {
+        self.handle_event(InputEvent::Character { character, modifiers: keymods });
+    }
 
-        self.shapes.iter_mut().for_each(|a| {
-            a.input_down(_keycode);
-        })
+    fn window_minimized_event(&mut self) {
+        self.handle_event(InputEvent::Minimized);
     }
 
-    fn key_up_event(&mut self, _keycode: KeyCode, _keymods: KeyMods) {
-        println!("Key up {:?}", _keycode);
+    fn quit_requested_event(&mut self) {
+        self.save_recording();
+    }
+}
 
-        self.shapes.iter_mut().for_each(|a| {
-            a.input_up(_keycode);
-        })
+pub fn default_actions() -> ActionMap {
+    let mut actions = ActionMap::new();
+    actions.bind_axis(triangle::MOVE_X, InputBinding::key(KeyCode::Left), InputBinding::key(KeyCode::Right));
+    actions.bind_axis(triangle::MOVE_X, InputBinding::key(KeyCode::A), InputBinding::key(KeyCode::D));
+    actions.bind_axis(triangle::MOVE_Y, InputBinding::key(KeyCode::Down), InputBinding::key(KeyCode::Up));
+    actions.bind_axis(triangle::MOVE_Y, InputBinding::key(KeyCode::S), InputBinding::key(KeyCode::W));
+    actions
+}
+
+fn beside_executable(file: &str) -> Option<PathBuf> {
+    std::env::current_exe()
+        .ok()
+        .and_then(|executable| executable.parent().map(|directory| directory.join(file)))
+}
+
+// Beside the executable rather than in the working directory, so
+// it is found however the game was started.
+pub fn default_input_config() -> PathBuf {
+    beside_executable(INPUT_CONFIG).unwrap_or_else(|| PathBuf::from(INPUT_CONFIG))
+}
+
+// The working directory covers `cargo run` from the crate, where
+// the executable is off in target/.
+pub fn default_scene_file() -> PathBuf {
+    beside_executable(SCENE_FILE)
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(SCENE_FILE))
+}
+
+pub fn load_actions(path: &Path) -> ActionMap {
+    if !path.exists() {
+        return default_actions();
     }
+    ActionMap::load(path).unwrap_or_else(|error| {
+        println!("{}, using the default bindings", error);
+        default_actions()
+    })
+}
+
+// Exits when the file cannot be loaded, there is nothing to show
+// without it.
+pub fn build_scene(context: &mut dyn DrawingContext, path: &Path) -> SceneGraph {
+    // Background keeps itself on BACKGROUND_LAYER, the rest draw
+    // in the order given unless their layer or z_index is changed.
+    scene_file::load(context, path).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    })
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let argument_after = |flag: &str| args
+        .iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1));
+    let path_after = |flag: &str| argument_after(flag).map(PathBuf::from);
+    let scene_file = path_after("--scene").unwrap_or_else(default_scene_file);
+
+    // Headless golden image check for GPU-less CI boxes, run from
+    // the crate. `--snapshot --update` re-records the stored images.
+    if args.iter().any(|arg| arg == "--snapshot") {
+        let update = args.iter().any(|arg| arg == "--update");
+        let passed = snapshot::run_all(Path::new(snapshot::SNAPSHOT_DIRECTORY), &scene_file, update);
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    // `--record <file>` saves the session's input when the window
+    // closes, `--replay <file>` reruns it headlessly and writes the
+    // last frame beside the file. `--scene <file>` picks what is
+    // shown, `--input <file>` the input config, `--timestep <mode>`
+    // how the simulation steps and `--pacing <mode>` how frames are
+    // paced.
+    let actions = load_actions(&path_after("--input").unwrap_or_else(default_input_config));
+    if let Some(path) = path_after("--replay") {
+        let passed = replay::run(&path, &|context| build_scene(context, &scene_file));
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+    let record_to = path_after("--record");
+    let timestep_mode = match argument_after("--timestep") {
+        Some(_) if record_to.is_some() => {
+            println!("--record replays at a fixed rate, using {:?}", DEFAULT_TIMESTEP_MODE);
+            DEFAULT_TIMESTEP_MODE
+        }
+        Some(text) => TimestepMode::parse(text).unwrap_or_else(|| {
+            println!("unknown timestep mode '{}', using {:?}", text, DEFAULT_TIMESTEP_MODE);
+            DEFAULT_TIMESTEP_MODE
+        }),
+        None => DEFAULT_TIMESTEP_MODE
+    };
+    let pacing_mode = match argument_after("--pacing") {
+        Some(text) => PacingMode::parse(text).unwrap_or_else(|| {
+            println!("unknown pacing mode '{}', using {:?}", text, DEFAULT_PACING_MODE);
+            DEFAULT_PACING_MODE
+        }),
+        None => DEFAULT_PACING_MODE
+    };
+
     let mut conf = conf::Conf::default();
     conf.platform.apple_gfx_api = conf::AppleGfxApi::OpenGl;
-    start(conf, move || Box::new(Stage::new()));
+    conf.platform.swap_interval = Some(pacing_mode.swap_interval());
+    start(conf, move || Box::new(Stage::new(&scene_file, actions, timestep_mode, pacing_mode, record_to)));
 }
 
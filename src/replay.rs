@@ -0,0 +1,56 @@
+use std::path::Path;
+use miniquad::PassAction;
+use crate::backend::drawing_context::DrawingContext;
+use crate::backend::framebuffer::Framebuffer;
+use crate::backend::software::SoftwareBackend;
+use crate::input::input_state::InputState;
+use crate::input::recording::InputRecording;
+use crate::scene::camera::Camera2D;
+use crate::scene::scene_graph::SceneGraph;
+use crate::scene::simulation::Simulation;
+use crate::snapshot;
+
+// Runs a recording against a freshly built scene on the software
+// backend with the bindings it was made with, no window needed.
+// Only the last step is drawn, at its latest pose, so the same
+// recording always gives the same frame. It is the size the window
+// started at, later resizes only move the camera.
+pub fn render_last_frame(recording: &InputRecording, build: &dyn Fn(&mut dyn DrawingContext) -> SceneGraph) -> Framebuffer {
+    let (width, height) = recording.get_window_size();
+    let mut context = SoftwareBackend::new(width as u32, height as u32);
+    let scene = build(&mut context);
+    let input = InputState::new(recording.get_actions().clone());
+    let mut simulation = Simulation::new(scene, Camera2D::new(width, height), input);
+
+    while simulation.replay_step(recording) {}
+
+    context.begin_default_pass(PassAction::default());
+    simulation.draw(&mut context, 1.);
+    context.end_render_pass();
+    context.commit_frame();
+    context.into_framebuffer()
+}
+
+// Replays the file and writes the last frame beside it as a png.
+pub fn run(path: &Path, build: &dyn Fn(&mut dyn DrawingContext) -> SceneGraph) -> bool {
+    let recording = match InputRecording::load(path) {
+        Ok(recording) => recording,
+        Err(error) => {
+            println!("replay ... FAILED: {}", error);
+            return false;
+        }
+    };
+
+    let frame = render_last_frame(&recording, build);
+    let output = path.with_extension("png");
+    match snapshot::save_png(&output, &frame) {
+        Ok(()) => {
+            println!("replay ... ok, {} steps, last frame in {}", recording.get_steps(), output.display());
+            true
+        }
+        Err(error) => {
+            println!("replay ... FAILED: {}", error);
+            false
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod camera;
+pub mod scene_file;
+pub mod scene_graph;
+pub mod simulation;
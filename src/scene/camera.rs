@@ -0,0 +1,319 @@
+use crate::shapes::matrix::Mat4;
+use crate::shapes::shared_c_resources::Vec2;
+
+// How the design resolution is fitted into the window. Except for
+// Stretch, world units keep square pixels: y spans -1..1 over the
+// design height and x spans the design aspect ratio either side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScalingPolicy {
+    // World -1..1 on both axes fills the window, distorting shapes
+    // whenever the window is not square.
+    Stretch,
+    // Largest centred area with the design aspect, bars elsewhere.
+    Letterbox,
+    // Design width always fills the window, more or less of the
+    // world is visible vertically.
+    FitWidth,
+    // Design height always fills the window, more or less of the
+    // world is visible horizontally.
+    FitHeight,
+    // Letterbox restricted to whole multiples of the design size
+    // so every design pixel covers the same number of window pixels.
+    PixelPerfect
+}
+
+// Window pixels, origin top left.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32
+}
+
+// Orthographic 2D camera. Screen coordinates are window pixels,
+// origin top left, y down.
+pub struct Camera2D {
+    position: Vec2,
+    zoom: f32,
+    rotation: f32,
+    design_width: f32,
+    design_height: f32,
+    window_width: f32,
+    window_height: f32,
+    scaling: ScalingPolicy
+}
+
+impl Camera2D {
+
+    // Starts with the window the same size as the design.
+    pub fn new(design_width: f32, design_height: f32) -> Camera2D {
+        Camera2D {
+            position: Vec2 { x: 0., y: 0. },
+            zoom: 1.,
+            rotation: 0.,
+            design_width,
+            design_height,
+            window_width: design_width,
+            window_height: design_height,
+            scaling: ScalingPolicy::Letterbox
+        }
+    }
+
+    pub fn get_position(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn get_zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn get_scaling(&self) -> ScalingPolicy {
+        self.scaling
+    }
+
+    pub fn get_window_size(&self) -> (f32, f32) {
+        (self.window_width, self.window_height)
+    }
+
+    // World point shown at the centre of the viewport.
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.position = Vec2 { x, y };
+    }
+
+    // Above 1 magnifies, clamped so the view never collapses.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(f32::EPSILON);
+    }
+
+    // Radians, counter-clockwise. Turns the view, so the world
+    // appears to rotate clockwise.
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+
+    pub fn set_scaling(&mut self, scaling: ScalingPolicy) {
+        self.scaling = scaling;
+    }
+
+    // Called from resize_event.
+    pub fn set_window_size(&mut self, window_width: f32, window_height: f32) {
+        self.window_width = window_width.max(1.);
+        self.window_height = window_height.max(1.);
+    }
+
+    // Moves along the screen axes, so panning right always scrolls
+    // the view right whatever the rotation.
+    pub fn pan(&mut self, x: f32, y: f32) {
+        let (sin, cos) = self.rotation.sin_cos();
+        self.position.x += (x * cos - y * sin) / self.zoom;
+        self.position.y += (x * sin + y * cos) / self.zoom;
+    }
+
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.set_zoom(self.zoom * factor);
+    }
+
+    // Zooms while keeping the world point under the cursor fixed.
+    pub fn zoom_at(&mut self, factor: f32, screen_x: f32, screen_y: f32) {
+        let before = self.screen_to_world(screen_x, screen_y);
+        self.zoom_by(factor);
+        let after = self.screen_to_world(screen_x, screen_y);
+        self.position.x += before.x - after.x;
+        self.position.y += before.y - after.y;
+    }
+
+    pub fn rotate(&mut self, rotation: f32) {
+        self.rotation += rotation;
+    }
+
+    // Part of the window drawn into, everything else stays the
+    // pass clear colour.
+    pub fn get_viewport(&self) -> Viewport {
+        let (width, height) = match self.scaling {
+            ScalingPolicy::Letterbox => {
+                let scale = self.fit_scale();
+                (self.design_width * scale, self.design_height * scale)
+            }
+            ScalingPolicy::PixelPerfect => {
+                let scale = self.fit_scale().floor().max(1.);
+                (self.design_width * scale, self.design_height * scale)
+            }
+            ScalingPolicy::Stretch | ScalingPolicy::FitWidth | ScalingPolicy::FitHeight =>
+                (self.window_width, self.window_height)
+        };
+
+        Viewport {
+            x: ((self.window_width - width) * 0.5).floor(),
+            y: ((self.window_height - height) * 0.5).floor(),
+            width,
+            height
+        }
+    }
+
+    // Maps world units onto the viewport's -1..1, before zoom,
+    // rotation and position are applied.
+    pub fn projection(&self) -> Mat4 {
+        let aspect = self.design_width / self.design_height;
+        let window_aspect = self.window_width / self.window_height;
+
+        match self.scaling {
+            ScalingPolicy::Stretch => Mat4::identity(),
+            ScalingPolicy::Letterbox | ScalingPolicy::PixelPerfect => Mat4::scale(1. / aspect, 1.),
+            ScalingPolicy::FitWidth => Mat4::scale(1. / aspect, window_aspect / aspect),
+            ScalingPolicy::FitHeight => Mat4::scale(1. / window_aspect, 1.)
+        }
+    }
+
+    // projection * scale(zoom) * rotate(-rotation) * translate(-position)
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection()
+            .multiply(&Mat4::scale(self.zoom, self.zoom))
+            .multiply(&Mat4::rotation(-self.rotation))
+            .multiply(&Mat4::translation(-self.position.x, -self.position.y))
+    }
+
+    pub fn world_to_screen(&self, x: f32, y: f32) -> Vec2 {
+        let viewport = self.get_viewport();
+        let (ndc_x, ndc_y) = self.view_projection().transform_point(x, y);
+        Vec2 {
+            x: viewport.x + (ndc_x + 1.) * 0.5 * viewport.width,
+            y: viewport.y + (1. - ndc_y) * 0.5 * viewport.height
+        }
+    }
+
+    // Where shapes locked to the camera are laid out, -1..1 across
+    // the viewport.
+    pub fn screen_to_clip(&self, x: f32, y: f32) -> Vec2 {
+        let viewport = self.get_viewport();
+        Vec2 {
+            x: (x - viewport.x) / viewport.width * 2. - 1.,
+            y: 1. - (y - viewport.y) / viewport.height * 2.
+        }
+    }
+
+    pub fn screen_to_world(&self, x: f32, y: f32) -> Vec2 {
+        let Vec2 { x: ndc_x, y: ndc_y } = self.screen_to_clip(x, y);
+        // Zoom is clamped above zero, the matrix always inverts.
+        let (world_x, world_y) = self.view_projection()
+            .inverse()
+            .unwrap_or_else(Mat4::identity)
+            .transform_point(ndc_x, ndc_y);
+        Vec2 { x: world_x, y: world_y }
+    }
+
+    fn fit_scale(&self) -> f32 {
+        (self.window_width / self.design_width).min(self.window_height / self.design_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!((actual.x - expected.x).abs() < 1e-3 && (actual.y - expected.y).abs() < 1e-3,
+                "({}, {}) != ({}, {})", actual.x, actual.y, expected.x, expected.y);
+    }
+
+    // Panned, zoomed and turned, in a window wider than the design.
+    fn moved_camera() -> Camera2D {
+        let mut camera = Camera2D::new(800., 600.);
+        camera.set_window_size(1000., 600.);
+        camera.set_position(0.4, -0.3);
+        camera.set_zoom(2.5);
+        camera.set_rotation(0.7);
+        camera
+    }
+
+    #[test]
+    fn world_and_screen_round_trip() {
+        let camera = moved_camera();
+        for (x, y) in [(0., 0.), (0.4, -0.3), (-1.2, 0.8), (0.05, 2.)] {
+            let screen = camera.world_to_screen(x, y);
+            assert_near(camera.screen_to_world(screen.x, screen.y), Vec2 { x, y });
+        }
+        for (x, y) in [(500., 300.), (120., 40.), (990., 580.)] {
+            let world = camera.screen_to_world(x, y);
+            assert_near(camera.world_to_screen(world.x, world.y), Vec2 { x, y });
+        }
+    }
+
+    #[test]
+    fn position_is_at_the_viewport_centre() {
+        let camera = moved_camera();
+        assert_near(camera.world_to_screen(0.4, -0.3), Vec2 { x: 500., y: 300. });
+
+        // Turning the view a quarter counter-clockwise turns the world
+        // clockwise, world +x points straight down the screen.
+        let mut camera = Camera2D::new(800., 600.);
+        camera.set_rotation(std::f32::consts::FRAC_PI_2);
+        camera.set_zoom(2.);
+        assert_near(camera.world_to_screen(0.5, 0.), Vec2 { x: 400., y: 600. });
+    }
+
+    #[test]
+    fn zoom_at_keeps_the_cursor_point() {
+        let mut camera = moved_camera();
+        let before = camera.screen_to_world(730., 145.);
+        camera.zoom_at(1.75, 730., 145.);
+        assert_eq!(camera.get_zoom(), 2.5 * 1.75);
+        assert_near(camera.screen_to_world(730., 145.), before);
+
+        // Zooming out from a corner too.
+        let corner = camera.screen_to_world(10., 590.);
+        camera.zoom_at(0.2, 10., 590.);
+        assert_near(camera.world_to_screen(corner.x, corner.y), Vec2 { x: 10., y: 590. });
+    }
+
+    fn camera_in(scaling: ScalingPolicy, window_width: f32, window_height: f32) -> Camera2D {
+        let mut camera = Camera2D::new(800., 600.);
+        camera.set_scaling(scaling);
+        camera.set_window_size(window_width, window_height);
+        camera
+    }
+
+    #[test]
+    fn letterbox_centres_the_design_aspect() {
+        // 1000 x 700 fits 800 x 600 at 7/6, bars left and right.
+        let viewport = camera_in(ScalingPolicy::Letterbox, 1000., 700.).get_viewport();
+        assert!((viewport.width - 2800. / 3.).abs() < 1e-3 && viewport.height == 700.);
+        assert_eq!((viewport.x, viewport.y), (33., 0.));
+
+        let viewport = camera_in(ScalingPolicy::Letterbox, 800., 1000.).get_viewport();
+        assert_eq!(viewport, Viewport { x: 0., y: 200., width: 800., height: 600. });
+    }
+
+    #[test]
+    fn pixel_perfect_rounds_the_scale_down() {
+        let viewport = camera_in(ScalingPolicy::PixelPerfect, 1000., 700.).get_viewport();
+        assert_eq!(viewport, Viewport { x: 100., y: 50., width: 800., height: 600. });
+
+        let viewport = camera_in(ScalingPolicy::PixelPerfect, 1701., 1250.).get_viewport();
+        assert_eq!(viewport, Viewport { x: 50., y: 25., width: 1600., height: 1200. });
+
+        // Never below the design size, even in a smaller window.
+        let viewport = camera_in(ScalingPolicy::PixelPerfect, 500., 400.).get_viewport();
+        assert_eq!(viewport, Viewport { x: -150., y: -100., width: 800., height: 600. });
+    }
+
+    #[test]
+    fn fit_policies_fill_the_window() {
+        let full = Viewport { x: 0., y: 0., width: 1000., height: 700. };
+        for scaling in [ScalingPolicy::Stretch, ScalingPolicy::FitWidth, ScalingPolicy::FitHeight] {
+            assert_eq!(camera_in(scaling, 1000., 700.).get_viewport(), full);
+        }
+
+        // Design width spans the window, a little more height shows.
+        let (x, y) = camera_in(ScalingPolicy::FitWidth, 1000., 700.).projection().transform_point(1., 1.);
+        assert_near(Vec2 { x, y }, Vec2 { x: 0.75, y: 1000. / 700. * 0.75 });
+
+        // Design height spans the window, more width shows.
+        let (x, y) = camera_in(ScalingPolicy::FitHeight, 1000., 700.).projection().transform_point(1., 1.);
+        assert_near(Vec2 { x, y }, Vec2 { x: 0.7, y: 1. });
+    }
+}
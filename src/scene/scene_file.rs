@@ -0,0 +1,526 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use crate::backend::drawing_context::DrawingContext;
+use crate::backend::texture::Texture;
+use crate::formats::json::{self, JsonError, JsonPositions, JsonValue};
+use crate::scene::scene_graph::{NodeId, SceneGraph};
+use crate::shapes::background::Background;
+use crate::shapes::circle::Circle;
+use crate::shapes::color::Color;
+use crate::shapes::ellipse::Ellipse;
+use crate::shapes::polygon::Polygon;
+use crate::shapes::polyline::Polyline;
+use crate::shapes::shape::Shape;
+use crate::shapes::shared_c_resources::Vec2;
+use crate::shapes::sprite::Sprite;
+use crate::shapes::square::Square;
+use crate::shapes::stroke::{LineCap, LineJoin};
+use crate::shapes::triangle::Triangle;
+
+// Segments for ellipses and circles that do not say.
+pub const DEFAULT_SEGMENTS: i32 = 64;
+
+// Every shape takes these besides its own fields.
+const COMMON_FIELDS: &[&str] = &["type", "layer", "z_index", "opacity", "visible", "children"];
+
+const SHAPE_FIELDS: &[(&str, &[&str])] = &[
+    ("background", &["color"]),
+    ("triangle", &["position", "size", "color"]),
+    ("square", &["position", "size", "color"]),
+    ("ellipse", &["position", "size", "segments", "color"]),
+    ("circle", &["position", "radius", "segments", "color"]),
+    ("polygon", &["points", "holes", "color"]),
+    ("polyline", &["points", "width", "join", "cap", "closed", "color"]),
+    ("sprite", &["position", "size", "texture", "color"]),
+    // Groups only take visible and children of the common fields.
+    ("group", &["position"])
+];
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(PathBuf, String),
+    // Syntax errors and values that do not describe a shape, with
+    // the file they were in when known.
+    Invalid(Option<PathBuf>, JsonError)
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneFileError::Io(path, message) => write!(f, "{}: {}", path.display(), message),
+            SceneFileError::Invalid(Some(path), error) => write!(f, "{}:{}", path.display(), error),
+            SceneFileError::Invalid(None, error) => write!(f, "scene file: {}", error)
+        }
+    }
+}
+
+// Builds a scene from a file listing its shapes, parents before the
+// children drawn on top of them.
+//
+// {
+//   "shapes": [
+//     { "type": "background", "color": [1.0, 0.937, 0.835] },
+//     { "type": "square", "position": [-0.4, -0.2], "size": [0.05, 0.05], "color": "lime", "layer": 1 },
+//     { "type": "group", "position": [0.3, 0.0], "children": [
+//       { "type": "sprite", "position": [0, 0], "size": [0.1, 0.1], "texture": "player.png" }
+//     ]}
+//   ]
+// }
+//
+// Types and the fields they take, besides layer, z_index, opacity,
+// visible and children which every shape has:
+//   background  color
+//   triangle    position, size, color
+//   square      position, size, color
+//   ellipse     position, size, segments, color
+//   circle      position, radius, segments, color
+//   polygon     points, holes, color
+//   polyline    points, width, join, cap, closed, color
+//   sprite      position, size, texture, color
+//   group       position
+// Sizes are half extents like the constructors take, colours are
+// anything Color::parse reads or [r, g, b] / [r, g, b, a] from 0 to 1,
+// textures are paths relative to the scene file.
+pub fn load(context: &mut dyn DrawingContext, path: impl AsRef<Path>) -> Result<SceneGraph, SceneFileError> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| SceneFileError::Io(path.to_path_buf(), error.to_string()))?;
+    let directory = path.parent().unwrap_or(Path::new(""));
+    from_json(context, &text, directory).map_err(|error| match error {
+        SceneFileError::Invalid(None, error) => SceneFileError::Invalid(Some(path.to_path_buf()), error),
+        error => error
+    })
+}
+
+// directory is where texture paths are looked up from.
+pub fn from_json(context: &mut dyn DrawingContext, text: &str, directory: &Path) -> Result<SceneGraph, SceneFileError> {
+    let invalid = |error| SceneFileError::Invalid(None, error);
+    let (file, positions) = json::parse_with_positions(text).map_err(invalid)?;
+
+    let mut loader = Loader {
+        context,
+        positions: &positions,
+        directory,
+        textures: HashMap::new()
+    };
+    if file.as_object().is_none() {
+        return Err(invalid(positions.error("", "expected an object with a \"shapes\" list")));
+    }
+    let shapes = file
+        .get("shapes")
+        .ok_or_else(|| invalid(positions.error("", "missing \"shapes\"")))?;
+
+    let mut scene = SceneGraph::new();
+    let root = scene.get_root();
+    loader.add_shapes(&mut scene, root, shapes, "shapes").map_err(invalid)?;
+    Ok(scene)
+}
+
+struct Loader<'a> {
+    context: &'a mut dyn DrawingContext,
+    positions: &'a JsonPositions,
+    directory: &'a Path,
+    // Each file is uploaded once however many sprites use it.
+    textures: HashMap<PathBuf, Texture>
+}
+
+impl<'a> Loader<'a> {
+
+    fn add_shapes(&mut self, scene: &mut SceneGraph, parent: NodeId, list: &JsonValue, path: &str) -> Result<(), JsonError> {
+        let entries = list
+            .as_array()
+            .ok_or_else(|| self.positions.error(path, "expected a list of shapes"))?;
+        for (index, value) in entries.iter().enumerate() {
+            let entry = Entry {
+                value,
+                path: format!("{}[{}]", path, index),
+                positions: self.positions
+            };
+            self.add_shape(scene, parent, &entry)?;
+        }
+        Ok(())
+    }
+
+    fn add_shape(&mut self, scene: &mut SceneGraph, parent: NodeId, entry: &Entry) -> Result<(), JsonError> {
+        if entry.value.as_object().is_none() {
+            return Err(entry.error("", "expected a shape object"));
+        }
+        let kind = entry.string("type")?;
+        let Some((_, fields)) = SHAPE_FIELDS.iter().find(|(name, _)| *name == kind) else {
+            let names: Vec<&str> = SHAPE_FIELDS.iter().map(|(name, _)| *name).collect();
+            return Err(entry.error("type", &format!("unknown shape type '{}', expected one of {}", kind, names.join(", "))));
+        };
+        entry.check_fields(fields)?;
+        if kind == "group" {
+            return self.add_group(scene, parent, entry);
+        }
+
+        let context = &mut *self.context;
+        let mut shape: Box<dyn Shape> = match kind {
+            "background" => {
+                let color = entry.color("color")?;
+                Box::new(Background::new(context, color.get_red(), color.get_green(), color.get_blue()))
+            }
+            "triangle" => {
+                let (position, size, color) = (entry.vec2("position")?, entry.vec2("size")?, entry.color("color")?);
+                Box::new(Triangle::new(context, position.x, position.y, size.x, size.y,
+                                       color.get_red(), color.get_green(), color.get_blue()))
+            }
+            "square" => {
+                let (position, size, color) = (entry.vec2("position")?, entry.vec2("size")?, entry.color("color")?);
+                Box::new(Square::new(context, position.x, position.y, size.x, size.y,
+                                     color.get_red(), color.get_green(), color.get_blue()))
+            }
+            "ellipse" => {
+                let (position, size, color) = (entry.vec2("position")?, entry.vec2("size")?, entry.color("color")?);
+                Box::new(Ellipse::new(context, position.x, position.y, size.x, size.y, entry.segments()?,
+                                      color.get_red(), color.get_green(), color.get_blue()))
+            }
+            "circle" => {
+                let (position, radius, color) = (entry.vec2("position")?, entry.number("radius")?, entry.color("color")?);
+                Box::new(Circle::new(context, position.x, position.y, radius, entry.segments()?,
+                                     color.get_red(), color.get_green(), color.get_blue()))
+            }
+            "polygon" => {
+                let (points, color) = (entry.points("points", 3)?, entry.color("color")?);
+                let holes = match entry.get("holes") {
+                    None => vec![],
+                    Some(holes) => holes
+                        .as_array()
+                        .ok_or_else(|| entry.error("holes", "expected a list of point lists"))?
+                        .iter()
+                        .enumerate()
+                        .map(|(index, hole)| entry.parse_points(hole, &format!("holes[{}]", index), 3))
+                        .collect::<Result<Vec<_>, _>>()?
+                };
+                if points.len() + holes.iter().map(|hole| hole.len()).sum::<usize>() > u16::MAX as usize {
+                    return Err(entry.error("points", &format!("too many points, the outline and holes share {}", u16::MAX)));
+                }
+                if holes.is_empty() {
+                    Box::new(Polygon::new(context, &points, color.get_red(), color.get_green(), color.get_blue()))
+                } else {
+                    Box::new(Polygon::new_with_holes(context, &points, &holes, color.get_red(), color.get_green(), color.get_blue()))
+                }
+            }
+            "polyline" => {
+                let (points, width, color) = (entry.points("points", 2)?, entry.number("width")?, entry.color("color")?);
+                if points.iter().all(|point| point.x == points[0].x && point.y == points[0].y) {
+                    return Err(entry.error("points", "expected at least 2 distinct points"));
+                }
+                let join = match entry.optional_string("join")?.unwrap_or("miter") {
+                    "miter" => LineJoin::Miter,
+                    "bevel" => LineJoin::Bevel,
+                    "round" => LineJoin::Round,
+                    other => return Err(entry.error("join", &format!("unknown join '{}', expected miter, bevel or round", other)))
+                };
+                let cap = match entry.optional_string("cap")?.unwrap_or("butt") {
+                    "butt" => LineCap::Butt,
+                    "square" => LineCap::Square,
+                    "round" => LineCap::Round,
+                    other => return Err(entry.error("cap", &format!("unknown cap '{}', expected butt, square or round", other)))
+                };
+                let closed = entry.optional_bool("closed")?.unwrap_or(false);
+                Box::new(Polyline::new(context, &points, width, join, cap, closed,
+                                       color.get_red(), color.get_green(), color.get_blue()))
+            }
+            // Colour tints the texture, white leaves it as it is.
+            _ => {
+                let (position, size) = (entry.vec2("position")?, entry.vec2("size")?);
+                let texture = self.texture(entry)?;
+                let mut sprite = Sprite::new(&mut *self.context, &texture, position.x, position.y, size.x, size.y);
+                if entry.get("color").is_some() {
+                    *sprite.get_uniform() = entry.color("color")?;
+                }
+                Box::new(sprite)
+            }
+        };
+
+        if let Some(layer) = entry.optional_integer("layer")? {
+            shape.set_layer(layer);
+        }
+        if let Some(z_index) = entry.optional_integer("z_index")? {
+            shape.set_z_index(z_index);
+        }
+        // A translucent colour fades the whole shape.
+        let alpha = match entry.get("color") {
+            Some(_) => entry.color("color")?.get_alpha(),
+            None => 1.
+        };
+        let opacity = entry.optional_number("opacity")?.unwrap_or(1.);
+        if !(0. ..=1.).contains(&opacity) {
+            return Err(entry.error("opacity", "opacity should be between 0 and 1"));
+        }
+        if alpha * opacity < 1. {
+            shape.set_opacity(alpha * opacity);
+        }
+
+        let node = scene.add_shape(parent, shape);
+        self.add_node_details(scene, node, entry)
+    }
+
+    // A node without a shape, moving its children as one.
+    fn add_group(&mut self, scene: &mut SceneGraph, parent: NodeId, entry: &Entry) -> Result<(), JsonError> {
+        for field in ["layer", "z_index", "opacity"] {
+            if entry.get(field).is_some() {
+                return Err(entry.error(field, &format!("groups have no {}, set it on their children", field)));
+            }
+        }
+        let position = match entry.get("position") {
+            Some(_) => entry.vec2("position")?,
+            None => Vec2 { x: 0., y: 0. }
+        };
+        let node = scene.add_group(parent);
+        let transform = scene.get_transform_mut(node);
+        transform.set_position(position.x, position.y);
+        // Starts where it is put rather than sliding in from the origin.
+        transform.save_previous();
+        self.add_node_details(scene, node, entry)
+    }
+
+    fn add_node_details(&mut self, scene: &mut SceneGraph, node: NodeId, entry: &Entry) -> Result<(), JsonError> {
+        if let Some(visible) = entry.optional_bool("visible")? {
+            scene.set_visible(node, visible);
+        }
+        match entry.get("children") {
+            Some(children) => self.add_shapes(scene, node, children, &entry.field_path("children")),
+            None => Ok(())
+        }
+    }
+
+    fn texture(&mut self, entry: &Entry) -> Result<Texture, JsonError> {
+        let path = self.directory.join(entry.string("texture")?);
+        if let Some(texture) = self.textures.get(&path) {
+            return Ok(*texture);
+        }
+        let texture = Texture::load(&mut *self.context, &path)
+            .map_err(|error| entry.error("texture", &format!("could not load texture: {}", error)))?;
+        self.textures.insert(path, texture);
+        Ok(texture)
+    }
+}
+
+// One shape's object and where it is in the file, for errors.
+struct Entry<'a> {
+    value: &'a JsonValue,
+    path: String,
+    positions: &'a JsonPositions
+}
+
+impl<'a> Entry<'a> {
+
+    fn get(&self, key: &str) -> Option<&'a JsonValue> {
+        self.value.get(key)
+    }
+
+    fn field_path(&self, key: &str) -> String {
+        match key {
+            "" => self.path.clone(),
+            key => format!("{}.{}", self.path, key)
+        }
+    }
+
+    // At the field, or at the shape when the field is missing.
+    fn error(&self, key: &str, message: &str) -> JsonError {
+        self.positions.error(&self.field_path(key), message)
+    }
+
+    fn required(&self, key: &str) -> Result<&'a JsonValue, JsonError> {
+        self.get(key).ok_or_else(|| self.error("", &format!("missing \"{}\"", key)))
+    }
+
+    // Catches typos such as "colour", which would otherwise be
+    // silently ignored.
+    fn check_fields(&self, fields: &[&str]) -> Result<(), JsonError> {
+        let members = self.value.as_object().unwrap_or(&[]);
+        for (name, _) in members {
+            if !COMMON_FIELDS.contains(&name.as_str()) && !fields.contains(&name.as_str()) {
+                return Err(self.error(name, &format!("unknown field \"{}\", expected one of {}", name, fields
+                    .iter()
+                    .chain(COMMON_FIELDS.iter().skip(1))
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join(", "))));
+            }
+        }
+        Ok(())
+    }
+
+    fn string(&self, key: &str) -> Result<&'a str, JsonError> {
+        self.required(key)?
+            .as_str()
+            .ok_or_else(|| self.error(key, &format!("\"{}\" should be a string", key)))
+    }
+
+    fn optional_string(&self, key: &str) -> Result<Option<&'a str>, JsonError> {
+        match self.get(key) {
+            Some(_) => self.string(key).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn number(&self, key: &str) -> Result<f32, JsonError> {
+        self.required(key)?
+            .as_f64()
+            .map(|number| number as f32)
+            .ok_or_else(|| self.error(key, &format!("\"{}\" should be a number", key)))
+    }
+
+    fn optional_number(&self, key: &str) -> Result<Option<f32>, JsonError> {
+        match self.get(key) {
+            Some(_) => self.number(key).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn optional_integer(&self, key: &str) -> Result<Option<i32>, JsonError> {
+        match self.optional_number(key)? {
+            Some(number) if number.fract() != 0. => Err(self.error(key, &format!("\"{}\" should be a whole number", key))),
+            number => Ok(number.map(|number| number as i32))
+        }
+    }
+
+    fn optional_bool(&self, key: &str) -> Result<Option<bool>, JsonError> {
+        match self.get(key) {
+            Some(value) => value
+                .as_bool()
+                .map(Some)
+                .ok_or_else(|| self.error(key, &format!("\"{}\" should be true or false", key))),
+            None => Ok(None)
+        }
+    }
+
+    fn segments(&self) -> Result<i32, JsonError> {
+        match self.optional_integer("segments")? {
+            Some(segments) if segments < 3 => Err(self.error("segments", "\"segments\" should be at least 3")),
+            segments => Ok(segments.unwrap_or(DEFAULT_SEGMENTS))
+        }
+    }
+
+    // [x, y]
+    fn vec2(&self, key: &str) -> Result<Vec2, JsonError> {
+        match self.required(key)?.as_array() {
+            Some([JsonValue::Number(x), JsonValue::Number(y)]) => Ok(Vec2 { x: *x as f32, y: *y as f32 }),
+            _ => Err(self.error(key, &format!("\"{}\" should be [x, y]", key)))
+        }
+    }
+
+    fn points(&self, key: &str, minimum: usize) -> Result<Vec<Vec2>, JsonError> {
+        self.parse_points(self.required(key)?, key, minimum)
+    }
+
+    // A list of at least minimum [x, y], key is where it is below
+    // the shape, e.g. "holes[0]".
+    fn parse_points(&self, value: &JsonValue, key: &str, minimum: usize) -> Result<Vec<Vec2>, JsonError> {
+        let points = value
+            .as_array()
+            .ok_or_else(|| self.error(key, "expected a list of [x, y] points"))?;
+        if points.len() < minimum {
+            return Err(self.error(key, &format!("expected at least {} points", minimum)));
+        }
+        points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| match point.as_array() {
+                Some([JsonValue::Number(x), JsonValue::Number(y)]) => Ok(Vec2 { x: *x as f32, y: *y as f32 }),
+                _ => Err(self.error(&format!("{}[{}]", key, index), "a point should be [x, y]"))
+            })
+            .collect()
+    }
+
+    // A name, hex or css function string, or [r, g, b] / [r, g, b, a].
+    fn color(&self, key: &str) -> Result<Color, JsonError> {
+        let value = self.required(key)?;
+        if let Some(text) = value.as_str() {
+            return Color::parse(text).map_err(|error| self.error(key, &error.to_string()));
+        }
+        let channels: Option<Vec<f32>> = value
+            .as_array()
+            .and_then(|channels| channels.iter().map(|channel| channel.as_f64().map(|channel| channel as f32)).collect());
+        match channels.as_deref() {
+            Some(&[red, green, blue]) => Ok(Color::new(red, green, blue)),
+            Some(&[red, green, blue, alpha]) => Ok(Color::new_rgba(red, green, blue, alpha)),
+            _ => Err(self.error(key, "a colour should be a string such as \"#ff8800\" or [r, g, b] / [r, g, b, a]"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::software::SoftwareBackend;
+
+    fn error_in(text: &str) -> JsonError {
+        match from_json(&mut SoftwareBackend::new(8, 8), text, Path::new("")) {
+            Err(SceneFileError::Invalid(None, error)) => error,
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("loaded {}", text)
+        }
+    }
+
+    #[test]
+    fn groups_start_at_their_position() {
+        let text = r#"{ "shapes": [{ "type": "group", "position": [0.3, -0.2] }] }"#;
+        let scene = from_json(&mut SoftwareBackend::new(8, 8), text, Path::new("")).unwrap();
+        let group = scene.get_children(scene.get_root())[0];
+        let (x, y) = scene.get_transform(group).to_local_matrix(0.).transform_point(0., 0.);
+        assert!((x - 0.3).abs() < 1e-6 && (y + 0.2).abs() < 1e-6, "({}, {})", x, y);
+    }
+
+    #[test]
+    fn errors_point_at_the_value() {
+        let error = error_in("{ \"shapes\": [\n  { \"type\": \"square\", \"position\": [0, 0], \"size\": 1 }\n] }");
+        assert_eq!((error.line, error.column), (2, 51));
+        assert_eq!(error.message, "\"size\" should be [x, y]");
+
+        let error = error_in("{ \"shapes\": [\n  { \"type\": \"hexagon\" }\n] }");
+        assert_eq!((error.line, error.column), (2, 13));
+    }
+
+    #[test]
+    fn polygons_need_three_points() {
+        let error = error_in("{ \"shapes\": [\n  { \"type\": \"polygon\", \"points\": [[0, 0], [1, 0]], \"color\": \"red\" }\n] }");
+        assert_eq!((error.line, error.column), (2, 34));
+        assert_eq!(error.message, "expected at least 3 points");
+
+        let error = error_in("{ \"shapes\": [\n  { \"type\": \"polygon\", \"color\": \"red\",\n    \"points\": [[0, 0], [1, 0], [0, 1]],\n    \"holes\": [[[0.1, 0.1], [0.2, 0.1]]] }\n] }");
+        assert_eq!((error.line, error.column), (4, 15));
+        assert_eq!(error.message, "expected at least 3 points");
+    }
+
+    #[test]
+    fn polylines_need_two_distinct_points() {
+        let error = error_in("{ \"shapes\": [\n  { \"type\": \"polyline\", \"width\": 0.1, \"color\": \"red\",\n    \"points\": [[0.5, 0.5], [0.5, 0.5], [0.5, 0.5]] }\n] }");
+        assert_eq!((error.line, error.column), (3, 15));
+        assert_eq!(error.message, "expected at least 2 distinct points");
+
+        let error = error_in("{ \"shapes\": [{ \"type\": \"polyline\", \"width\": 0.1, \"color\": \"red\", \"points\": [[0, 0]] }] }");
+        assert_eq!(error.message, "expected at least 2 points");
+    }
+
+    #[test]
+    fn missing_fields_point_at_the_shape() {
+        let error = error_in("{ \"shapes\": [\n\n    { \"type\": \"circle\", \"position\": [0, 0] }\n] }");
+        assert_eq!((error.line, error.column), (3, 5));
+        assert_eq!(error.message, "missing \"radius\"");
+    }
+
+    #[test]
+    fn load_reports_the_file() {
+        let path = std::env::temp_dir().join(format!("scene_file_{}.json", std::process::id()));
+        std::fs::write(&path, "{\n  \"shapes\": [\n    { \"type\": \"group\", \"layer\": 2 }\n  ]\n}\n").unwrap();
+        let result = load(&mut SoftwareBackend::new(8, 8), &path);
+        std::fs::remove_file(&path).unwrap();
+
+        match result {
+            Err(SceneFileError::Invalid(Some(file), error)) => {
+                assert_eq!(file, path);
+                assert_eq!((error.line, error.column), (3, 33));
+            }
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("loaded a group with a layer")
+        }
+        assert!(matches!(load(&mut SoftwareBackend::new(8, 8), &path), Err(SceneFileError::Io(..))));
+    }
+}
+
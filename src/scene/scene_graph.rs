@@ -0,0 +1,351 @@
+use crate::backend::drawing_context::DrawingContext;
+use crate::input::input_state::InputState;
+use crate::scene::camera::Camera2D;
+use crate::shapes::draw_parameters::DrawParameters;
+use crate::shapes::matrix::Mat4;
+use crate::shapes::shape::Shape;
+use crate::shapes::shared_c_resources::Vec2;
+use crate::shapes::transform::Transform;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+// A window position seen from one node's shape.
+#[derive(Clone, Copy)]
+pub struct PointerHit {
+    pub node: NodeId,
+    // Scene units, clip space for shapes locked to the camera.
+    pub position: Vec2,
+    // The coordinates the shape's vertices are in.
+    pub local: Vec2
+}
+
+// A node is a transform plus an optional shape. Nodes without a
+// shape are groups, useful for moving several shapes as one.
+pub struct Node {
+    shape: Option<Box<dyn Shape>>,
+    transform: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    visible: bool
+}
+
+// Children draw after their parent and in insertion order unless
+// a shape's layer or z_index says otherwise, see draw_order.
+pub struct SceneGraph {
+    // Removed nodes leave a None so every other NodeId stays valid.
+    nodes: Vec<Option<Node>>,
+    root: NodeId,
+    // Last set by draw, picking sees the shapes where they were drawn.
+    interpolation: f32
+}
+
+impl Default for SceneGraph {
+    fn default() -> SceneGraph {
+        SceneGraph::new()
+    }
+}
+
+impl SceneGraph {
+
+    pub fn new() -> SceneGraph {
+        SceneGraph {
+            nodes: vec![Some(Node {
+                shape: None,
+                transform: Transform::new(0., 0.),
+                parent: None,
+                children: vec![],
+                visible: true
+            })],
+            root: NodeId(0),
+            interpolation: 1.
+        }
+    }
+
+    // Each shape becomes a direct child of the root, keeping the
+    // order of the vector.
+    pub fn from_shapes(shapes: Vec<Box<dyn Shape>>) -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        let root = scene.get_root();
+        shapes.into_iter().for_each(|shape| { scene.add_shape(root, shape); });
+        scene
+    }
+
+    pub fn get_root(&self) -> NodeId {
+        self.root
+    }
+
+    pub fn add_shape(&mut self, parent: NodeId, shape: Box<dyn Shape>) -> NodeId {
+        self.add_node(parent, Some(shape))
+    }
+
+    pub fn add_group(&mut self, parent: NodeId) -> NodeId {
+        self.add_node(parent, None)
+    }
+
+    fn add_node(&mut self, parent: NodeId, shape: Option<Box<dyn Shape>>) -> NodeId {
+        assert!(self.contains(parent), "Parent node {:?} does not exist.", parent);
+
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(Some(Node {
+            shape,
+            transform: Transform::new(0., 0.),
+            parent: Some(parent),
+            children: vec![],
+            visible: true
+        }));
+        self.node_mut(parent).children.push(id);
+        id
+    }
+
+    // Drops the node, its shape and everything below it.
+    pub fn remove_node(&mut self, id: NodeId) {
+        if id == self.root || !self.contains(id) {
+            return;
+        }
+
+        if let Some(parent) = self.node(id).parent {
+            self.node_mut(parent).children.retain(|child| *child != id);
+        }
+
+        let mut pending = vec![id];
+        while let Some(next) = pending.pop() {
+            if let Some(node) = self.nodes[next.0].take() {
+                pending.extend(node.children);
+            }
+        }
+    }
+
+    // Moves a subtree under a new parent, the local transform is kept
+    // as is so the subtree will jump if the parents' worlds differ.
+    // Refuses to create a cycle.
+    pub fn set_parent(&mut self, id: NodeId, parent: NodeId) -> bool {
+        if id == self.root || !self.contains(id) || !self.contains(parent) {
+            return false;
+        }
+
+        let mut ancestor = Some(parent);
+        while let Some(current) = ancestor {
+            if current == id {
+                return false;
+            }
+            ancestor = self.node(current).parent;
+        }
+
+        if let Some(old_parent) = self.node(id).parent {
+            self.node_mut(old_parent).children.retain(|child| *child != id);
+        }
+        self.node_mut(parent).children.push(id);
+        self.node_mut(id).parent = Some(parent);
+        true
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.nodes.get(id.0).is_some_and(|node| node.is_some())
+    }
+
+    pub fn get_parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    pub fn get_children(&self, id: NodeId) -> &[NodeId] {
+        &self.node(id).children
+    }
+
+    pub fn get_shape(&self, id: NodeId) -> Option<&dyn Shape> {
+        self.node(id).shape.as_deref()
+    }
+
+    pub fn get_shape_mut(&mut self, id: NodeId) -> Option<&mut (dyn Shape + 'static)> {
+        self.node_mut(id).shape.as_deref_mut()
+    }
+
+    pub fn get_transform(&self, id: NodeId) -> &Transform {
+        &self.node(id).transform
+    }
+
+    pub fn get_transform_mut(&mut self, id: NodeId) -> &mut Transform {
+        &mut self.node_mut(id).transform
+    }
+
+    pub fn is_visible(&self, id: NodeId) -> bool {
+        self.node(id).visible
+    }
+
+    // Hiding a node hides its whole subtree.
+    pub fn set_visible(&mut self, id: NodeId, visible: bool) {
+        self.node_mut(id).visible = visible;
+    }
+
+    pub fn world_matrix(&self, id: NodeId) -> Mat4 {
+        let node = self.node(id);
+        let local = node.transform.to_local_matrix(self.interpolation);
+        match node.parent {
+            Some(parent) => self.world_matrix(parent).multiply(&local),
+            None => local
+        }
+    }
+
+    // Every live shape, hidden ones included, in scene order.
+    pub fn for_each_shape_mut(&mut self, mut f: impl FnMut(&mut dyn Shape)) {
+        let mut pending = vec![self.root];
+        while let Some(id) = pending.pop() {
+            let node = self.node_mut(id);
+            if let Some(shape) = node.shape.as_deref_mut() {
+                f(shape);
+            }
+            pending.extend(node.children.iter().rev());
+        }
+    }
+
+    // Hidden shapes keep updating, visibility only affects drawing.
+    // Every pose is saved first so draw can interpolate from it.
+    pub fn update(&mut self, dt: f32, input: &InputState) {
+        self.nodes.iter_mut().flatten().for_each(|node| node.transform.save_previous());
+        self.for_each_shape_mut(|shape| {
+            shape.get_transform_mut().save_previous();
+            shape.update(dt, input);
+        });
+    }
+
+    // Puts the shape above everything else on its layer.
+    pub fn bring_to_front(&mut self, id: NodeId) {
+        let Some(layer) = self.get_shape(id).map(|shape| shape.get_layer()) else {
+            return;
+        };
+        let top = self.layer_z_indices(layer, id).max().unwrap_or(0);
+        if let Some(shape) = self.get_shape_mut(id) {
+            shape.set_z_index(top.saturating_add(1));
+        }
+    }
+
+    // Puts the shape below everything else on its layer.
+    pub fn send_to_back(&mut self, id: NodeId) {
+        let Some(layer) = self.get_shape(id).map(|shape| shape.get_layer()) else {
+            return;
+        };
+        let bottom = self.layer_z_indices(layer, id).min().unwrap_or(0);
+        if let Some(shape) = self.get_shape_mut(id) {
+            shape.set_z_index(bottom.saturating_sub(1));
+        }
+    }
+
+    fn layer_z_indices(&self, layer: i32, except: NodeId) -> impl Iterator<Item = i32> + '_ {
+        self.nodes.iter()
+            .enumerate()
+            .filter(move |(index, _)| *index != except.0)
+            .filter_map(|(_, node)| node.as_ref().and_then(|node| node.shape.as_deref()))
+            .filter(move |shape| shape.get_layer() == layer)
+            .map(|shape| shape.get_z_index())
+    }
+
+    // Visible shapes with their node's world matrix, depth first
+    // then stable sorted by layer and z_index, so shapes that never
+    // set either draw in scene order.
+    pub fn draw_order(&self) -> Vec<(NodeId, Mat4)> {
+        let mut order = vec![];
+        self.collect_visible(self.root, &Mat4::identity(), &mut order);
+        order.sort_by_key(|(id, _)| {
+            let shape = self.get_shape(*id).unwrap();
+            (shape.get_layer(), shape.get_z_index())
+        });
+        order
+    }
+
+    fn collect_visible(&self, id: NodeId, parent_world: &Mat4, order: &mut Vec<(NodeId, Mat4)>) {
+        let node = self.node(id);
+        if !node.visible {
+            return;
+        }
+
+        let world = parent_world.multiply(&node.transform.to_local_matrix(self.interpolation));
+        if node.shape.is_some() {
+            order.push((id, world));
+        }
+        for child in node.children.iter() {
+            self.collect_visible(*child, &world, order);
+        }
+    }
+
+    // interpolation is how far the frame is between the last two
+    // updates, see SimulationClock::get_alpha. 1 draws the latest.
+    pub fn draw(&mut self, drawing_context: &mut dyn DrawingContext, camera: &Camera2D, interpolation: f32) {
+        self.set_interpolation(interpolation);
+
+        let viewport = camera.get_viewport();
+        drawing_context.apply_viewport(
+            viewport.x as i32,
+            viewport.y as i32,
+            viewport.width as i32,
+            viewport.height as i32
+        );
+        let view_projection = camera.view_projection();
+        let interpolation = self.interpolation;
+
+        for (id, world) in self.draw_order() {
+            if let Some(shape) = self.get_shape_mut(id) {
+                let shape_view_projection = if shape.is_locked_to_camera() {
+                    Mat4::identity()
+                } else {
+                    view_projection
+                };
+                let parameters = DrawParameters::new(world, shape_view_projection, interpolation);
+                shape.draw(drawing_context, &parameters, true);
+            }
+        }
+    }
+
+    pub fn get_interpolation(&self) -> f32 {
+        self.interpolation
+    }
+
+    // Where picking poses every transform between its last two
+    // updates without drawing, e.g. so a replayed click sees what
+    // was on screen.
+    pub fn set_interpolation(&mut self, interpolation: f32) {
+        self.interpolation = interpolation.clamp(0., 1.);
+    }
+
+    // Topmost visible shape under the window position, in the
+    // reverse of draw order.
+    pub fn pick(&self, camera: &Camera2D, screen_x: f32, screen_y: f32) -> Option<PointerHit> {
+        self.draw_order()
+            .into_iter()
+            .rev()
+            .filter_map(|(id, world)| self.hit_from(id, &world, camera, screen_x, screen_y))
+            .find(|hit| self.get_shape(hit.node).is_some_and(|shape| shape.contains_point(hit.local.x, hit.local.y)))
+    }
+
+    // Where the window position falls for the node's shape, whether
+    // or not it is over it. None for groups and collapsed shapes.
+    pub fn pointer_hit(&self, id: NodeId, camera: &Camera2D, screen_x: f32, screen_y: f32) -> Option<PointerHit> {
+        self.hit_from(id, &self.world_matrix(id), camera, screen_x, screen_y)
+    }
+
+    fn hit_from(&self, id: NodeId, world: &Mat4, camera: &Camera2D, screen_x: f32, screen_y: f32) -> Option<PointerHit> {
+        let shape = self.get_shape(id)?;
+        let position = if shape.is_locked_to_camera() {
+            camera.screen_to_clip(screen_x, screen_y)
+        } else {
+            camera.screen_to_world(screen_x, screen_y)
+        };
+        let (local_x, local_y) = world
+            .multiply(&shape.get_transform().to_local_matrix(self.interpolation))
+            .inverse()?
+            .transform_point(position.x, position.y);
+
+        Some(PointerHit {
+            node: id,
+            position,
+            local: Vec2 { x: local_x, y: local_y }
+        })
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        self.nodes[id.0].as_ref().expect("Node has been removed.")
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.nodes[id.0].as_mut().expect("Node has been removed.")
+    }
+}
@@ -0,0 +1,123 @@
+use crate::backend::drawing_context::DrawingContext;
+use crate::input::input_state::InputState;
+use crate::input::pointer::PointerRouter;
+use crate::input::recording::{InputEvent, InputRecording};
+use crate::scene::camera::Camera2D;
+use crate::scene::scene_graph::SceneGraph;
+
+// The scene and everything input does to it, without a window.
+// Stage feeds it live events and ticks it from its clock, replays
+// feed it a recording, so both go through the same code.
+pub struct Simulation {
+    scene: SceneGraph,
+    camera: Camera2D,
+    input: InputState,
+    pointer: PointerRouter,
+    // Steps run so far.
+    step: u64
+}
+
+impl Simulation {
+
+    pub fn new(scene: SceneGraph, camera: Camera2D, input: InputState) -> Simulation {
+        Simulation {
+            scene,
+            camera,
+            input,
+            pointer: PointerRouter::new(),
+            step: 0
+        }
+    }
+
+    pub fn get_scene(&self) -> &SceneGraph {
+        &self.scene
+    }
+
+    pub fn get_scene_mut(&mut self) -> &mut SceneGraph {
+        &mut self.scene
+    }
+
+    pub fn get_camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    pub fn get_camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    pub fn get_input(&self) -> &InputState {
+        &self.input
+    }
+
+    pub fn get_input_mut(&mut self) -> &mut InputState {
+        &mut self.input
+    }
+
+    pub fn get_pointer(&self) -> &PointerRouter {
+        &self.pointer
+    }
+
+    pub fn get_step(&self) -> u64 {
+        self.step
+    }
+
+    // True when a press was taken by ActionMap::rebind_next and
+    // should go no further.
+    pub fn handle_event(&mut self, event: &InputEvent) -> bool {
+        let (scene, camera) = (&mut self.scene, &self.camera);
+        match *event {
+            InputEvent::KeyDown { key, modifiers, repeat } => return self.input.key_down(key, modifiers, repeat),
+            InputEvent::KeyUp { key, modifiers } => self.input.key_up(key, modifiers),
+            InputEvent::Character { character, modifiers } => self.input.character(character, modifiers),
+            InputEvent::MouseMotion { x, y } => {
+                self.input.mouse_motion(x, y);
+                self.pointer.mouse_motion(scene, camera, x, y);
+            }
+            InputEvent::MouseWheel { x, y } => {
+                self.input.mouse_wheel(x, y);
+                self.pointer.mouse_wheel(scene, camera, x, y);
+            }
+            InputEvent::MouseButtonDown { button, x, y } => {
+                if self.input.mouse_button_down(button) {
+                    return true;
+                }
+                self.pointer.mouse_button_down(scene, camera, button, x, y);
+            }
+            InputEvent::MouseButtonUp { button, x, y } => {
+                self.input.mouse_button_up(button);
+                self.pointer.mouse_button_up(scene, camera, button, x, y);
+            }
+            InputEvent::Touch { phase, id, x, y } => self.pointer.touch(scene, camera, phase, id, x, y),
+            InputEvent::Resize { width, height } => self.camera.set_window_size(width, height),
+            // The releases for anything held would never arrive.
+            InputEvent::Minimized => self.input.release_all()
+        }
+        false
+    }
+
+    // One fixed or variable update of dt seconds.
+    pub fn step(&mut self, dt: f32) {
+        self.input.update(dt);
+        self.scene.update(dt, &self.input);
+        self.step += 1;
+    }
+
+    pub fn draw(&mut self, drawing_context: &mut dyn DrawingContext, interpolation: f32) {
+        self.scene.draw(drawing_context, &self.camera, interpolation);
+    }
+
+    // Feeds in the recording's events for the next step at the
+    // interpolation they were seen at, then runs the step. false
+    // once every recorded step has run.
+    pub fn replay_step(&mut self, recording: &InputRecording) -> bool {
+        if self.step >= recording.get_steps() {
+            return false;
+        }
+        for recorded in recording.get_events_before_step(self.step) {
+            self.scene.set_interpolation(recorded.interpolation);
+            self.handle_event(&recorded.event);
+        }
+        self.step(recording.get_step_time());
+        true
+    }
+}
@@ -6,3 +6,18 @@ pub mod background;
 pub mod shared_c_resources;
 pub mod default_shader;
 pub mod color;
+pub mod color_names;
+pub mod gradient;
+pub mod ellipse;
+pub mod circle;
+pub mod polygon;
+pub mod triangulation;
+pub mod polyline;
+pub mod sprite;
+pub mod animation;
+pub mod animated_sprite;
+pub mod frame_graph;
+pub mod stroke;
+pub mod matrix;
+pub mod transform;
+pub mod draw_parameters;
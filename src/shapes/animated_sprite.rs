@@ -0,0 +1,130 @@
+use crate::backend::drawing_context::DrawingContext;
+use crate::shapes::animation::{AnimationClip, AnimationEvent, AnimationPlayer};
+use crate::shapes::color::Color;
+use crate::shapes::draw_parameters::DrawParameters;
+use crate::shapes::shape::{BaseShape, Shape};
+use crate::shapes::sprite::Sprite;
+use crate::input::input_state::InputState;
+
+pub type AnimationHandler = Box<dyn FnMut(&AnimationEvent)>;
+
+// A Sprite whose texture and region follow an AnimationPlayer.
+pub struct AnimatedSprite {
+    base_details: Sprite,
+    player: AnimationPlayer,
+    event_handler: Option<AnimationHandler>
+}
+
+impl Shape for AnimatedSprite {
+
+    fn base(&self) -> &BaseShape {
+        self.base_details.base()
+    }
+
+    fn base_mut(&mut self) -> &mut BaseShape {
+        self.base_details.base_mut()
+    }
+
+    fn update(&mut self, dt: f32, _input: &InputState) {
+        self.advance(dt);
+    }
+
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, draw: bool) {
+        self.base_details.draw(drawing_context, parameters, draw);
+    }
+}
+
+impl AnimatedSprite {
+
+    // Starts playing clip straight away. width and height are half
+    // extents like Sprite's.
+    pub fn new(context: &mut dyn DrawingContext,
+               clip: AnimationClip,
+               x: f32,
+               y: f32,
+               width: f32,
+               height: f32) -> AnimatedSprite {
+
+        assert!(!clip.get_frames().is_empty(), "Animation clip has no frames.");
+        let first_frame = &clip.get_frames()[0];
+        let mut sprite = Sprite::new(context, first_frame.get_texture(), x, y, width, height);
+        sprite.set_region(first_frame.get_region());
+
+        let name = clip.get_name().to_string();
+        let mut player = AnimationPlayer::new();
+        player.add_clip(clip);
+        player.play(&name);
+
+        AnimatedSprite {
+            base_details: sprite,
+            player,
+            event_handler: None
+        }
+    }
+
+    pub fn get_player(&self) -> &AnimationPlayer {
+        &self.player
+    }
+
+    pub fn get_player_mut(&mut self) -> &mut AnimationPlayer {
+        &mut self.player
+    }
+
+    pub fn get_uniform(&mut self) -> &mut Color {
+        self.base_details.get_uniform()
+    }
+
+    // Called with every event the player reports while updating.
+    pub fn set_event_handler(&mut self, event_handler: Option<AnimationHandler>) {
+        self.event_handler = event_handler;
+    }
+
+    // Moves the animation on by dt seconds and shows the frame it
+    // lands on.
+    pub fn advance(&mut self, dt: f32) {
+        let events = self.player.advance(dt);
+        if let Some(event_handler) = self.event_handler.as_mut() {
+            events.iter().for_each(event_handler);
+        }
+
+        if let Some(frame) = self.player.get_current_frame() {
+            if frame.get_texture() != self.base_details.get_texture() {
+                self.base_details.set_texture(frame.get_texture());
+            }
+            self.base_details.set_region(frame.get_region());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+    use crate::backend::software::SoftwareBackend;
+    use crate::backend::texture::Texture;
+    use crate::shapes::animation::PlaybackMode;
+    use crate::shapes::sprite::Region;
+
+    #[test]
+    fn advance_delivers_events_to_the_handler() {
+        let mut context = SoftwareBackend::new(1, 1);
+        let texture = Texture::from_rgba8(&mut context, 1, 1, vec![255; 4]).unwrap();
+        let mut clip = AnimationClip::new("clip", PlaybackMode::Once);
+        for _ in 0..2 {
+            clip.add_frame(&texture, Region { u_min: 0., v_min: 0., u_max: 1., v_max: 1. }, 0.1);
+        }
+        clip.set_frame_event(1, Some("hit"));
+        let mut sprite = AnimatedSprite::new(&mut context, clip, 0., 0., 0.5, 0.5);
+
+        let events = Rc::new(RefCell::new(vec![]));
+        let handled = events.clone();
+        sprite.set_event_handler(Some(Box::new(move |event| handled.borrow_mut().push(event.clone()))));
+        sprite.advance(0.25);
+
+        assert_eq!(*events.borrow(), vec![
+            AnimationEvent::Frame { clip: "clip".to_string(), frame: 1, name: "hit".to_string() },
+            AnimationEvent::Finished { clip: "clip".to_string() }
+        ]);
+    }
+}
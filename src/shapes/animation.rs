@@ -0,0 +1,564 @@
+use crate::backend::atlas::Atlas;
+use crate::backend::sprite_sheet::{SheetFrame, SpriteSheet, TagDirection};
+use crate::backend::texture::Texture;
+use crate::shapes::sprite::Region;
+
+// Keeps a zero length frame from stalling advance in a loop.
+const MIN_FRAME_DURATION: f32 = 0.001;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaybackMode {
+    Loop,
+    // Forwards then backwards, the end frames are not repeated.
+    PingPong,
+    // Stops on the last frame.
+    Once
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnimationEvent {
+    // A frame with an event name was reached.
+    Frame { clip: String, frame: usize, name: String },
+    // Loop and PingPong clips starting over.
+    Looped { clip: String },
+    // A Once clip reached the end of its last frame.
+    Finished { clip: String }
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationFrame {
+    texture: Texture,
+    region: Region,
+    // Seconds at speed 1.
+    duration: f32,
+    event: Option<String>
+}
+
+impl AnimationFrame {
+
+    pub fn get_texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn get_region(&self) -> Region {
+        self.region
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.duration
+    }
+
+    pub fn get_event(&self) -> Option<&str> {
+        self.event.as_deref()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    name: String,
+    frames: Vec<AnimationFrame>,
+    mode: PlaybackMode
+}
+
+impl AnimationClip {
+
+    pub fn new(name: &str, mode: PlaybackMode) -> AnimationClip {
+        AnimationClip {
+            name: name.to_string(),
+            frames: vec![],
+            mode
+        }
+    }
+
+    // Cells of a uniform grid, left to right then top to bottom.
+    pub fn from_grid(name: &str,
+                     texture: &Texture,
+                     columns: u32,
+                     rows: u32,
+                     count: u32,
+                     frame_duration: f32,
+                     mode: PlaybackMode) -> AnimationClip {
+
+        let mut clip = AnimationClip::new(name, mode);
+        let (columns, rows) = (columns.max(1), rows.max(1));
+        let (cell_width, cell_height) = (1. / columns as f32, 1. / rows as f32);
+
+        for index in 0..count.min(columns * rows) {
+            let (column, row) = ((index % columns) as f32, (index / columns) as f32);
+            clip.add_frame(texture, Region {
+                u_min: column * cell_width,
+                v_min: row * cell_height,
+                u_max: (column + 1.) * cell_width,
+                v_max: (row + 1.) * cell_height
+            }, frame_duration);
+        }
+        clip
+    }
+
+    // None if any of the names is missing from the atlas.
+    pub fn from_atlas(name: &str,
+                      atlas: &Atlas,
+                      regions: &[&str],
+                      frame_duration: f32,
+                      mode: PlaybackMode) -> Option<AnimationClip> {
+
+        let mut clip = AnimationClip::new(name, mode);
+        for region_name in regions {
+            let region = atlas.get_region(region_name)?;
+            clip.add_frame(atlas.get_page(region.page)?, region.region, frame_duration);
+        }
+        Some(clip)
+    }
+
+    // A tagged run of sprite sheet frames, using the sheet's own
+    // durations where it has them. Tags always loop, reverse tags
+    // just list their frames backwards.
+    pub fn from_sprite_sheet(atlas: &Atlas,
+                             sheet: &SpriteSheet,
+                             tag: &str,
+                             default_duration: f32) -> Option<AnimationClip> {
+
+        let tag = sheet.get_tag(tag)?;
+        let frames = sheet.get_frames().get(tag.from..=tag.to)?;
+        let mode = match tag.direction {
+            TagDirection::PingPong => PlaybackMode::PingPong,
+            TagDirection::Forward | TagDirection::Reverse => PlaybackMode::Loop
+        };
+
+        let mut clip = AnimationClip::new(&tag.name, mode);
+        let mut add = |frame: &SheetFrame| -> Option<()> {
+            let region = atlas.get_region(&frame.name)?;
+            clip.add_frame(atlas.get_page(region.page)?, region.region, frame.duration.unwrap_or(default_duration));
+            Some(())
+        };
+        if tag.direction == TagDirection::Reverse {
+            frames.iter().rev().try_for_each(&mut add)?;
+        } else {
+            frames.iter().try_for_each(&mut add)?;
+        }
+        Some(clip)
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    pub fn get_mode(&self) -> PlaybackMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: PlaybackMode) {
+        self.mode = mode;
+    }
+
+    pub fn add_frame(&mut self, texture: &Texture, region: Region, duration: f32) {
+        self.frames.push(AnimationFrame {
+            texture: *texture,
+            region,
+            duration: duration.max(MIN_FRAME_DURATION),
+            event: None
+        });
+    }
+
+    // Reaching the frame reports AnimationEvent::Frame with this
+    // name, None removes it.
+    pub fn set_frame_event(&mut self, frame: usize, event: Option<&str>) {
+        if let Some(frame) = self.frames.get_mut(frame) {
+            frame.event = event.map(String::from);
+        }
+    }
+
+    pub fn set_frame_duration(&mut self, frame: usize, duration: f32) {
+        if let Some(frame) = self.frames.get_mut(frame) {
+            frame.duration = duration.max(MIN_FRAME_DURATION);
+        }
+    }
+
+    // Seconds at speed 1 before playback is back on the same frame
+    // heading the same way. PingPong passes the end frames once per
+    // cycle and everything between twice.
+    fn cycle_duration(&self) -> f32 {
+        let total: f32 = self.frames.iter().map(|frame| frame.duration).sum();
+        match (self.mode, self.frames.first(), self.frames.last()) {
+            (PlaybackMode::PingPong, Some(first), Some(last)) if self.frames.len() > 1 =>
+                2. * total - first.duration - last.duration,
+            _ => total
+        }
+    }
+}
+
+// Plays one clip at a time out of the clips it holds.
+pub struct AnimationPlayer {
+    clips: Vec<AnimationClip>,
+    current: Option<usize>,
+    frame: usize,
+    // Seconds spent on the current frame.
+    elapsed: f32,
+    // PingPong travel, 1 forwards or -1 backwards.
+    direction: i32,
+    speed: f32,
+    playing: bool,
+    finished: bool,
+    // Events for frames entered outside advance, e.g. by play.
+    pending: Vec<AnimationEvent>
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> AnimationPlayer {
+        AnimationPlayer::new()
+    }
+}
+
+impl AnimationPlayer {
+
+    pub fn new() -> AnimationPlayer {
+        AnimationPlayer {
+            clips: vec![],
+            current: None,
+            frame: 0,
+            elapsed: 0.,
+            direction: 1,
+            speed: 1.,
+            playing: false,
+            finished: false,
+            pending: vec![]
+        }
+    }
+
+    // Replaces a clip with the same name, restarting it if it was
+    // the one playing.
+    pub fn add_clip(&mut self, clip: AnimationClip) {
+        match self.clips.iter().position(|existing| existing.name == clip.name) {
+            Some(index) => {
+                self.clips[index] = clip;
+                if self.current == Some(index) {
+                    if self.clips[index].frames.is_empty() {
+                        self.stop();
+                    } else {
+                        self.restart();
+                    }
+                }
+            }
+            None => self.clips.push(clip)
+        }
+    }
+
+    pub fn get_clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.iter().find(|clip| clip.name == name)
+    }
+
+    pub fn get_clip_mut(&mut self, name: &str) -> Option<&mut AnimationClip> {
+        self.clips.iter_mut().find(|clip| clip.name == name)
+    }
+
+    pub fn get_current_clip(&self) -> Option<&AnimationClip> {
+        self.current.map(|index| &self.clips[index])
+    }
+
+    pub fn get_current_frame(&self) -> Option<&AnimationFrame> {
+        self.get_current_clip().and_then(|clip| clip.frames.get(self.frame))
+    }
+
+    pub fn get_frame_index(&self) -> usize {
+        self.frame
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // 2 plays twice as fast, 0 holds the current frame.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.);
+    }
+
+    // Starts the clip from its first frame, unless it is already the
+    // one playing. False when there is no clip with that name or it
+    // has no frames.
+    pub fn play(&mut self, name: &str) -> bool {
+        let Some(index) = self.clips.iter().position(|clip| clip.name == name) else {
+            return false;
+        };
+        if self.clips[index].frames.is_empty() {
+            return false;
+        }
+        if self.current == Some(index) && self.playing {
+            return true;
+        }
+        self.current = Some(index);
+        self.restart();
+        true
+    }
+
+    // Back to the first frame of the current clip and playing.
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.;
+        self.direction = 1;
+        self.finished = false;
+        self.playing = self.current.is_some();
+        self.enter_frame();
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    // Continues a paused clip, finished Once clips stay finished.
+    pub fn resume(&mut self) {
+        self.playing = self.current.is_some() && !self.finished;
+    }
+
+    // Stops and forgets the clip.
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.playing = false;
+        self.pending.clear();
+    }
+
+    // Jumps to a frame of the current clip without reporting events
+    // for the frames skipped.
+    pub fn seek(&mut self, frame: usize) {
+        let Some(clip) = self.get_current_clip() else {
+            return;
+        };
+        self.frame = frame.min(clip.frames.len() - 1);
+        self.elapsed = 0.;
+        self.enter_frame();
+    }
+
+    // Moves time on by dt seconds, scaled by speed. Events come
+    // back in the order they happened.
+    pub fn advance(&mut self, dt: f32) -> Vec<AnimationEvent> {
+        let mut events = std::mem::take(&mut self.pending);
+        let Some(index) = self.current else {
+            return events;
+        };
+        if !self.playing {
+            return events;
+        }
+
+        self.elapsed += dt.max(0.) * self.speed;
+
+        // A whole cycle ends on the frame and direction it started
+        // from, so all but one are dropped and the loop below never
+        // runs more than two cycles' frames. Their events would only
+        // repeat the ones the remaining cycle reports.
+        let clip = &self.clips[index];
+        let cycle = clip.cycle_duration();
+        if clip.mode != PlaybackMode::Once && self.elapsed >= 2. * cycle {
+            self.elapsed = cycle + self.elapsed.rem_euclid(cycle);
+        }
+
+        loop {
+            let clip = &self.clips[index];
+            let duration = clip.frames[self.frame].duration;
+            if self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+
+            let last = clip.frames.len() - 1;
+            match clip.mode {
+                PlaybackMode::Loop => {
+                    if self.frame == last {
+                        self.frame = 0;
+                        events.push(AnimationEvent::Looped { clip: clip.name.clone() });
+                    } else {
+                        self.frame += 1;
+                    }
+                }
+                PlaybackMode::Once => {
+                    if self.frame == last {
+                        self.elapsed = duration;
+                        self.playing = false;
+                        self.finished = true;
+                        events.push(AnimationEvent::Finished { clip: clip.name.clone() });
+                        break;
+                    }
+                    self.frame += 1;
+                }
+                PlaybackMode::PingPong => {
+                    if last == 0 {
+                        events.push(AnimationEvent::Looped { clip: clip.name.clone() });
+                        continue;
+                    }
+                    if (self.frame == last && self.direction > 0) || (self.frame == 0 && self.direction < 0) {
+                        self.direction = -self.direction;
+                    }
+                    self.frame = (self.frame as i32 + self.direction) as usize;
+                    if self.frame == 0 {
+                        events.push(AnimationEvent::Looped { clip: clip.name.clone() });
+                    }
+                }
+            }
+
+            self.enter_frame();
+            events.append(&mut self.pending);
+        }
+        events
+    }
+
+    fn enter_frame(&mut self) {
+        let Some(clip) = self.get_current_clip() else {
+            return;
+        };
+        if let Some(name) = clip.frames[self.frame].get_event() {
+            let event = AnimationEvent::Frame {
+                clip: clip.name.clone(),
+                frame: self.frame,
+                name: name.to_string()
+            };
+            self.pending.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::software::SoftwareBackend;
+
+    fn clip(mode: PlaybackMode, frames: usize) -> AnimationClip {
+        let mut context = SoftwareBackend::new(1, 1);
+        let texture = Texture::from_rgba8(&mut context, 1, 1, vec![255; 4]).unwrap();
+        let mut clip = AnimationClip::new("clip", mode);
+        for _ in 0..frames {
+            clip.add_frame(&texture, Region { u_min: 0., v_min: 0., u_max: 1., v_max: 1. }, 0.1);
+        }
+        clip
+    }
+
+    fn player(mode: PlaybackMode, frames: usize) -> AnimationPlayer {
+        let mut player = AnimationPlayer::new();
+        player.add_clip(clip(mode, frames));
+        assert!(player.play("clip"));
+        player
+    }
+
+    // Frame after each of the given advances.
+    fn frames_after(player: &mut AnimationPlayer, steps: &[f32]) -> Vec<usize> {
+        steps.iter().map(|dt| { player.advance(*dt); player.get_frame_index() }).collect()
+    }
+
+    fn looped() -> AnimationEvent {
+        AnimationEvent::Looped { clip: "clip".to_string() }
+    }
+
+    #[test]
+    fn loop_wraps_to_the_first_frame() {
+        let mut player = player(PlaybackMode::Loop, 3);
+        assert_eq!(frames_after(&mut player, &[0.05, 0.1, 0.1, 0.1]), vec![0, 1, 2, 0]);
+        assert_eq!(player.advance(0.45), vec![looped()]);
+        assert_eq!(player.get_frame_index(), 2);
+        assert!(player.is_playing());
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame() {
+        let mut player = player(PlaybackMode::Once, 3);
+        assert_eq!(frames_after(&mut player, &[0.15, 0.1]), vec![1, 2]);
+        assert_eq!(player.advance(0.1), vec![AnimationEvent::Finished { clip: "clip".to_string() }]);
+        assert_eq!(player.get_frame_index(), 2);
+        assert!(player.is_finished() && !player.is_playing());
+        assert_eq!(player.advance(1.), vec![]);
+        assert_eq!(player.get_frame_index(), 2);
+    }
+
+    #[test]
+    fn ping_pong_does_not_repeat_the_end_frames() {
+        let mut player = player(PlaybackMode::PingPong, 4);
+        let frames = frames_after(&mut player, &[0.05; 14]);
+        assert_eq!(frames, vec![0, 1, 1, 2, 2, 3, 3, 2, 2, 1, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn ping_pong_loops_on_reaching_the_first_frame() {
+        let mut player = player(PlaybackMode::PingPong, 3);
+        assert_eq!(player.advance(0.35), vec![]);
+        assert_eq!(player.get_frame_index(), 1);
+        assert_eq!(player.advance(0.1), vec![looped()]);
+        assert_eq!(player.get_frame_index(), 0);
+    }
+
+    #[test]
+    fn single_frame_clips_stay_put() {
+        for mode in [PlaybackMode::Loop, PlaybackMode::PingPong] {
+            let mut player = player(mode, 1);
+            assert_eq!(player.advance(0.05), vec![]);
+            assert_eq!(player.advance(0.1), vec![looped()]);
+            assert_eq!(player.get_frame_index(), 0);
+        }
+
+        let mut player = player(PlaybackMode::Once, 1);
+        assert_eq!(player.advance(0.1), vec![AnimationEvent::Finished { clip: "clip".to_string() }]);
+        assert_eq!(player.get_frame_index(), 0);
+    }
+
+    // Whole cycles are skipped rather than stepped through.
+    #[test]
+    fn long_advance_lands_on_the_same_frame() {
+        let mut stepped = player(PlaybackMode::PingPong, 4);
+        let mut jumped = player(PlaybackMode::PingPong, 4);
+        for _ in 0..25 {
+            stepped.advance(0.07);
+        }
+        jumped.advance(1.75);
+        assert_eq!(stepped.get_frame_index(), 1);
+        assert_eq!(jumped.get_frame_index(), 1);
+
+        let mut player = player(PlaybackMode::Loop, 3);
+        assert_eq!(player.advance(1000.05), vec![looped()]);
+        assert_eq!(player.get_frame_index(), 1);
+    }
+
+    #[test]
+    fn frame_events_are_reported_in_order() {
+        let mut player = AnimationPlayer::new();
+        let mut clip = clip(PlaybackMode::Loop, 3);
+        clip.set_frame_event(0, Some("start"));
+        clip.set_frame_event(2, Some("hit"));
+        player.add_clip(clip);
+        player.play("clip");
+
+        let frame = |frame: usize, name: &str| AnimationEvent::Frame { clip: "clip".to_string(), frame, name: name.to_string() };
+        assert_eq!(player.advance(0.), vec![frame(0, "start")]);
+        assert_eq!(player.advance(0.3), vec![frame(2, "hit"), looped(), frame(0, "start")]);
+    }
+
+    #[test]
+    fn pause_resume_and_seek() {
+        let mut looping = player(PlaybackMode::Loop, 3);
+        looping.advance(0.15);
+        looping.pause();
+        assert_eq!(looping.advance(1.), vec![]);
+        assert_eq!(looping.get_frame_index(), 1);
+
+        looping.resume();
+        assert!(looping.is_playing());
+        assert_eq!(frames_after(&mut looping, &[0.05]), vec![2]);
+
+        // Seeking starts the frame over and clamps to the clip.
+        looping.seek(0);
+        assert_eq!(frames_after(&mut looping, &[0.09]), vec![0]);
+        looping.seek(10);
+        assert_eq!(looping.get_frame_index(), 2);
+
+        let mut finished = player(PlaybackMode::Once, 2);
+        finished.advance(1.);
+        finished.resume();
+        assert!(finished.is_finished() && !finished.is_playing());
+    }
+}
@@ -1,60 +1,45 @@
-use miniquad::{Bindings, KeyCode, Pipeline, RenderingBackend};
-use crate::shapes::color::Color;
-use crate::shapes::shape::{ Shape };
+use crate::backend::drawing_context::DrawingContext;
+use crate::shapes::draw_parameters::DrawParameters;
+use crate::shapes::shape::{BaseShape, Shape};
 use crate::shapes::square::{ Square};
 
+// Below anything left on the default layer 0.
+pub const BACKGROUND_LAYER: i32 = -100;
+
 pub struct Background {
     base_details: Square,
-    uniforms: Color
+    locked_to_camera: bool
 }
 
 impl Shape for Background {
 
-    fn get_bindings(&self) -> &Bindings {
-        &self.base_details.get_bindings()
-    }
-
-    fn get_pipeline(&self) -> &Pipeline {
-        &self.base_details.get_pipeline()
-    }
-
-    fn get_segments(&self) -> i32 {
-        self.base_details.get_segments()
-    }
-
-    fn set_binding(&mut self, bindings: Bindings) {
-        self.base_details.set_binding(bindings);
+    fn base(&self) -> &BaseShape {
+        self.base_details.base()
     }
 
-    fn set_pipeline(&mut self, pipeline: Pipeline) {
-        self.base_details.set_pipeline(pipeline);
+    fn base_mut(&mut self) -> &mut BaseShape {
+        self.base_details.base_mut()
     }
 
-    fn set_segments(&mut self, segments: i32) {
-        self.base_details.set_segments(segments);
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, _draw: bool) {
+        self.base_details.draw(drawing_context, parameters, true);
     }
 
-    fn draw(&mut self, drawing_context: &mut Box<dyn RenderingBackend>, draw: bool) {
-        self.base_details.draw(drawing_context, true);
+    fn is_locked_to_camera(&self) -> bool {
+        self.locked_to_camera
     }
-
-    fn input_down(&mut self, key_code: KeyCode) {}
-
-    fn input_up(&mut self, key_code: KeyCode) {}
 }
 impl Background {
 
-    pub fn new(context: &mut Box<dyn RenderingBackend>,
+    pub fn new(context: &mut dyn DrawingContext,
                red: f32,
                green: f32,
                blue: f32) -> Background {
 
-        Background {
+        let mut background = Background {
             base_details:
-                // Static for now(800x600), needs to be based on the window
-                // configuration i.e window width + height.
-                // Realistically need a camera object for advanced viewing, which
-                // this background would stick to when active.
+                // Covers the whole of NDC, by default it sticks to the
+                // camera so panning or zooming never reveals an edge.
                 Square::new(
                     context,
                     -0.95f32,
@@ -65,12 +50,15 @@ impl Background {
                     green,
                     blue
                 ),
-            uniforms:
-                Color::new(
-                    red,
-                    green,
-                    blue,
-                )
-        }
+            locked_to_camera: true
+        };
+        background.set_layer(BACKGROUND_LAYER);
+        background
+    }
+
+    // Unlocked, the background is a world object the camera can
+    // move across like any other shape.
+    pub fn set_locked_to_camera(&mut self, locked_to_camera: bool) {
+        self.locked_to_camera = locked_to_camera;
     }
 }
\ No newline at end of file
@@ -0,0 +1,83 @@
+use crate::backend::drawing_context::DrawingContext;
+use crate::shapes::ellipse::Ellipse;
+use crate::shapes::color::Color;
+use crate::shapes::draw_parameters::DrawParameters;
+use crate::shapes::shape::{BaseShape, Shape};
+
+pub struct Circle {
+    base_details: Ellipse
+}
+
+impl Shape for Circle {
+
+    fn base(&self) -> &BaseShape {
+        self.base_details.base()
+    }
+
+    fn base_mut(&mut self) -> &mut BaseShape {
+        self.base_details.base_mut()
+    }
+
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, draw: bool) {
+        self.base_details.draw(drawing_context, parameters, draw);
+    }
+
+    fn set_segments(&mut self, segments: i32) {
+        self.base_details.set_segments(segments);
+    }
+}
+
+impl Circle {
+
+    pub fn get_uniform(&mut self) -> &mut Color {
+        self.base_details.get_uniform()
+    }
+
+    pub fn get_segment_count(&self) -> i32 {
+        self.base_details.get_segment_count()
+    }
+
+    pub fn set_segment_count(&mut self, segment_count: i32) {
+        self.base_details.set_segment_count(segment_count);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(context: &mut dyn DrawingContext,
+               x: f32,
+               y: f32,
+               radius: f32,
+               segment_count: i32,
+               red: f32,
+               green: f32,
+               blue: f32) -> Circle {
+
+        Circle {
+            base_details:
+                Ellipse::new(
+                    context,
+                    x,
+                    y,
+                    radius,
+                    radius,
+                    segment_count,
+                    red,
+                    green,
+                    blue
+                )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::software::SoftwareBackend;
+
+    #[test]
+    fn set_segments_reaches_the_ellipse() {
+        let mut context = SoftwareBackend::new(1, 1);
+        let mut circle = Circle::new(&mut context, 0., 0., 0.5, 8, 1., 1., 1.);
+        circle.set_segments(30);
+        assert_eq!(circle.get_segment_count(), 10);
+    }
+}
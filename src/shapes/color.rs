@@ -1,16 +1,105 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::shapes::color_names::NAMED_COLORS;
+
+// Straight (not premultiplied) RGBA, every channel 0..1. Channels
+// are sRGB encoded unless a method says otherwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Color {
     red: f32,
     green: f32,
-    blue: f32
+    blue: f32,
+    alpha: f32
 }
 
 impl Color {
     pub fn new(red: f32, green: f32, blue: f32) -> Color {
+        Color::new_rgba(red, green, blue, 1.)
+    }
+
+    pub fn new_rgba(red: f32, green: f32, blue: f32, alpha: f32) -> Color {
         Color {
             red,
             green,
-            blue
+            blue,
+            alpha
+        }
+    }
+
+    // #RGB, #RGBA, #RRGGBB or #RRGGBBAA, the # is optional.
+    pub fn from_hex(hex: &str) -> Result<Color, ColorParseError> {
+        let trimmed = hex.trim();
+        let digits = trimmed.strip_prefix('#').unwrap_or(trimmed);
+        let invalid = || ColorParseError::InvalidHex(hex.to_string());
+        if !digits.chars().all(|digit| digit.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let channel = |index: usize, width: usize| {
+            let value = u8::from_str_radix(&digits[index * width..(index + 1) * width], 16).unwrap();
+            // Short form digits repeat, f is ff.
+            let value = if width == 1 { value * 17 } else { value };
+            value as f32 / 255.
+        };
+
+        match digits.len() {
+            3 => Ok(Color::new(channel(0, 1), channel(1, 1), channel(2, 1))),
+            4 => Ok(Color::new_rgba(channel(0, 1), channel(1, 1), channel(2, 1), channel(3, 1))),
+            6 => Ok(Color::new(channel(0, 2), channel(1, 2), channel(2, 2))),
+            8 => Ok(Color::new_rgba(channel(0, 2), channel(1, 2), channel(2, 2), channel(3, 2))),
+            _ => Err(invalid())
+        }
+    }
+
+    // Packed 0xRRGGBB, opaque.
+    pub fn from_rgb_u32(rgb: u32) -> Color {
+        Color::new(
+            ((rgb >> 16) & 0xFF) as f32 / 255.,
+            ((rgb >> 8) & 0xFF) as f32 / 255.,
+            (rgb & 0xFF) as f32 / 255.
+        )
+    }
+
+    // CSS colour names, case insensitive.
+    pub fn from_name(name: &str) -> Option<Color> {
+        let name = name.trim().to_ascii_lowercase();
+        if name == "transparent" {
+            return Some(Color::new_rgba(0., 0., 0., 0.));
+        }
+        NAMED_COLORS
+            .binary_search_by(|(candidate, _)| candidate.cmp(&name.as_str()))
+            .ok()
+            .map(|index| Color::from_rgb_u32(NAMED_COLORS[index].1))
+    }
+
+    // Anything CSS accepts for a colour: hex, names, rgb()/rgba() and
+    // hsl()/hsla(), with either comma or space separated arguments.
+    pub fn parse(input: &str) -> Result<Color, ColorParseError> {
+        let trimmed = input.trim();
+        let lowercase = trimmed.to_ascii_lowercase();
+
+        if lowercase.starts_with('#') {
+            return Color::from_hex(trimmed);
+        }
+        if let Some(arguments) = function_arguments(&lowercase, &["rgb", "rgba"]) {
+            return parse_rgb(&arguments).ok_or_else(|| ColorParseError::InvalidFunction(input.to_string()));
+        }
+        if let Some(arguments) = function_arguments(&lowercase, &["hsl", "hsla"]) {
+            return parse_hsl(&arguments).ok_or_else(|| ColorParseError::InvalidFunction(input.to_string()));
         }
+        Color::from_name(&lowercase).ok_or_else(|| ColorParseError::UnknownName(input.to_string()))
+    }
+
+    // Hue in degrees, saturation and value 0..1.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32, alpha: f32) -> Color {
+        let chroma = value * saturation;
+        from_hue_chroma(hue, chroma, value - chroma, alpha)
+    }
+
+    // Hue in degrees, saturation and lightness 0..1.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Color {
+        let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+        from_hue_chroma(hue, chroma, lightness - chroma * 0.5, alpha)
     }
 
     pub fn get_red(&self) -> f32 {
@@ -25,6 +114,10 @@ impl Color {
         self.blue
     }
 
+    pub fn get_alpha(&self) -> f32 {
+        self.alpha
+    }
+
     pub fn set_red(&mut self, red: f32) {
         self.red = red
     }
@@ -36,4 +129,421 @@ impl Color {
     pub fn set_blue(&mut self, blue: f32) {
         self.blue = blue;
     }
-}
\ No newline at end of file
+
+    pub fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+
+    // (hue, saturation, value), hue is 0 for greys.
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (max, min) = self.extremes();
+        let saturation = if max == 0. { 0. } else { (max - min) / max };
+        (self.hue(), saturation, max)
+    }
+
+    // (hue, saturation, lightness), hue is 0 for greys.
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (max, min) = self.extremes();
+        let lightness = (max + min) * 0.5;
+        let saturation = if max == min {
+            0.
+        } else {
+            (max - min) / (1. - (2. * lightness - 1.).abs())
+        };
+        (self.hue(), saturation, lightness)
+    }
+
+    // Decodes the sRGB channels, alpha is already linear.
+    pub fn to_linear(self) -> Color {
+        Color::new_rgba(
+            srgb_to_linear(self.red),
+            srgb_to_linear(self.green),
+            srgb_to_linear(self.blue),
+            self.alpha
+        )
+    }
+
+    // Inverse of to_linear, self holds linear channels.
+    pub fn to_srgb(self) -> Color {
+        Color::new_rgba(
+            linear_to_srgb(self.red),
+            linear_to_srgb(self.green),
+            linear_to_srgb(self.blue),
+            self.alpha
+        )
+    }
+
+    // #RRGGBB, or #RRGGBBAA when not opaque.
+    pub fn to_hex(self) -> String {
+        let byte = |channel: f32| (channel.clamp(0., 1.) * 255.).round() as u8;
+        let rgb = format!("#{:02X}{:02X}{:02X}", byte(self.red), byte(self.green), byte(self.blue));
+        if byte(self.alpha) == 255 {
+            rgb
+        } else {
+            format!("{}{:02X}", rgb, byte(self.alpha))
+        }
+    }
+
+    // t of 0 gives self, 1 gives other. Hue takes the short way
+    // round, a grey end borrows the other end's hue so fading to
+    // white does not sweep through the rainbow.
+    pub fn interpolate(&self, other: &Color, t: f32, space: ColorSpace) -> Color {
+        let alpha = lerp(self.alpha, other.alpha, t);
+
+        match space {
+            ColorSpace::Srgb => Color::new_rgba(
+                lerp(self.red, other.red, t),
+                lerp(self.green, other.green, t),
+                lerp(self.blue, other.blue, t),
+                alpha
+            ),
+            ColorSpace::Linear => {
+                let (from, to) = (self.to_linear(), other.to_linear());
+                Color::new_rgba(
+                    lerp(from.red, to.red, t),
+                    lerp(from.green, to.green, t),
+                    lerp(from.blue, to.blue, t),
+                    alpha
+                ).to_srgb()
+            }
+            ColorSpace::Hsv => {
+                let (from, to) = (self.to_hsv(), other.to_hsv());
+                let hue = lerp_hue(from, to, t);
+                Color::from_hsv(hue, lerp(from.1, to.1, t), lerp(from.2, to.2, t), alpha)
+            }
+            ColorSpace::Hsl => {
+                let (from, to) = (self.to_hsl(), other.to_hsl());
+                let hue = lerp_hue(from, to, t);
+                Color::from_hsl(hue, lerp(from.1, to.1, t), lerp(from.2, to.2, t), alpha)
+            }
+        }
+    }
+
+    fn extremes(&self) -> (f32, f32) {
+        (
+            self.red.max(self.green).max(self.blue),
+            self.red.min(self.green).min(self.blue)
+        )
+    }
+
+    // Degrees 0..360, shared by HSV and HSL.
+    fn hue(&self) -> f32 {
+        let (max, min) = self.extremes();
+        let delta = max - min;
+        if delta == 0. {
+            return 0.;
+        }
+
+        let hue = if max == self.red {
+            ((self.green - self.blue) / delta).rem_euclid(6.)
+        } else if max == self.green {
+            (self.blue - self.red) / delta + 2.
+        } else {
+            (self.red - self.green) / delta + 4.
+        };
+        hue * 60.
+    }
+}
+
+impl FromStr for Color {
+    type Err = ColorParseError;
+
+    fn from_str(input: &str) -> Result<Color, ColorParseError> {
+        Color::parse(input)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorSpace {
+    // Straight on the stored channels, what CSS transitions do.
+    Srgb,
+    // Physically even blends, no dark band between complements.
+    Linear,
+    Hsv,
+    Hsl
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ColorParseError {
+    InvalidHex(String),
+    UnknownName(String),
+    InvalidFunction(String),
+}
+
+impl fmt::Display for ColorParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorParseError::InvalidHex(input) =>
+                write!(f, "'{}' is not a #RGB, #RGBA, #RRGGBB or #RRGGBBAA colour", input),
+            ColorParseError::UnknownName(input) =>
+                write!(f, "'{}' is not a known colour name", input),
+            ColorParseError::InvalidFunction(input) =>
+                write!(f, "'{}' is not a valid rgb() or hsl() colour", input),
+        }
+    }
+}
+
+pub fn srgb_to_linear(channel: f32) -> f32 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(channel: f32) -> f32 {
+    if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1. / 2.4) - 0.055
+    }
+}
+
+fn lerp(from: f32, to: f32, t: f32) -> f32 {
+    from + (to - from) * t
+}
+
+// Takes (hue, saturation, _) pairs so greys can be spotted.
+fn lerp_hue(from: (f32, f32, f32), to: (f32, f32, f32), t: f32) -> f32 {
+    let (from_hue, to_hue) = match (from.1 == 0., to.1 == 0.) {
+        (true, false) => (to.0, to.0),
+        (false, true) => (from.0, from.0),
+        _ => (from.0, to.0)
+    };
+    let delta = (to_hue - from_hue + 180.).rem_euclid(360.) - 180.;
+    (from_hue + delta * t).rem_euclid(360.)
+}
+
+fn from_hue_chroma(hue: f32, chroma: f32, lightest: f32, alpha: f32) -> Color {
+    let sector = hue.rem_euclid(360.) / 60.;
+    let second = chroma * (1. - (sector % 2. - 1.).abs());
+
+    let (red, green, blue) = match sector as u32 {
+        0 => (chroma, second, 0.),
+        1 => (second, chroma, 0.),
+        2 => (0., chroma, second),
+        3 => (0., second, chroma),
+        4 => (second, 0., chroma),
+        _ => (chroma, 0., second)
+    };
+    Color::new_rgba(red + lightest, green + lightest, blue + lightest, alpha)
+}
+
+// "rgb(1 2 3)" -> ["1", "2", "3"], commas and the "/" before
+// alpha are just separators here.
+fn function_arguments(input: &str, names: &[&str]) -> Option<Vec<String>> {
+    let open = input.find('(')?;
+    if !names.contains(&input[..open].trim()) || !input.ends_with(')') {
+        return None;
+    }
+
+    Some(input[open + 1..input.len() - 1]
+        .split(|character: char| character == ',' || character == '/' || character.is_whitespace())
+        .filter(|argument| !argument.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+// Plain numbers are divided by scale, percentages by 100.
+fn parse_component(argument: &str, scale: f32) -> Option<f32> {
+    let value = match argument.strip_suffix('%') {
+        Some(percentage) => percentage.parse::<f32>().ok()? / 100.,
+        None => argument.parse::<f32>().ok()? / scale
+    };
+    Some(value.clamp(0., 1.))
+}
+
+fn parse_alpha(arguments: &[String]) -> Option<f32> {
+    match arguments.get(3) {
+        Some(alpha) => parse_component(alpha, 1.),
+        None => Some(1.)
+    }
+}
+
+fn parse_rgb(arguments: &[String]) -> Option<Color> {
+    if arguments.len() != 3 && arguments.len() != 4 {
+        return None;
+    }
+    Some(Color::new_rgba(
+        parse_component(&arguments[0], 255.)?,
+        parse_component(&arguments[1], 255.)?,
+        parse_component(&arguments[2], 255.)?,
+        parse_alpha(arguments)?
+    ))
+}
+
+// Hue accepts deg, rad and turn units, saturation and lightness
+// are percentages (the % may be left off).
+fn parse_hsl(arguments: &[String]) -> Option<Color> {
+    if arguments.len() != 3 && arguments.len() != 4 {
+        return None;
+    }
+
+    let hue = &arguments[0];
+    let hue = if let Some(degrees) = hue.strip_suffix("deg") {
+        degrees.parse::<f32>().ok()?
+    } else if let Some(radians) = hue.strip_suffix("rad") {
+        radians.parse::<f32>().ok()?.to_degrees()
+    } else if let Some(turns) = hue.strip_suffix("turn") {
+        turns.parse::<f32>().ok()? * 360.
+    } else {
+        hue.parse::<f32>().ok()?
+    };
+    let percentage = |argument: &str| {
+        let number = argument.strip_suffix('%').unwrap_or(argument);
+        Some((number.parse::<f32>().ok()? / 100.).clamp(0., 1.))
+    };
+
+    Some(Color::from_hsl(
+        hue,
+        percentage(&arguments[1])?,
+        percentage(&arguments[2])?,
+        parse_alpha(arguments)?
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Color, expected: Color) {
+        let channels = |color: Color| [color.red, color.green, color.blue, color.alpha];
+        for (a, e) in channels(actual).iter().zip(channels(expected).iter()) {
+            assert!((a - e).abs() < 1e-3, "{:?} is not {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn hex() {
+        assert_close(Color::from_hex("#fff").unwrap(), Color::new(1., 1., 1.));
+        assert_close(Color::from_hex("#f008").unwrap(), Color::new_rgba(1., 0., 0., 136. / 255.));
+        assert_close(Color::from_hex("#336699").unwrap(), Color::new(0.2, 0.4, 0.6));
+        assert_close(Color::from_hex("33669980").unwrap(), Color::new_rgba(0.2, 0.4, 0.6, 128. / 255.));
+        assert_close(Color::parse(" #00FF00 ").unwrap(), Color::new(0., 1., 0.));
+        assert_eq!(Color::from_hex("#12345").unwrap_err(), ColorParseError::InvalidHex("#12345".to_string()));
+        assert!(Color::from_hex("#ggg").is_err());
+        // Only one # is allowed.
+        assert!(Color::from_hex("##fff").is_err());
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(Color::from_hex("#336699").unwrap().to_hex(), "#336699");
+        assert_eq!(Color::from_hex("#33669980").unwrap().to_hex(), "#33669980");
+    }
+
+    #[test]
+    fn rgb() {
+        assert_close(Color::parse("rgb(255, 0, 0)").unwrap(), Color::new(1., 0., 0.));
+        assert_close(Color::parse("rgb(0 51 102)").unwrap(), Color::new(0., 0.2, 0.4));
+        assert_close(Color::parse("RGB(100%, 50%, 0%)").unwrap(), Color::new(1., 0.5, 0.));
+        assert_close(Color::parse("rgba(255, 255, 255, 0.5)").unwrap(), Color::new_rgba(1., 1., 1., 0.5));
+        assert_close(Color::parse("rgb(0 0 255 / 25%)").unwrap(), Color::new_rgba(0., 0., 1., 0.25));
+        assert!(matches!(Color::parse("rgb(1, 2)"), Err(ColorParseError::InvalidFunction(_))));
+        assert!(matches!(Color::parse("rgb(red, 0, 0)"), Err(ColorParseError::InvalidFunction(_))));
+    }
+
+    #[test]
+    fn hsl() {
+        assert_close(Color::parse("hsl(0, 100%, 50%)").unwrap(), Color::new(1., 0., 0.));
+        assert_close(Color::parse("hsl(120deg 100% 25%)").unwrap(), Color::new(0., 0.5, 0.));
+        assert_close(Color::parse("hsl(0.5turn, 100%, 50%)").unwrap(), Color::new(0., 1., 1.));
+        assert_close(Color::parse("hsla(240, 100, 50, 0.5)").unwrap(), Color::new_rgba(0., 0., 1., 0.5));
+        assert_close(Color::parse("hsl(0, 0%, 100%)").unwrap(), Color::new(1., 1., 1.));
+        assert!(matches!(Color::parse("hsl(0, 100%%, 50%)"), Err(ColorParseError::InvalidFunction(_))));
+    }
+
+    #[test]
+    fn named() {
+        assert_close(Color::parse("rebeccapurple").unwrap(), Color::from_rgb_u32(0x663399));
+        assert_close(Color::parse("Lime").unwrap(), Color::new(0., 1., 0.));
+        assert_close(Color::from_name(" navy ").unwrap(), Color::from_rgb_u32(0x000080));
+        assert_close(Color::parse("transparent").unwrap(), Color::new_rgba(0., 0., 0., 0.));
+        assert_eq!(Color::parse("blurple").unwrap_err(), ColorParseError::UnknownName("blurple".to_string()));
+    }
+
+    fn assert_triple(actual: (f32, f32, f32), expected: (f32, f32, f32)) {
+        let close = |a: f32, e: f32| (a - e).abs() < 1e-3;
+        assert!(close(actual.0, expected.0) && close(actual.1, expected.1) && close(actual.2, expected.2),
+                "{:?} is not {:?}", actual, expected);
+    }
+
+    #[test]
+    fn hsv_and_hsl_of_primaries() {
+        for (color, hue) in [
+            (Color::new(1., 0., 0.), 0.),
+            (Color::new(1., 1., 0.), 60.),
+            (Color::new(0., 1., 0.), 120.),
+            (Color::new(0., 1., 1.), 180.),
+            (Color::new(0., 0., 1.), 240.),
+            (Color::new(1., 0., 1.), 300.)
+        ] {
+            assert_triple(color.to_hsv(), (hue, 1., 1.));
+            assert_triple(color.to_hsl(), (hue, 1., 0.5));
+            assert_close(Color::from_hsv(hue, 1., 1., 1.), color);
+            assert_close(Color::from_hsl(hue, 1., 0.5, 1.), color);
+        }
+    }
+
+    #[test]
+    fn greys_have_no_hue() {
+        for value in [0., 0.5, 1.] {
+            let grey = Color::new(value, value, value);
+            assert_triple(grey.to_hsv(), (0., 0., value));
+            assert_triple(grey.to_hsl(), (0., 0., value));
+        }
+    }
+
+    #[test]
+    fn hsv_and_hsl_round_trip() {
+        for color in [Color::from_rgb_u32(0x663399), Color::from_rgb_u32(0xFF8800), Color::new_rgba(0.2, 0.7, 0.4, 0.5)] {
+            let (hue, saturation, value) = color.to_hsv();
+            assert_close(Color::from_hsv(hue, saturation, value, color.alpha), color);
+            let (hue, saturation, lightness) = color.to_hsl();
+            assert_close(Color::from_hsl(hue, saturation, lightness, color.alpha), color);
+        }
+        // Hue wraps rather than clamps.
+        assert_close(Color::from_hsv(480., 1., 1., 1.), Color::new(0., 1., 0.));
+        assert_close(Color::from_hsl(-120., 1., 0.5, 1.), Color::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn linear_and_srgb() {
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+        assert!((linear_to_srgb(0.214) - 0.5).abs() < 1e-3);
+        assert_close(Color::new_rgba(0.5, 0., 1., 0.5).to_linear(), Color::new_rgba(0.214, 0., 1., 0.5));
+
+        // Both curves meet the straight segment near black.
+        assert_eq!(srgb_to_linear(0.), 0.);
+        assert!((srgb_to_linear(0.02) - 0.02 / 12.92).abs() < 1e-6);
+        for channel in [0., 0.01, 0.04045, 0.2, 0.5, 0.8, 1.] {
+            assert!((linear_to_srgb(srgb_to_linear(channel)) - channel).abs() < 1e-5, "{}", channel);
+        }
+        let color = Color::from_rgb_u32(0x336699);
+        assert_close(color.to_linear().to_srgb(), color);
+    }
+
+    #[test]
+    fn interpolation_in_each_space() {
+        let (red, blue) = (Color::new(1., 0., 0.), Color::new(0., 0., 1.));
+        let (black, white) = (Color::new(0., 0., 0.), Color::new(1., 1., 1.));
+
+        for space in [ColorSpace::Srgb, ColorSpace::Linear, ColorSpace::Hsv, ColorSpace::Hsl] {
+            assert_close(red.interpolate(&blue, 0., space), red);
+            assert_close(red.interpolate(&blue, 1., space), blue);
+        }
+
+        assert_close(red.interpolate(&blue, 0.5, ColorSpace::Srgb), Color::new(0.5, 0., 0.5));
+        // Half the light of white, brighter than sRGB's 0.5.
+        let middle = linear_to_srgb(0.5);
+        assert_close(black.interpolate(&white, 0.5, ColorSpace::Linear), Color::new(middle, middle, middle));
+        // 0 to 240 degrees the short way is through magenta.
+        assert_close(red.interpolate(&blue, 0.5, ColorSpace::Hsv), Color::new(1., 0., 1.));
+        assert_close(red.interpolate(&Color::new(0., 1., 0.), 0.5, ColorSpace::Hsl), Color::new(1., 1., 0.));
+        // White keeps red's hue, so half way is pink.
+        assert_close(red.interpolate(&white, 0.5, ColorSpace::Hsv), Color::new(1., 0.5, 0.5));
+
+        let faded = Color::new_rgba(1., 0., 0., 0.).interpolate(&blue, 0.25, ColorSpace::Hsl);
+        assert!((faded.get_alpha() - 0.25).abs() < 1e-6);
+    }
+}
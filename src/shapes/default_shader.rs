@@ -1,45 +1,185 @@
+// Named after its file so shapes refer to default_shader::Uniforms.
+#[allow(clippy::module_inception)]
 pub mod default_shader {
     use miniquad::*;
+    use crate::shapes::color::Color;
+    use crate::shapes::gradient::MAX_COLOR_STOPS;
+    use crate::shapes::matrix::Mat4;
+    use crate::backend::drawing_context::{BlendMode, UniformBlock};
+    use crate::shapes::draw_parameters::DrawParameters;
+    use crate::shapes::shape::BaseShape;
 
     pub const VERTEX: &str = r#"#version 100
         attribute vec2 in_pos;
         attribute vec2 in_uv;
 
-        uniform vec2 offset;
+        uniform mat4 model;
+        uniform mat4 projection;
 
-        varying lowp vec2 texcoord;
+        varying mediump vec2 texcoord;
 
         void main() {
-            gl_Position = vec4(in_pos + offset, 0, 1);
+            gl_Position = projection * model * vec4(in_pos, 0, 1);
             texcoord = in_uv;
         }
     "#;
 
     pub const FRAGMENT: &str = r#"#version 100
-        varying lowp vec2 texcoord;
+        precision mediump float;
+
+        varying mediump vec2 texcoord;
 
         uniform sampler2D tex;
         uniform highp float red;
         uniform highp float green;
         uniform highp float blue;
+        uniform highp float alpha;
+        // 0 flat colour, 1 linear, 2 radial, 3 conic.
+        uniform highp float gradient_kind;
+        uniform highp vec4 gradient_parameters;
+        uniform highp float stop_count;
+        uniform highp float stop_offsets[8];
+        uniform highp vec4 stop_colors[8];
+
+        // Mirrors gradient::position.
+        float gradient_position() {
+            vec2 point = texcoord - gradient_parameters.xy;
+            float position;
+            if (gradient_kind < 1.5) {
+                vec2 direction = gradient_parameters.zw - gradient_parameters.xy;
+                float length_squared = dot(direction, direction);
+                position = length_squared == 0.0 ? 0.0 : dot(point, direction) / length_squared;
+            } else if (gradient_kind < 2.5) {
+                position = gradient_parameters.z == 0.0 ? 1.0 : length(point) / gradient_parameters.z;
+            } else {
+                position = fract((atan(point.y, point.x) - gradient_parameters.z) / 6.28318530718);
+            }
+            return clamp(position, 0.0, 1.0);
+        }
+
+        // Mirrors gradient::sample.
+        vec4 gradient_color() {
+            float position = gradient_position();
+            vec4 color = stop_colors[0];
+            for (int index = 1; index < 8; index++) {
+                if (float(index) >= stop_count) {
+                    break;
+                }
+                float from = stop_offsets[index - 1];
+                float to = stop_offsets[index];
+                if (position >= to) {
+                    color = stop_colors[index];
+                } else if (position > from) {
+                    color = mix(stop_colors[index - 1], stop_colors[index], (position - from) / (to - from));
+                    break;
+                }
+            }
+            return color;
+        }
 
         void main() {
-            // gl_FragColor = texture2D(tex, texcoord);
-            gl_FragColor = vec4( red, green, blue, 0.0);
+            // Textures are premultiplied on upload, so is the fill,
+            // the fill acts as a tint.
+            vec4 fill;
+            if (gradient_kind < 0.5) {
+                fill = vec4( red, green, blue, alpha);
+            } else {
+                fill = gradient_color();
+            }
+            gl_FragColor = texture2D(tex, texcoord) * fill;
         }
     "#;
 
+    // Order has to match the fields of Uniforms.
     pub fn meta() -> ShaderMeta {
         ShaderMeta {
             images: vec!["tex".to_string()],
             uniforms: UniformBlockLayout {
-                uniforms: vec![UniformDesc::new("offset", UniformType::Float2)],
+                uniforms: vec![
+                    UniformDesc::new("model", UniformType::Mat4),
+                    UniformDesc::new("projection", UniformType::Mat4),
+                    UniformDesc::new("red", UniformType::Float1),
+                    UniformDesc::new("green", UniformType::Float1),
+                    UniformDesc::new("blue", UniformType::Float1),
+                    UniformDesc::new("alpha", UniformType::Float1),
+                    UniformDesc::new("gradient_kind", UniformType::Float1),
+                    UniformDesc::new("gradient_parameters", UniformType::Float4),
+                    UniformDesc::new("stop_count", UniformType::Float1),
+                    UniformDesc::new("stop_offsets", UniformType::Float1).array(MAX_COLOR_STOPS),
+                    UniformDesc::new("stop_colors", UniformType::Float4).array(MAX_COLOR_STOPS),
+                ],
             },
         }
     }
 
     #[repr(C)]
     pub struct Uniforms {
-        pub offset: (f32, f32),
+        pub model: Mat4,
+        pub projection: Mat4,
+        pub red: f32,
+        pub green: f32,
+        pub blue: f32,
+        pub alpha: f32,
+        pub gradient_kind: f32,
+        pub gradient_parameters: [f32; 4],
+        pub stop_count: f32,
+        pub stop_offsets: [f32; MAX_COLOR_STOPS],
+        // Premultiplied, like red/green/blue.
+        pub stop_colors: [[f32; 4]; MAX_COLOR_STOPS],
+    }
+
+    // All f32 and Mat4, so there is no padding.
+    unsafe impl UniformBlock for Uniforms {}
+
+    impl Uniforms {
+
+        // Folds the shape's opacity in and premultiplies, unless the
+        // colour is already premultiplied for BlendMode::Premultiplied.
+        // A gradient set on the shape replaces the flat colour.
+        pub fn new(shape: &BaseShape, parameters: &DrawParameters, color: &Color) -> Uniforms {
+            let [red, green, blue, alpha] = premultiply(shape, color);
+
+            let mut uniforms = Uniforms {
+                model: parameters.model_matrix(shape.get_transform()),
+                projection: parameters.view_projection,
+                red,
+                green,
+                blue,
+                alpha,
+                gradient_kind: 0.,
+                gradient_parameters: [0.; 4],
+                stop_count: 0.,
+                stop_offsets: [0.; MAX_COLOR_STOPS],
+                stop_colors: [[0.; 4]; MAX_COLOR_STOPS],
+            };
+
+            if let Some(gradient) = shape.get_gradient() {
+                let stops = gradient.get_stops();
+                if !stops.is_empty() {
+                    uniforms.gradient_kind = gradient.get_kind_id();
+                    uniforms.gradient_parameters = gradient.get_parameters();
+                    uniforms.stop_count = stops.len().min(MAX_COLOR_STOPS) as f32;
+                    for (index, stop) in stops.iter().take(MAX_COLOR_STOPS).enumerate() {
+                        uniforms.stop_offsets[index] = stop.get_offset();
+                        uniforms.stop_colors[index] = premultiply(shape, stop.get_color());
+                    }
+                }
+            }
+            uniforms
+        }
+    }
+
+    fn premultiply(shape: &BaseShape, color: &Color) -> [f32; 4] {
+        let alpha = color.get_alpha() * shape.get_opacity();
+        let color_scale = match shape.get_blend_mode() {
+            BlendMode::Premultiplied => shape.get_opacity(),
+            _ => alpha
+        };
+        [
+            color.get_red() * color_scale,
+            color.get_green() * color_scale,
+            color.get_blue() * color_scale,
+            alpha
+        ]
     }
-}
\ No newline at end of file
+}
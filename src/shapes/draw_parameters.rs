@@ -0,0 +1,37 @@
+use crate::shapes::matrix::Mat4;
+use crate::shapes::transform::Transform;
+
+// Worked out by SceneGraph::draw for each shape every frame, so the
+// shape's Transform only ever holds its own pose.
+#[derive(Clone, Copy)]
+pub struct DrawParameters {
+    // World matrix of the node holding the shape.
+    pub parent: Mat4,
+    // Camera matrix for the frame, identity for shapes locked to
+    // the screen.
+    pub view_projection: Mat4,
+    // How far the frame is between the last two updates, 1 draws
+    // the latest pose.
+    pub interpolation: f32
+}
+
+impl DrawParameters {
+
+    pub fn new(parent: Mat4, view_projection: Mat4, interpolation: f32) -> DrawParameters {
+        DrawParameters {
+            parent,
+            view_projection,
+            interpolation
+        }
+    }
+
+    // For shapes drawn straight onto the screen outside a scene.
+    pub fn screen() -> DrawParameters {
+        DrawParameters::new(Mat4::identity(), Mat4::identity(), 1.)
+    }
+
+    // Parent matrix applied after the shape's interpolated pose.
+    pub fn model_matrix(&self, transform: &Transform) -> Mat4 {
+        self.parent.multiply(&transform.to_local_matrix(self.interpolation))
+    }
+}
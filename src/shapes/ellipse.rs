@@ -0,0 +1,222 @@
+use std::f32::consts::TAU;
+use miniquad::BufferUsage;
+use crate::backend::drawing_context::{Bindings, BlendMode, DrawingContext};
+use crate::shapes::color::Color;
+use crate::shapes::shared_c_resources::{Vec2, Vertex};
+use crate::shapes::draw_parameters::DrawParameters;
+use crate::shapes::shape::{BaseShape, Shape};
+use crate::shapes::default_shader::default_shader;
+
+// Fewer than three segments is no longer a closed outline and
+// the center + rim vertices have to fit a u16 index.
+const MIN_SEGMENTS: i32 = 3;
+const MAX_SEGMENTS: i32 = u16::MAX as i32 - 1;
+
+pub struct Ellipse {
+    base_details: BaseShape,
+    uniforms: Color,
+    x: f32,
+    y: f32,
+    radius_x: f32,
+    radius_y: f32,
+    // Triangles in the fan, get_segments is the index count
+    // like every other shape.
+    segment_count: i32,
+    // set_segment_count has no context to upload with, so the
+    // buffers are rebuilt on the next draw instead.
+    rebuild: bool
+}
+
+impl Shape for Ellipse {
+
+    fn base(&self) -> &BaseShape {
+        &self.base_details
+    }
+
+    fn base_mut(&mut self) -> &mut BaseShape {
+        &mut self.base_details
+    }
+
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, draw: bool) {
+        if self.rebuild {
+            self.rebuild_buffers(drawing_context);
+        }
+
+        self.base_details.draw(drawing_context, false);
+
+        drawing_context.apply_uniforms(&default_shader::Uniforms::new(
+            &self.base_details,
+            parameters,
+            &self.uniforms
+        ));
+
+        if draw {
+            drawing_context.draw(0, self.base_details.get_segments(), 1);
+        }
+    }
+
+    // segments is an index count like get_segments, so the fan is
+    // rebuilt with a triangle for every three.
+    fn set_segments(&mut self, segments: i32) {
+        self.set_segment_count(segments / 3);
+    }
+}
+
+impl Ellipse {
+
+    pub fn get_uniform(&mut self) -> &mut Color {
+        &mut self.uniforms
+    }
+
+    pub fn get_segment_count(&self) -> i32 {
+        self.segment_count
+    }
+
+    pub fn set_segment_count(&mut self, segment_count: i32) {
+        let segment_count = segment_count.clamp(MIN_SEGMENTS, MAX_SEGMENTS);
+        if segment_count != self.segment_count {
+            self.segment_count = segment_count;
+            self.rebuild = true;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(context: &mut dyn DrawingContext,
+               x: f32,
+               y: f32,
+               radius_x: f32,
+               radius_y: f32,
+               segment_count: i32,
+               red: f32,
+               green: f32,
+               blue: f32) -> Ellipse {
+
+        let segment_count = segment_count.clamp(MIN_SEGMENTS, MAX_SEGMENTS);
+        let (vertices, indices) = triangle_fan(x, y, radius_x, radius_y, segment_count);
+
+        let vertex_buffer = context.new_vertex_buffer(
+            BufferUsage::Immutable,
+            &vertices,
+        );
+        let index_buffer = context.new_index_buffer(
+            BufferUsage::Immutable,
+            &indices,
+        );
+
+        let pixels: [u8; 4 * 4 * 4] = [0xFF; 4 * 4 * 4];
+        let texture = context.new_texture_from_rgba8(4, 4, &pixels);
+
+        let bindings = Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            index_buffer,
+            images: vec![texture],
+        };
+
+        let shader = context
+            .new_shader(
+                default_shader::VERTEX,
+                default_shader::FRAGMENT,
+                default_shader::meta(),
+            )
+            .unwrap();
+
+        let pipeline = context.new_pipeline(shader, BlendMode::Alpha);
+
+        let mut ellipse = Ellipse {
+            base_details:
+                BaseShape::new (
+                    bindings,
+                    pipeline,
+                    indices.len() as i32,
+                    Vec2 { x, y }
+                ),
+            uniforms:
+                Color::new (
+                    red,
+                    green,
+                    blue,
+                ),
+            x,
+            y,
+            radius_x,
+            radius_y,
+            segment_count,
+            rebuild: false
+        };
+        ellipse.base_details.set_hit_mesh(&vertices, &indices);
+        ellipse
+    }
+
+    fn rebuild_buffers(&mut self, context: &mut dyn DrawingContext) {
+        let (vertices, indices) = triangle_fan(self.x, self.y, self.radius_x, self.radius_y, self.segment_count);
+        let old_bindings = self.base_details.get_bindings().clone();
+
+        old_bindings.vertex_buffers.iter().for_each(|buffer| context.delete_buffer(*buffer));
+        context.delete_buffer(old_bindings.index_buffer);
+
+        let bindings = Bindings {
+            vertex_buffers: vec![context.new_vertex_buffer(BufferUsage::Immutable, &vertices)],
+            index_buffer: context.new_index_buffer(BufferUsage::Immutable, &indices),
+            images: old_bindings.images,
+        };
+
+        self.base_details.set_binding(bindings);
+        self.base_details.set_segments(indices.len() as i32);
+        self.base_details.set_hit_mesh(&vertices, &indices);
+        self.rebuild = false;
+    }
+}
+
+// Vertex 0 is the center, the rim starts on the +x axis and winds
+// counter-clockwise. UVs map the bounding box onto 0..1.
+fn triangle_fan(x: f32, y: f32, radius_x: f32, radius_y: f32, segments: i32) -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::with_capacity(segments as usize + 1);
+    vertices.push(Vertex { pos: Vec2 { x, y }, uv: Vec2 { x: 0.5, y: 0.5 } });
+
+    for segment in 0..segments {
+        let angle = TAU * segment as f32 / segments as f32;
+        let (sin, cos) = angle.sin_cos();
+        vertices.push(Vertex {
+            pos: Vec2 { x: x + cos * radius_x, y: y + sin * radius_y },
+            uv: Vec2 { x: 0.5 + cos * 0.5, y: 0.5 + sin * 0.5 }
+        });
+    }
+
+    let mut indices = Vec::with_capacity(segments as usize * 3);
+    for segment in 0..segments as u16 {
+        let next = (segment + 1) % segments as u16;
+        indices.extend_from_slice(&[0, segment + 1, next + 1]);
+    }
+
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use miniquad::PassAction;
+    use super::*;
+    use crate::backend::software::SoftwareBackend;
+
+    // Red where a pixel is covered.
+    fn covered(context: &mut SoftwareBackend, ellipse: &mut Ellipse, x: u32, y: u32) -> bool {
+        context.begin_default_pass(PassAction::default());
+        ellipse.draw(context, &DrawParameters::screen(), true);
+        context.end_render_pass();
+        context.get_framebuffer().get_pixel(x, y)[0] == 255
+    }
+
+    #[test]
+    fn set_segments_rebuilds_the_fan() {
+        let mut context = SoftwareBackend::new(32, 32);
+        let mut ellipse = Ellipse::new(&mut context, 0., 0., 0.9, 0.9, 4, 1., 0., 0.);
+
+        // Just inside the rim at 45 degrees, outside the diamond
+        // four segments make.
+        assert!(!covered(&mut context, &mut ellipse, 25, 7));
+
+        ellipse.set_segments(64 * 3);
+        assert_eq!(ellipse.get_segment_count(), 64);
+        assert!(covered(&mut context, &mut ellipse, 25, 7));
+        assert_eq!(ellipse.get_segments(), 64 * 3);
+    }
+}
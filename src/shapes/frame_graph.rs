@@ -0,0 +1,207 @@
+use miniquad::BufferUsage;
+use crate::backend::drawing_context::{Bindings, BlendMode, DrawingContext};
+use crate::shapes::color::Color;
+use crate::shapes::default_shader::default_shader;
+use crate::shapes::draw_parameters::DrawParameters;
+use crate::shapes::shape::{BaseShape, Shape};
+use crate::shapes::shared_c_resources::{Vec2, Vertex};
+use crate::timing::frame_stats::FrameStats;
+
+// Quads ahead of the bars: the panel and the budget line.
+const FIXED_QUADS: usize = 2;
+// Keeps every index in a u16.
+const MAX_BARS: usize = (u16::MAX as usize + 1) / 4 - FIXED_QUADS;
+
+// A bar per recorded frame, oldest on the left, over a panel with a
+// line at the frame budget. Bars reach the top at twice the budget.
+// Locked to the camera, so positions are in clip space.
+pub struct FrameGraph {
+    base_details: BaseShape,
+    uniforms: Color,
+    panel_color: Color,
+    budget_color: Color,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    bars: usize,
+    // Seconds a frame is meant to take.
+    budget: f64,
+    frame_times: Vec<f64>,
+    rebuild: bool
+}
+
+impl Shape for FrameGraph {
+
+    fn base(&self) -> &BaseShape {
+        &self.base_details
+    }
+
+    fn base_mut(&mut self) -> &mut BaseShape {
+        &mut self.base_details
+    }
+
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, draw: bool) {
+        if self.rebuild {
+            self.rebuild_buffers(drawing_context);
+        }
+
+        self.base_details.draw(drawing_context, false);
+        if !draw {
+            return;
+        }
+
+        // One draw per colour, all out of the same buffers.
+        let bars = self.frame_times.len() as i32;
+        let parts = [
+            (self.panel_color, 0, 6),
+            (self.budget_color, 6, 6),
+            (self.uniforms, 12, bars * 6)
+        ];
+        for (color, base_element, num_elements) in parts {
+            if num_elements > 0 {
+                drawing_context.apply_uniforms(&default_shader::Uniforms::new(&self.base_details, parameters, &color));
+                drawing_context.draw(base_element, num_elements, 1);
+            }
+        }
+    }
+
+    fn is_locked_to_camera(&self) -> bool {
+        true
+    }
+}
+
+impl FrameGraph {
+
+    pub fn get_uniform(&mut self) -> &mut Color {
+        &mut self.uniforms
+    }
+
+    // Room for bars frames. width and height are half extents like
+    // Square's.
+    pub fn new(context: &mut dyn DrawingContext,
+               bars: usize,
+               x: f32,
+               y: f32,
+               width: f32,
+               height: f32) -> FrameGraph {
+
+        let bars = bars.clamp(1, MAX_BARS);
+        let vertices = vec![Vertex { pos: Vec2 { x, y }, uv: Vec2 { x: 0., y: 0. } }; (FIXED_QUADS + bars) * 4];
+        let vertex_buffer = context.new_vertex_buffer(
+            BufferUsage::Immutable,
+            &vertices,
+        );
+
+        let indices: Vec<u16> = (0..(FIXED_QUADS + bars) as u16)
+            .flat_map(|quad| [0, 1, 2, 0, 2, 3].map(|corner| quad * 4 + corner))
+            .collect();
+        let index_buffer = context.new_index_buffer(
+            BufferUsage::Immutable,
+            &indices,
+        );
+
+        let pixels: [u8; 4 * 4 * 4] = [0xFF; 4 * 4 * 4];
+        let texture = context.new_texture_from_rgba8(4, 4, &pixels);
+
+        let bindings = Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            index_buffer,
+            images: vec![texture],
+        };
+
+        let shader = context
+            .new_shader(
+                default_shader::VERTEX,
+                default_shader::FRAGMENT,
+                default_shader::meta(),
+            )
+            .unwrap();
+
+        let pipeline = context.new_pipeline(shader, BlendMode::Alpha);
+
+        FrameGraph {
+            base_details:
+                BaseShape::new (
+                    bindings,
+                    pipeline,
+                    indices.len() as i32,
+                    Vec2 { x, y }
+                ),
+            uniforms:
+                Color::new (
+                    0.2,
+                    0.9,
+                    0.3,
+                ),
+            panel_color: Color::new_rgba(0., 0., 0., 0.6),
+            budget_color: Color::new(0.9, 0.2, 0.2),
+            x,
+            y,
+            width,
+            height,
+            bars,
+            budget: 1. / 60.,
+            frame_times: vec![],
+            rebuild: true
+        }
+    }
+
+    pub fn get_budget(&self) -> f64 {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: f64) {
+        self.budget = budget.max(f64::EPSILON);
+        self.rebuild = true;
+    }
+
+    pub fn set_panel_color(&mut self, panel_color: Color) {
+        self.panel_color = panel_color;
+    }
+
+    pub fn set_budget_color(&mut self, budget_color: Color) {
+        self.budget_color = budget_color;
+    }
+
+    // Shows the latest frames of stats, as many as there are bars.
+    pub fn set_frame_times(&mut self, stats: &FrameStats) {
+        let skip = stats.get_frame_count().saturating_sub(self.bars);
+        self.frame_times.clear();
+        self.frame_times.extend(stats.get_frame_times().skip(skip));
+        self.rebuild = true;
+    }
+
+    fn rebuild_buffers(&mut self, context: &mut dyn DrawingContext) {
+        let (left, bottom) = (self.x - self.width, self.y - self.height);
+        let (full_width, full_height) = (self.width * 2., self.height * 2.);
+        let bar_width = full_width / self.bars as f32;
+        let budget_y = bottom + full_height * 0.5;
+        let line_height = full_height * 0.01;
+
+        let mut vertices = Vec::with_capacity((FIXED_QUADS + self.frame_times.len()) * 4);
+        vertices.extend(quad(left, bottom, full_width, full_height));
+        vertices.extend(quad(left, budget_y - line_height, full_width, line_height * 2.));
+        for (index, frame_time) in self.frame_times.iter().enumerate() {
+            let fraction = (frame_time / (self.budget * 2.)).min(1.) as f32;
+            vertices.extend(quad(left + index as f32 * bar_width, bottom, bar_width, full_height * fraction));
+        }
+
+        let mut bindings = self.base_details.get_bindings().clone();
+        bindings.vertex_buffers.iter().for_each(|buffer| context.delete_buffer(*buffer));
+        bindings.vertex_buffers = vec![context.new_vertex_buffer(BufferUsage::Immutable, &vertices)];
+
+        self.base_details.set_binding(bindings);
+        self.rebuild = false;
+    }
+}
+
+// From the bottom left corner, counter-clockwise.
+fn quad(x: f32, y: f32, width: f32, height: f32) -> [Vertex; 4] {
+    [
+        Vertex { pos: Vec2 { x, y }, uv: Vec2 { x: 0., y: 0. } },
+        Vertex { pos: Vec2 { x: x + width, y }, uv: Vec2 { x: 1., y: 0. } },
+        Vertex { pos: Vec2 { x: x + width, y: y + height }, uv: Vec2 { x: 1., y: 1. } },
+        Vertex { pos: Vec2 { x, y: y + height }, uv: Vec2 { x: 0., y: 1. } },
+    ]
+}
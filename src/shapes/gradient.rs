@@ -0,0 +1,183 @@
+use std::f32::consts::TAU;
+use crate::shapes::color::Color;
+use crate::shapes::shared_c_resources::Vec2;
+
+// Fixed by the uniform arrays in default_shader, extra stops are
+// ignored when the gradient is uploaded.
+pub const MAX_COLOR_STOPS: usize = 8;
+
+// Positions are in the shape's uv space, 0..1 across its bounds.
+#[derive(Clone, Copy)]
+pub enum GradientKind {
+    // Colour changes along start -> end, constant across it.
+    Linear { start: Vec2, end: Vec2 },
+    Radial { center: Vec2, radius: f32 },
+    // Colour sweeps counter-clockwise around center, starting at
+    // angle (radians).
+    Conic { center: Vec2, angle: f32 }
+}
+
+#[derive(Clone, Copy)]
+pub struct ColorStop {
+    offset: f32,
+    color: Color
+}
+
+impl ColorStop {
+    pub fn new(offset: f32, color: Color) -> ColorStop {
+        ColorStop {
+            offset: offset.clamp(0., 1.),
+            color
+        }
+    }
+
+    pub fn get_offset(&self) -> f32 {
+        self.offset
+    }
+
+    pub fn get_color(&self) -> &Color {
+        &self.color
+    }
+}
+
+// Positions before the first stop or after the last take that
+// stop's colour, like CSS.
+#[derive(Clone)]
+pub struct Gradient {
+    kind: GradientKind,
+    stops: Vec<ColorStop>
+}
+
+impl Gradient {
+
+    pub fn new(kind: GradientKind, stops: Vec<ColorStop>) -> Gradient {
+        let mut gradient = Gradient {
+            kind,
+            stops: vec![]
+        };
+        for stop in stops {
+            gradient.add_stop(stop);
+        }
+        gradient
+    }
+
+    pub fn new_linear(start: Vec2, end: Vec2, stops: Vec<ColorStop>) -> Gradient {
+        Gradient::new(GradientKind::Linear { start, end }, stops)
+    }
+
+    pub fn new_radial(center: Vec2, radius: f32, stops: Vec<ColorStop>) -> Gradient {
+        Gradient::new(GradientKind::Radial { center, radius }, stops)
+    }
+
+    pub fn new_conic(center: Vec2, angle: f32, stops: Vec<ColorStop>) -> Gradient {
+        Gradient::new(GradientKind::Conic { center, angle }, stops)
+    }
+
+    pub fn get_kind(&self) -> &GradientKind {
+        &self.kind
+    }
+
+    pub fn get_stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    pub fn set_kind(&mut self, kind: GradientKind) {
+        self.kind = kind;
+    }
+
+    // Keeps the stops sorted, equal offsets stay in insertion order
+    // to allow hard colour edges.
+    pub fn add_stop(&mut self, stop: ColorStop) {
+        let index = self.stops.partition_point(|existing| existing.offset <= stop.offset);
+        self.stops.insert(index, stop);
+    }
+
+    pub fn clear_stops(&mut self) {
+        self.stops.clear();
+    }
+
+    // Shader encoding of the kind, see default_shader::FRAGMENT.
+    pub fn get_kind_id(&self) -> f32 {
+        match self.kind {
+            GradientKind::Linear { .. } => 1.,
+            GradientKind::Radial { .. } => 2.,
+            GradientKind::Conic { .. } => 3.
+        }
+    }
+
+    // Linear: start.xy, end.xy. Radial: center.xy, radius.
+    // Conic: center.xy, angle.
+    pub fn get_parameters(&self) -> [f32; 4] {
+        match self.kind {
+            GradientKind::Linear { start, end } => [start.x, start.y, end.x, end.y],
+            GradientKind::Radial { center, radius } => [center.x, center.y, radius, 0.],
+            GradientKind::Conic { center, angle } => [center.x, center.y, angle, 0.]
+        }
+    }
+
+    // Straight alpha colour at a uv position.
+    pub fn color_at(&self, u: f32, v: f32) -> Color {
+        let stops = &self.stops[..self.stops.len().min(MAX_COLOR_STOPS)];
+        let offsets: Vec<f32> = stops.iter().map(|stop| stop.offset).collect();
+        let colors: Vec<[f32; 4]> = stops
+            .iter()
+            .map(|stop| {
+                let color = &stop.color;
+                [color.get_red(), color.get_green(), color.get_blue(), color.get_alpha()]
+            })
+            .collect();
+
+        let [red, green, blue, alpha] = sample(self.get_kind_id(), self.get_parameters(), &offsets, &colors, u, v);
+        Color::new_rgba(red, green, blue, alpha)
+    }
+}
+
+// Position along the gradient, 0 at the first stop and 1 at the
+// last. Shared by the CPU paths and mirrored in GLSL.
+pub fn position(kind_id: f32, parameters: [f32; 4], u: f32, v: f32) -> f32 {
+    let (x, y) = (u - parameters[0], v - parameters[1]);
+    let position = match kind_id as u32 {
+        1 => {
+            let (direction_x, direction_y) = (parameters[2] - parameters[0], parameters[3] - parameters[1]);
+            let length_squared = direction_x * direction_x + direction_y * direction_y;
+            if length_squared == 0. {
+                0.
+            } else {
+                (x * direction_x + y * direction_y) / length_squared
+            }
+        }
+        2 => {
+            if parameters[2] == 0. {
+                1.
+            } else {
+                (x * x + y * y).sqrt() / parameters[2]
+            }
+        }
+        _ => ((y.atan2(x) - parameters[2]) / TAU).rem_euclid(1.)
+    };
+    position.clamp(0., 1.)
+}
+
+// Interpolates between the stop colours, whatever premultiplication
+// the colours carry is preserved.
+pub fn sample(kind_id: f32, parameters: [f32; 4], offsets: &[f32], colors: &[[f32; 4]], u: f32, v: f32) -> [f32; 4] {
+    let Some(first) = colors.first() else {
+        return [0.; 4];
+    };
+    let position = position(kind_id, parameters, u, v);
+
+    let mut color = *first;
+    for index in 1..offsets.len().min(colors.len()) {
+        let (from, to) = (offsets[index - 1], offsets[index]);
+        if position >= to {
+            color = colors[index];
+        } else if position > from {
+            let t = (position - from) / (to - from);
+            for channel in 0..4 {
+                color[channel] = colors[index - 1][channel] + (colors[index][channel] - colors[index - 1][channel]) * t;
+            }
+            break;
+        }
+    }
+    color
+}
\ No newline at end of file
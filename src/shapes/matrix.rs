@@ -0,0 +1,83 @@
+// Column-major 4x4 matrix, the layout GLSL expects for a mat4 uniform.
+// Only the 2D affine part is ever populated.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    values: [f32; 16]
+}
+
+impl Mat4 {
+
+    pub fn identity() -> Mat4 {
+        Mat4::from_affine(1., 0., 0., 1., 0., 0.)
+    }
+
+    pub fn translation(x: f32, y: f32) -> Mat4 {
+        Mat4::from_affine(1., 0., 0., 1., x, y)
+    }
+
+    // Counter-clockwise, in radians.
+    pub fn rotation(radians: f32) -> Mat4 {
+        let (sin, cos) = radians.sin_cos();
+        Mat4::from_affine(cos, sin, -sin, cos, 0., 0.)
+    }
+
+    pub fn scale(x: f32, y: f32) -> Mat4 {
+        Mat4::from_affine(x, 0., 0., y, 0., 0.)
+    }
+
+    // Maps (x, y) to (a * x + c * y + tx, b * x + d * y + ty).
+    pub fn from_affine(a: f32, b: f32, c: f32, d: f32, tx: f32, ty: f32) -> Mat4 {
+        Mat4 {
+            values: [
+                a, b, 0., 0.,
+                c, d, 0., 0.,
+                0., 0., 1., 0.,
+                tx, ty, 0., 1.,
+            ]
+        }
+    }
+
+    pub fn get_values(&self) -> &[f32; 16] {
+        &self.values
+    }
+
+    // self * other, other is applied to a point first.
+    pub fn multiply(&self, other: &Mat4) -> Mat4 {
+        let mut values = [0f32; 16];
+        for column in 0..4 {
+            for row in 0..4 {
+                values[column * 4 + row] = (0..4)
+                    .map(|k| self.values[k * 4 + row] * other.values[column * 4 + k])
+                    .sum();
+            }
+        }
+        Mat4 { values }
+    }
+
+    pub fn transform_point(&self, x: f32, y: f32) -> (f32, f32) {
+        let v = &self.values;
+        (v[0] * x + v[4] * y + v[12], v[1] * x + v[5] * y + v[13])
+    }
+
+    // None when the 2D part is singular, e.g. a zero scale.
+    pub fn inverse(&self) -> Option<Mat4> {
+        let v = &self.values;
+        let (a, b, c, d, tx, ty) = (v[0], v[1], v[4], v[5], v[12], v[13]);
+        let determinant = a * d - b * c;
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inverse = 1. / determinant;
+        let (ia, ib, ic, id) = (d * inverse, -b * inverse, -c * inverse, a * inverse);
+        Some(Mat4::from_affine(
+            ia,
+            ib,
+            ic,
+            id,
+            -(ia * tx + ic * ty),
+            -(ib * tx + id * ty),
+        ))
+    }
+}
@@ -0,0 +1,131 @@
+use miniquad::BufferUsage;
+use crate::backend::drawing_context::{Bindings, BlendMode, DrawingContext};
+use crate::shapes::color::Color;
+use crate::shapes::shared_c_resources::{Vec2, Vertex};
+use crate::shapes::draw_parameters::DrawParameters;
+use crate::shapes::shape::{BaseShape, Shape};
+use crate::shapes::default_shader::default_shader;
+use crate::shapes::triangulation::triangulate;
+
+pub struct Polygon {
+    base_details: BaseShape,
+    uniforms: Color
+}
+
+impl Shape for Polygon {
+
+    fn base(&self) -> &BaseShape {
+        &self.base_details
+    }
+
+    fn base_mut(&mut self) -> &mut BaseShape {
+        &mut self.base_details
+    }
+
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, draw: bool) {
+        self.base_details.draw(drawing_context, false);
+
+        drawing_context.apply_uniforms(&default_shader::Uniforms::new(
+            &self.base_details,
+            parameters,
+            &self.uniforms
+        ));
+
+        if draw {
+            drawing_context.draw(0, self.base_details.get_segments(), 1);
+        }
+    }
+}
+
+impl Polygon {
+
+    pub fn get_uniform(&mut self) -> &mut Color {
+        &mut self.uniforms
+    }
+
+    pub fn new(context: &mut dyn DrawingContext,
+               points: &[Vec2],
+               red: f32,
+               green: f32,
+               blue: f32) -> Polygon {
+        Polygon::new_with_holes(context, points, &[], red, green, blue)
+    }
+
+    // Holes are cut out of the outline, either winding is accepted
+    // for the outline and the holes.
+    pub fn new_with_holes(context: &mut dyn DrawingContext,
+                          points: &[Vec2],
+                          holes: &[Vec<Vec2>],
+                          red: f32,
+                          green: f32,
+                          blue: f32) -> Polygon {
+
+        let vertex_count = points.len() + holes.iter().map(|hole| hole.len()).sum::<usize>();
+        assert!(points.len() >= 3, "A polygon needs at least three points.");
+        assert!(vertex_count <= u16::MAX as usize, "Too many points for a u16 index buffer.");
+
+        // UVs span the bounding box of the outline, same 0..1 range
+        // Square gives its corners.
+        let min_x = points.iter().map(|point| point.x).fold(f32::MAX, f32::min);
+        let max_x = points.iter().map(|point| point.x).fold(f32::MIN, f32::max);
+        let min_y = points.iter().map(|point| point.y).fold(f32::MAX, f32::min);
+        let max_y = points.iter().map(|point| point.y).fold(f32::MIN, f32::max);
+        let width = (max_x - min_x).max(f32::EPSILON);
+        let height = (max_y - min_y).max(f32::EPSILON);
+
+        let vertices: Vec<Vertex> = points.iter()
+            .chain(holes.iter().flatten())
+            .map(|point| Vertex {
+                pos: Vec2 { x: point.x, y: point.y },
+                uv: Vec2 { x: (point.x - min_x) / width, y: (point.y - min_y) / height }
+            })
+            .collect();
+        let vertex_buffer = context.new_vertex_buffer(
+            BufferUsage::Immutable,
+            &vertices,
+        );
+
+        let indices = triangulate(points, holes);
+        let index_buffer = context.new_index_buffer(
+            BufferUsage::Immutable,
+            &indices,
+        );
+
+        let pixels: [u8; 4 * 4 * 4] = [0xFF; 4 * 4 * 4];
+        let texture = context.new_texture_from_rgba8(4, 4, &pixels);
+
+        let bindings = Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            index_buffer,
+            images: vec![texture],
+        };
+
+        let shader = context
+            .new_shader(
+                default_shader::VERTEX,
+                default_shader::FRAGMENT,
+                default_shader::meta(),
+            )
+            .unwrap();
+
+        let pipeline = context.new_pipeline(shader, BlendMode::Alpha);
+
+        let mut polygon = Polygon {
+            base_details:
+                BaseShape::new (
+                    bindings,
+                    pipeline,
+                    indices.len() as i32,
+                    Vec2 { x: (min_x + max_x) * 0.5, y: (min_y + max_y) * 0.5 }
+                ),
+            uniforms:
+                Color::new (
+                    red,
+                    green,
+                    blue,
+                )
+        };
+        polygon.base_details.set_hit_mesh(&vertices, &indices);
+        polygon
+    }
+}
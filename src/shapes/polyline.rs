@@ -0,0 +1,123 @@
+use miniquad::BufferUsage;
+use crate::backend::drawing_context::{Bindings, BlendMode, DrawingContext};
+use crate::shapes::color::Color;
+use crate::shapes::shared_c_resources::{Vec2, Vertex};
+use crate::shapes::draw_parameters::DrawParameters;
+use crate::shapes::shape::{BaseShape, Shape};
+use crate::shapes::default_shader::default_shader;
+use crate::shapes::stroke::{stroke, LineCap, LineJoin};
+
+pub struct Polyline {
+    base_details: BaseShape,
+    uniforms: Color
+}
+
+impl Shape for Polyline {
+
+    fn base(&self) -> &BaseShape {
+        &self.base_details
+    }
+
+    fn base_mut(&mut self) -> &mut BaseShape {
+        &mut self.base_details
+    }
+
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, draw: bool) {
+        self.base_details.draw(drawing_context, false);
+
+        drawing_context.apply_uniforms(&default_shader::Uniforms::new(
+            &self.base_details,
+            parameters,
+            &self.uniforms
+        ));
+
+        if draw {
+            drawing_context.draw(0, self.base_details.get_segments(), 1);
+        }
+    }
+}
+
+impl Polyline {
+
+    pub fn get_uniform(&mut self) -> &mut Color {
+        &mut self.uniforms
+    }
+
+    // Width is in the same NDC units as the points, `closed` joins
+    // the last point back to the first and ignores the caps.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(context: &mut dyn DrawingContext,
+               points: &[Vec2],
+               width: f32,
+               join: LineJoin,
+               cap: LineCap,
+               closed: bool,
+               red: f32,
+               green: f32,
+               blue: f32) -> Polyline {
+
+        let (positions, indices) = stroke(points, width, join, cap, closed);
+        assert!(!indices.is_empty(), "A polyline needs at least two distinct points.");
+        assert!(positions.len() <= u16::MAX as usize, "Too many points for a u16 index buffer.");
+
+        // UVs span the bounding box of the stroked outline.
+        let min_x = positions.iter().map(|point| point.x).fold(f32::MAX, f32::min);
+        let max_x = positions.iter().map(|point| point.x).fold(f32::MIN, f32::max);
+        let min_y = positions.iter().map(|point| point.y).fold(f32::MAX, f32::min);
+        let max_y = positions.iter().map(|point| point.y).fold(f32::MIN, f32::max);
+        let width = (max_x - min_x).max(f32::EPSILON);
+        let height = (max_y - min_y).max(f32::EPSILON);
+
+        let vertices: Vec<Vertex> = positions.iter()
+            .map(|point| Vertex {
+                pos: Vec2 { x: point.x, y: point.y },
+                uv: Vec2 { x: (point.x - min_x) / width, y: (point.y - min_y) / height }
+            })
+            .collect();
+        let vertex_buffer = context.new_vertex_buffer(
+            BufferUsage::Immutable,
+            &vertices,
+        );
+        let index_buffer = context.new_index_buffer(
+            BufferUsage::Immutable,
+            &indices,
+        );
+
+        let pixels: [u8; 4 * 4 * 4] = [0xFF; 4 * 4 * 4];
+        let texture = context.new_texture_from_rgba8(4, 4, &pixels);
+
+        let bindings = Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            index_buffer,
+            images: vec![texture],
+        };
+
+        let shader = context
+            .new_shader(
+                default_shader::VERTEX,
+                default_shader::FRAGMENT,
+                default_shader::meta(),
+            )
+            .unwrap();
+
+        let pipeline = context.new_pipeline(shader, BlendMode::Alpha);
+
+        let mut polyline = Polyline {
+            base_details:
+                BaseShape::new (
+                    bindings,
+                    pipeline,
+                    indices.len() as i32,
+                    Vec2 { x: (min_x + max_x) * 0.5, y: (min_y + max_y) * 0.5 }
+                ),
+            uniforms:
+                Color::new (
+                    red,
+                    green,
+                    blue,
+                )
+        };
+        polyline.base_details.set_hit_mesh(&vertices, &indices);
+        polyline
+    }
+}
@@ -1,16 +1,138 @@
-use miniquad::{Bindings, KeyCode, Pipeline, RenderingBackend};
+use crate::backend::drawing_context::{Bindings, BlendMode, DrawingContext, Pipeline};
+use crate::shapes::draw_parameters::DrawParameters;
+use crate::shapes::gradient::Gradient;
+use crate::input::pointer::{PointerEvent, PointerHandlers};
+use crate::shapes::shared_c_resources::{Vec2, Vertex};
+use crate::shapes::transform::Transform;
+use crate::input::input_state::InputState;
 
+// Shapes wrap a BaseShape, directly or through another shape, and
+// hand it out through base/base_mut. Everything else defaults to it,
+// so a shape only writes draw and what it does differently.
 pub trait Shape {
 
-    fn get_bindings(&self) -> &Bindings;
-    fn get_pipeline(&self) -> &Pipeline;
-    fn get_segments(&self) -> i32;
-    fn set_binding(&mut self, bindings: Bindings);
-    fn set_pipeline(&mut self, pipeline: Pipeline);
-    fn set_segments(&mut self, segments: i32);
-    fn draw(&mut self, drawing_context: &mut Box<dyn RenderingBackend>, draw: bool);
-    fn input_down(&mut self, key_code: KeyCode);
-    fn input_up(&mut self, key_code: KeyCode);
+    fn base(&self) -> &BaseShape;
+    fn base_mut(&mut self) -> &mut BaseShape;
+
+    fn get_bindings(&self) -> &Bindings {
+        self.base().get_bindings()
+    }
+
+    fn get_pipeline(&self) -> &Pipeline {
+        self.base().get_pipeline()
+    }
+
+    fn get_segments(&self) -> i32 {
+        self.base().get_segments()
+    }
+
+    fn get_transform(&self) -> &Transform {
+        self.base().get_transform()
+    }
+
+    fn get_transform_mut(&mut self) -> &mut Transform {
+        self.base_mut().get_transform_mut()
+    }
+
+    fn get_layer(&self) -> i32 {
+        self.base().get_layer()
+    }
+
+    fn get_z_index(&self) -> i32 {
+        self.base().get_z_index()
+    }
+
+    fn get_blend_mode(&self) -> BlendMode {
+        self.base().get_blend_mode()
+    }
+
+    fn get_opacity(&self) -> f32 {
+        self.base().get_opacity()
+    }
+
+    fn get_gradient(&self) -> Option<&Gradient> {
+        self.base().get_gradient()
+    }
+
+    fn set_binding(&mut self, bindings: Bindings) {
+        self.base_mut().set_binding(bindings);
+    }
+
+    fn set_pipeline(&mut self, pipeline: Pipeline) {
+        self.base_mut().set_pipeline(pipeline);
+    }
+
+    fn set_segments(&mut self, segments: i32) {
+        self.base_mut().set_segments(segments);
+    }
+
+    fn set_transform(&mut self, transform: Transform) {
+        self.base_mut().set_transform(transform);
+    }
+
+    fn set_layer(&mut self, layer: i32) {
+        self.base_mut().set_layer(layer);
+    }
+
+    fn set_z_index(&mut self, z_index: i32) {
+        self.base_mut().set_z_index(z_index);
+    }
+
+    fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.base_mut().set_blend_mode(blend_mode);
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.base_mut().set_opacity(opacity);
+    }
+
+    // None goes back to the flat colour.
+    fn set_gradient(&mut self, gradient: Option<Gradient>) {
+        self.base_mut().set_gradient(gradient);
+    }
+
+    // Seconds since the last update. Movement and animation belong
+    // here, draw only renders the current state. input is shared by
+    // every shape and answers for this update.
+    fn update(&mut self, _dt: f32, _input: &InputState) {}
+
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, draw: bool);
+
+    // x and y are in the same coordinates as the shape's vertices.
+    fn contains_point(&self, x: f32, y: f32) -> bool {
+        self.base().contains_point(x, y)
+    }
+
+    fn get_pointer_handlers_mut(&mut self) -> &mut PointerHandlers {
+        self.base_mut().get_pointer_handlers_mut()
+    }
+
+    // Called by PointerRouter, BaseShape runs the matching closure
+    // from its PointerHandlers.
+    fn on_click(&mut self, event: &PointerEvent) {
+        self.base_mut().on_click(event);
+    }
+
+    fn on_hover_enter(&mut self, event: &PointerEvent) {
+        self.base_mut().on_hover_enter(event);
+    }
+
+    fn on_hover_leave(&mut self, event: &PointerEvent) {
+        self.base_mut().on_hover_leave(event);
+    }
+
+    fn on_drag(&mut self, event: &PointerEvent) {
+        self.base_mut().on_drag(event);
+    }
+
+    fn on_scroll(&mut self, event: &PointerEvent) {
+        self.base_mut().on_scroll(event);
+    }
+
+    // Locked shapes ignore the camera and stay put on screen.
+    fn is_locked_to_camera(&self) -> bool {
+        false
+    }
 }
 
 pub struct BaseShape {
@@ -18,35 +140,113 @@ pub struct BaseShape {
     bindings: Bindings,
     pipeline: Pipeline,
     segments: i32,
+    transform: Transform,
+    // Draw order, lower layers first then lower z_index within a
+    // layer. Ties keep scene order.
+    layer: i32,
+    z_index: i32,
+    blend_mode: BlendMode,
+    // Set when blend_mode changes, the pipeline is rebuilt on the
+    // next draw since setters have no context.
+    blend_mode_changed: bool,
+    // Multiplies the colour's own alpha.
+    opacity: f32,
+    // Replaces the flat colour when set, evaluated over in_uv.
+    gradient: Option<Gradient>,
+    // CPU copy of the triangles for hit testing, the GPU buffers
+    // cannot be read back.
+    hit_vertices: Vec<Vec2>,
+    hit_indices: Vec<u16>,
+    pointer_handlers: PointerHandlers
 }
 
-impl Shape for BaseShape {
+impl BaseShape {
 
-    fn get_bindings(&self) -> &Bindings {
+    pub fn get_bindings(&self) -> &Bindings {
         &self.bindings
     }
 
-    fn get_pipeline(&self) -> &Pipeline {
+    pub fn get_pipeline(&self) -> &Pipeline {
         &self.pipeline
     }
 
-    fn get_segments(&self) -> i32 {
+    pub fn get_segments(&self) -> i32 {
         self.segments
     }
 
-    fn set_binding(&mut self, bindings: Bindings) {
+    pub fn get_transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn get_transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+
+    pub fn get_layer(&self) -> i32 {
+        self.layer
+    }
+
+    pub fn get_z_index(&self) -> i32 {
+        self.z_index
+    }
+
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn get_opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn get_gradient(&self) -> Option<&Gradient> {
+        self.gradient.as_ref()
+    }
+
+    pub fn set_binding(&mut self, bindings: Bindings) {
         self.bindings = bindings;
     }
 
-    fn set_pipeline(&mut self, pipeline: Pipeline) {
+    pub fn set_pipeline(&mut self, pipeline: Pipeline) {
         self.pipeline = pipeline;
     }
 
-    fn set_segments(&mut self, segments: i32) {
+    pub fn set_segments(&mut self, segments: i32) {
         self.segments = segments;
     }
 
-    fn draw(&mut self, drawing_context: &mut Box<dyn RenderingBackend>, draw: bool) {
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn set_layer(&mut self, layer: i32) {
+        self.layer = layer;
+    }
+
+    pub fn set_z_index(&mut self, z_index: i32) {
+        self.z_index = z_index;
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        if blend_mode != self.blend_mode {
+            self.blend_mode = blend_mode;
+            self.blend_mode_changed = true;
+        }
+    }
+
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0., 1.);
+    }
+
+    pub fn set_gradient(&mut self, gradient: Option<Gradient>) {
+        self.gradient = gradient;
+    }
+
+    pub fn draw(&mut self, drawing_context: &mut dyn DrawingContext, draw: bool) {
+        if self.blend_mode_changed {
+            drawing_context.set_pipeline_blend_mode(&self.pipeline, self.blend_mode);
+            self.blend_mode_changed = false;
+        }
+
         drawing_context.apply_pipeline(&self.pipeline);
         drawing_context.apply_bindings(&self.bindings);
 
@@ -55,17 +255,82 @@ impl Shape for BaseShape {
         }
     }
 
-    fn input_down(&mut self, key_code: KeyCode) {} // NOT IMPLEMENTED
-    fn input_up(&mut self, key_code: KeyCode) {} // NOT IMPLEMENTED
-}
+    pub fn contains_point(&self, x: f32, y: f32) -> bool {
+        let point = Vec2 { x, y };
+        self.hit_indices.chunks_exact(3).any(|triangle| {
+            let corner = |index: usize| self.hit_vertices.get(triangle[index] as usize).copied();
+            match (corner(0), corner(1), corner(2)) {
+                (Some(a), Some(b), Some(c)) => in_triangle(point, a, b, c),
+                _ => false
+            }
+        })
+    }
 
-impl BaseShape {
+    pub fn get_pointer_handlers_mut(&mut self) -> &mut PointerHandlers {
+        &mut self.pointer_handlers
+    }
+
+    pub fn on_click(&mut self, event: &PointerEvent) {
+        if let Some(handler) = self.pointer_handlers.on_click.as_mut() {
+            handler(event);
+        }
+    }
+
+    pub fn on_hover_enter(&mut self, event: &PointerEvent) {
+        if let Some(handler) = self.pointer_handlers.on_hover_enter.as_mut() {
+            handler(event);
+        }
+    }
+
+    pub fn on_hover_leave(&mut self, event: &PointerEvent) {
+        if let Some(handler) = self.pointer_handlers.on_hover_leave.as_mut() {
+            handler(event);
+        }
+    }
 
-    pub fn new<'a>(bindings: Bindings, pipeline: Pipeline, segments: i32) -> BaseShape {
+    pub fn on_drag(&mut self, event: &PointerEvent) {
+        if let Some(handler) = self.pointer_handlers.on_drag.as_mut() {
+            handler(event);
+        }
+    }
+
+    pub fn on_scroll(&mut self, event: &PointerEvent) {
+        if let Some(handler) = self.pointer_handlers.on_scroll.as_mut() {
+            handler(event);
+        }
+    }
+
+    // Pivot is usually the centre the shape was built around.
+    pub fn new(bindings: Bindings, pipeline: Pipeline, segments: i32, pivot: Vec2) -> BaseShape {
         BaseShape {
             bindings,
             pipeline,
-            segments
+            segments,
+            transform: Transform::new(pivot.x, pivot.y),
+            layer: 0,
+            z_index: 0,
+            // Shapes build their pipeline with BlendMode::Alpha.
+            blend_mode: BlendMode::Alpha,
+            blend_mode_changed: false,
+            opacity: 1.,
+            gradient: None,
+            hit_vertices: vec![],
+            hit_indices: vec![],
+            pointer_handlers: PointerHandlers::default()
         }
     }
-}
\ No newline at end of file
+
+    // The triangles contains_point tests, normally the same ones
+    // handed to the vertex and index buffers.
+    pub fn set_hit_mesh(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        self.hit_vertices = vertices.iter().map(|vertex| vertex.pos).collect();
+        self.hit_indices = indices.to_vec();
+    }
+}
+
+// Either winding, points on an edge count as inside.
+fn in_triangle(point: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let side = |from: Vec2, to: Vec2| (to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x);
+    let (ab, bc, ca) = (side(a, b), side(b, c), side(c, a));
+    (ab >= 0. && bc >= 0. && ca >= 0.) || (ab <= 0. && bc <= 0. && ca <= 0.)
+}
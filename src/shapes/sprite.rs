@@ -0,0 +1,224 @@
+use miniquad::BufferUsage;
+use crate::backend::drawing_context::{Bindings, BlendMode, DrawingContext};
+use crate::backend::atlas::Atlas;
+use crate::backend::texture::Texture;
+use crate::shapes::color::Color;
+use crate::shapes::shared_c_resources::{Vec2, Vertex};
+use crate::shapes::draw_parameters::DrawParameters;
+use crate::shapes::shape::{BaseShape, Shape};
+use crate::shapes::default_shader::default_shader;
+
+// Part of the texture shown, in uvs with v = 0 at the top of the
+// image. Going past 0..1 tiles according to the texture's wrap mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub u_min: f32,
+    pub v_min: f32,
+    pub u_max: f32,
+    pub v_max: f32
+}
+
+impl Region {
+    pub fn full() -> Region {
+        Region {
+            u_min: 0.,
+            v_min: 0.,
+            u_max: 1.,
+            v_max: 1.
+        }
+    }
+}
+
+// A textured quad. The tint multiplies every texel, white shows the
+// texture unchanged.
+pub struct Sprite {
+    base_details: BaseShape,
+    uniforms: Color,
+    texture: Texture,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    region: Region,
+    // set_region has no context to upload with, so the vertex
+    // buffer is rebuilt on the next draw instead.
+    rebuild: bool
+}
+
+impl Shape for Sprite {
+
+    fn base(&self) -> &BaseShape {
+        &self.base_details
+    }
+
+    fn base_mut(&mut self) -> &mut BaseShape {
+        &mut self.base_details
+    }
+
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, draw: bool) {
+        if self.rebuild {
+            self.rebuild_buffers(drawing_context);
+        }
+
+        self.base_details.draw(drawing_context, false);
+
+        drawing_context.apply_uniforms(&default_shader::Uniforms::new(
+            &self.base_details,
+            parameters,
+            &self.uniforms
+        ));
+
+        if draw {
+            drawing_context.draw(0, self.base_details.get_segments(), 1);
+        }
+    }
+}
+
+impl Sprite {
+
+    pub fn get_uniform(&mut self) -> &mut Color {
+        &mut self.uniforms
+    }
+
+    // width and height are half extents like Square's.
+    pub fn new(context: &mut dyn DrawingContext,
+               texture: &Texture,
+               x: f32,
+               y: f32,
+               width: f32,
+               height: f32) -> Sprite {
+
+        let region = Region::full();
+        let vertices = quad(x, y, width, height, &region);
+        let vertex_buffer = context.new_vertex_buffer(
+            BufferUsage::Immutable,
+            &vertices,
+        );
+
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+        let index_buffer = context.new_index_buffer(
+            BufferUsage::Immutable,
+            &indices,
+        );
+
+        let bindings = Bindings {
+            vertex_buffers: vec![vertex_buffer],
+            index_buffer,
+            images: vec![texture.get_id()],
+        };
+
+        let shader = context
+            .new_shader(
+                default_shader::VERTEX,
+                default_shader::FRAGMENT,
+                default_shader::meta(),
+            )
+            .unwrap();
+
+        let pipeline = context.new_pipeline(shader, BlendMode::Alpha);
+
+        let mut sprite = Sprite {
+            base_details:
+                BaseShape::new (
+                    bindings,
+                    pipeline,
+                    6,
+                    Vec2 { x, y }
+                ),
+            uniforms:
+                Color::new (
+                    1.,
+                    1.,
+                    1.,
+                ),
+            texture: *texture,
+            x,
+            y,
+            width,
+            height,
+            region,
+            rebuild: false
+        };
+        sprite.base_details.set_hit_mesh(&vertices, &indices);
+        sprite
+    }
+
+    // None when the atlas has no region called name.
+    pub fn from_atlas(context: &mut dyn DrawingContext,
+                      atlas: &Atlas,
+                      name: &str,
+                      x: f32,
+                      y: f32,
+                      width: f32,
+                      height: f32) -> Option<Sprite> {
+
+        let region = atlas.get_region(name)?;
+        let mut sprite = Sprite::new(context, atlas.get_page(region.page)?, x, y, width, height);
+        sprite.set_region(region.region);
+        Some(sprite)
+    }
+
+    pub fn get_texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn get_region(&self) -> Region {
+        self.region
+    }
+
+    pub fn set_tint(&mut self, tint: Color) {
+        self.uniforms = tint;
+    }
+
+    pub fn set_texture(&mut self, texture: &Texture) {
+        self.texture = *texture;
+        let mut bindings = self.base_details.get_bindings().clone();
+        bindings.images = vec![texture.get_id()];
+        self.base_details.set_binding(bindings);
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        if region != self.region {
+            self.region = region;
+            self.rebuild = true;
+        }
+    }
+
+    // Switches page and region together, false leaves the sprite
+    // untouched when the name is unknown.
+    pub fn set_atlas_region(&mut self, atlas: &Atlas, name: &str) -> bool {
+        let Some(region) = atlas.get_region(name) else {
+            return false;
+        };
+        let Some(page) = atlas.get_page(region.page) else {
+            return false;
+        };
+        if *page != self.texture {
+            self.set_texture(page);
+        }
+        self.set_region(region.region);
+        true
+    }
+
+    fn rebuild_buffers(&mut self, context: &mut dyn DrawingContext) {
+        let vertices = quad(self.x, self.y, self.width, self.height, &self.region);
+        let mut bindings = self.base_details.get_bindings().clone();
+
+        bindings.vertex_buffers.iter().for_each(|buffer| context.delete_buffer(*buffer));
+        bindings.vertex_buffers = vec![context.new_vertex_buffer(BufferUsage::Immutable, &vertices)];
+
+        self.base_details.set_binding(bindings);
+        self.rebuild = false;
+    }
+}
+
+// Bottom left first, counter-clockwise. Image rows run top down,
+// so the bottom edge samples v_max.
+fn quad(x: f32, y: f32, width: f32, height: f32, region: &Region) -> [Vertex; 4] {
+    [
+        Vertex { pos: Vec2 { x: x - width, y: y - height }, uv: Vec2 { x: region.u_min, y: region.v_max } },
+        Vertex { pos: Vec2 { x: x + width, y: y - height }, uv: Vec2 { x: region.u_max, y: region.v_max } },
+        Vertex { pos: Vec2 { x: x + width, y: y + height }, uv: Vec2 { x: region.u_max, y: region.v_min } },
+        Vertex { pos: Vec2 { x: x - width, y: y + height }, uv: Vec2 { x: region.u_min, y: region.v_min } },
+    ]
+}
\ No newline at end of file
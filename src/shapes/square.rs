@@ -1,10 +1,8 @@
-use miniquad::{
-    Bindings, BufferLayout, BufferSource, BufferType, BufferUsage, KeyCode,
-    Pipeline, RenderingBackend, ShaderMeta, ShaderSource, UniformBlockLayout,
-    UniformDesc, UniformsSource, UniformType, VertexAttribute, VertexFormat
-};
+use miniquad::BufferUsage;
+use crate::backend::drawing_context::{Bindings, BlendMode, DrawingContext};
 use crate::shapes::color::Color;
 use crate::shapes::shared_c_resources::{Vec2, Vertex};
+use crate::shapes::draw_parameters::DrawParameters;
 use crate::shapes::shape::{BaseShape, Shape};
 use crate::shapes::default_shader::default_shader;
 
@@ -14,61 +12,38 @@ pub struct Square {
     uniforms: Color
 }
 
-pub fn shader_meta() -> ShaderMeta {
-    ShaderMeta {
-        images: vec![],
-        uniforms: UniformBlockLayout {
-            uniforms: vec![
-                UniformDesc::new("red", UniformType::Float1),
-                UniformDesc::new("green", UniformType::Float1),
-                UniformDesc::new("blue", UniformType::Float1),
-            ],
-        },
-    }
-}
-
 impl Shape for Square {
 
-    fn get_bindings(&self) -> &Bindings {
-        &self.base_details.get_bindings()
-    }
-
-    fn get_pipeline(&self) -> &Pipeline {
-        &self.base_details.get_pipeline()
-    }
-
-    fn get_segments(&self) -> i32 {
-        self.base_details.get_segments()
-    }
-
-    fn set_binding(&mut self, bindings: Bindings) {
-        self.base_details.set_binding(bindings);
+    fn base(&self) -> &BaseShape {
+        &self.base_details
     }
 
-    fn set_pipeline(&mut self, pipeline: Pipeline) {
-        self.base_details.set_pipeline(pipeline);
+    fn base_mut(&mut self) -> &mut BaseShape {
+        &mut self.base_details
     }
 
-    fn set_segments(&mut self, segments: i32) {
-        self.base_details.set_segments(segments);
-    }
-
-    fn draw(&mut self, drawing_context: &mut Box<dyn RenderingBackend>, draw: bool) {
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, draw: bool) {
         self.base_details.draw(drawing_context, false);
 
-        drawing_context.apply_uniforms(UniformsSource::table(&self.uniforms));
+        drawing_context.apply_uniforms(&default_shader::Uniforms::new(
+            &self.base_details,
+            parameters,
+            &self.uniforms
+        ));
 
         if draw {
             drawing_context.draw(0, self.base_details.get_segments(), 1);
         }
     }
-
-    fn input_down(&mut self, key_code: KeyCode) {}
-    fn input_up(&mut self, key_code: KeyCode) {}
 }
 impl Square {
 
-    pub fn new(context: &mut Box<dyn RenderingBackend>,
+    pub fn get_uniform(&mut self) -> &mut Color {
+        &mut self.uniforms
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(context: &mut dyn DrawingContext,
                x: f32,
                y: f32,
                width: f32,
@@ -83,19 +58,17 @@ impl Square {
             Vertex { pos : Vec2 { x: width + x, y: height + y }, uv: Vec2 { x: 1., y: 1. } },
             Vertex { pos : Vec2 { x: -width + x, y: height + y }, uv: Vec2 { x: 0., y: 1. } },
         ];
-        let vertex_buffer = context.new_buffer(
-            BufferType::VertexBuffer,
+        let vertex_buffer = context.new_vertex_buffer(
             BufferUsage::Immutable,
-            BufferSource::slice(&vertices),
+            &vertices,
         );
 
         // Used to specify the order of the vertex indices
         // Is used to form a square using two triangles.
         let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
-        let index_buffer = context.new_buffer(
-            BufferType::IndexBuffer,
+        let index_buffer = context.new_index_buffer(
             BufferUsage::Immutable,
-            BufferSource::slice(&indices),
+            &indices,
         );
 
         let pixels: [u8; 4 * 4 * 4] = [
@@ -115,30 +88,22 @@ impl Square {
 
         let shader = context
             .new_shader(
-                ShaderSource::Glsl {
-                    vertex: default_shader::VERTEX,
-                    fragment: default_shader::FRAGMENT,
-                },
-                shader_meta(),
+                default_shader::VERTEX,
+                default_shader::FRAGMENT,
+                default_shader::meta(),
             )
             .unwrap();
 
-        let pipeline = context.new_pipeline(
-            &[BufferLayout::default()],
-            &[
-                VertexAttribute::new("in_pos", VertexFormat::Float2),
-                VertexAttribute::new("in_uv", VertexFormat::Float2),
-            ],
-            shader,
-        );
+        let pipeline = context.new_pipeline(shader, BlendMode::Alpha);
 
 
-        Square {
+        let mut square = Square {
             base_details:
             BaseShape::new (
                 bindings,
                 pipeline,
-                6
+                6,
+                Vec2 { x, y }
             ),
             uniforms:
                 Color::new (
@@ -146,6 +111,8 @@ impl Square {
                     green,
                     blue,
                 )
-        }
+        };
+        square.base_details.set_hit_mesh(&vertices, &indices);
+        square
     }
 }
\ No newline at end of file
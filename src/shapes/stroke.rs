@@ -0,0 +1,195 @@
+use std::f32::consts::PI;
+use crate::shapes::shared_c_resources::Vec2;
+
+// Miter joins longer than this many half-widths fall back to a bevel,
+// the same default SVG uses.
+pub const MITER_LIMIT: f32 = 4.;
+
+// Largest angle a single triangle of a round join or cap may cover.
+const ROUND_STEP: f32 = PI / 8.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineJoin {
+    Miter,
+    Bevel,
+    Round
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineCap {
+    Butt,
+    Square,
+    Round
+}
+
+// Expands a polyline into a triangle list. Every segment is its own
+// quad, joins and caps only fill in the outside of each corner so
+// inner corners overlap rather than being trimmed.
+pub fn stroke(points: &[Vec2], width: f32, join: LineJoin, cap: LineCap, closed: bool) -> (Vec<Vec2>, Vec<u16>) {
+    let mut points: Vec<(f32, f32)> = points.iter().map(|point| (point.x, point.y)).collect();
+    points.dedup();
+    if closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    let mut mesh = Mesh { positions: vec![], indices: vec![] };
+    if points.len() < 2 {
+        return (vec![], vec![]);
+    }
+
+    let half = width * 0.5;
+    let count = points.len();
+    let segment_count = if closed { count } else { count - 1 };
+
+    for segment in 0..segment_count {
+        let mut start = points[segment];
+        let mut end = points[(segment + 1) % count];
+        let direction = normalize(sub(end, start));
+        let normal = perpendicular(direction);
+
+        if !closed && cap == LineCap::Square {
+            if segment == 0 {
+                start = sub(start, scale(direction, half));
+            }
+            if segment == segment_count - 1 {
+                end = add(end, scale(direction, half));
+            }
+        }
+
+        let base = mesh.push(add(start, scale(normal, half)));
+        mesh.push(sub(start, scale(normal, half)));
+        mesh.push(sub(end, scale(normal, half)));
+        mesh.push(add(end, scale(normal, half)));
+        mesh.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    let joined = if closed { 0..count } else { 1..count - 1 };
+    for corner in joined {
+        let previous = points[(corner + count - 1) % count];
+        let point = points[corner];
+        let next = points[(corner + 1) % count];
+        add_join(&mut mesh, previous, point, next, half, join);
+    }
+
+    if !closed && cap == LineCap::Round {
+        add_round_cap(&mut mesh, points[0], normalize(sub(points[0], points[1])), half);
+        add_round_cap(&mut mesh, points[count - 1], normalize(sub(points[count - 1], points[count - 2])), half);
+    }
+
+    let positions = mesh.positions.into_iter().map(|(x, y)| Vec2 { x, y }).collect();
+    (positions, mesh.indices)
+}
+
+struct Mesh {
+    positions: Vec<(f32, f32)>,
+    indices: Vec<u16>
+}
+
+impl Mesh {
+
+    fn push(&mut self, position: (f32, f32)) -> u16 {
+        self.positions.push(position);
+        (self.positions.len() - 1) as u16
+    }
+
+    fn fan(&mut self, center: (f32, f32), rim: &[(f32, f32)]) {
+        let center = self.push(center);
+        let first = self.positions.len() as u16;
+        rim.iter().for_each(|position| { self.push(*position); });
+        for offset in 0..rim.len().saturating_sub(1) as u16 {
+            self.indices.extend_from_slice(&[center, first + offset, first + offset + 1]);
+        }
+    }
+}
+
+fn add_join(mesh: &mut Mesh, previous: (f32, f32), point: (f32, f32), next: (f32, f32), half: f32, join: LineJoin) {
+    let incoming = normalize(sub(point, previous));
+    let outgoing = normalize(sub(next, point));
+    let turn = cross(incoming, outgoing);
+    if turn.abs() < f32::EPSILON && dot(incoming, outgoing) > 0. {
+        return;
+    }
+
+    // The gap opens on the right of a left turn and vice versa.
+    let side = if turn > 0. { -1. } else { 1. };
+    let incoming_normal = scale(perpendicular(incoming), side);
+    let outgoing_normal = scale(perpendicular(outgoing), side);
+    let incoming_edge = add(point, scale(incoming_normal, half));
+    let outgoing_edge = add(point, scale(outgoing_normal, half));
+
+    match join {
+        LineJoin::Miter => {
+            let bisector = add(incoming_normal, outgoing_normal);
+            let cosine = dot(normalize(bisector), incoming_normal);
+            if length(bisector) > f32::EPSILON && 1. / cosine <= MITER_LIMIT {
+                let tip = add(point, scale(normalize(bisector), half / cosine));
+                mesh.fan(point, &[incoming_edge, tip, outgoing_edge]);
+            } else {
+                mesh.fan(point, &[incoming_edge, outgoing_edge]);
+            }
+        }
+        LineJoin::Bevel => mesh.fan(point, &[incoming_edge, outgoing_edge]),
+        LineJoin::Round => {
+            let start = incoming_normal.1.atan2(incoming_normal.0);
+            let mut sweep = outgoing_normal.1.atan2(outgoing_normal.0) - start;
+            if sweep > PI {
+                sweep -= 2. * PI;
+            } else if sweep < -PI {
+                sweep += 2. * PI;
+            }
+            mesh.fan(point, &arc(point, half, start, sweep));
+        }
+    }
+}
+
+// Half disc centred on the end point, bulging towards `outward`.
+fn add_round_cap(mesh: &mut Mesh, point: (f32, f32), outward: (f32, f32), half: f32) {
+    let normal = perpendicular(outward);
+    let start = normal.1.atan2(normal.0);
+    let sweep = if cross(normal, outward) > 0. { PI } else { -PI };
+    mesh.fan(point, &arc(point, half, start, sweep));
+}
+
+fn arc(center: (f32, f32), radius: f32, start: f32, sweep: f32) -> Vec<(f32, f32)> {
+    let steps = (sweep.abs() / ROUND_STEP).ceil().max(1.) as usize;
+    (0..=steps)
+        .map(|step| {
+            let angle = start + sweep * step as f32 / steps as f32;
+            (center.0 + angle.cos() * radius, center.1 + angle.sin() * radius)
+        })
+        .collect()
+}
+
+fn add(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: (f32, f32), factor: f32) -> (f32, f32) {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn dot(a: (f32, f32), b: (f32, f32)) -> f32 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn cross(a: (f32, f32), b: (f32, f32)) -> f32 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+fn length(a: (f32, f32)) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn normalize(a: (f32, f32)) -> (f32, f32) {
+    let length = length(a);
+    if length == 0. { a } else { scale(a, 1. / length) }
+}
+
+// Left hand normal.
+fn perpendicular(a: (f32, f32)) -> (f32, f32) {
+    (-a.1, a.0)
+}
@@ -0,0 +1,106 @@
+use crate::shapes::matrix::Mat4;
+use crate::shapes::shared_c_resources::Vec2;
+
+// Applied on top of the vertices a shape was built with, so the
+// defaults leave it exactly where its constructor put it. Pivot is
+// in the same coordinates as those vertices, rotation and scale
+// happen around it.
+pub struct Transform {
+    position: Vec2,
+    rotation: f32,
+    scale: Vec2,
+    pivot: Vec2,
+    // Pose before the latest fixed update, to_local_matrix blends
+    // from it towards the current pose.
+    previous_position: Vec2,
+    previous_rotation: f32,
+    previous_scale: Vec2
+}
+
+impl Transform {
+
+    pub fn new(pivot_x: f32, pivot_y: f32) -> Transform {
+        Transform {
+            position: Vec2 { x: 0., y: 0. },
+            rotation: 0.,
+            scale: Vec2 { x: 1., y: 1. },
+            pivot: Vec2 { x: pivot_x, y: pivot_y },
+            previous_position: Vec2 { x: 0., y: 0. },
+            previous_rotation: 0.,
+            previous_scale: Vec2 { x: 1., y: 1. }
+        }
+    }
+
+    pub fn get_position(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    pub fn get_scale(&self) -> Vec2 {
+        self.scale
+    }
+
+    pub fn get_pivot(&self) -> Vec2 {
+        self.pivot
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.position = Vec2 { x, y };
+    }
+
+    // Radians, counter-clockwise.
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+
+    pub fn set_scale(&mut self, x: f32, y: f32) {
+        self.scale = Vec2 { x, y };
+    }
+
+    pub fn set_pivot(&mut self, x: f32, y: f32) {
+        self.pivot = Vec2 { x, y };
+    }
+
+    // Called before each fixed update. Calling it again right after
+    // a set_position makes the move a jump instead of a slide.
+    pub fn save_previous(&mut self) {
+        self.previous_position = self.position;
+        self.previous_rotation = self.rotation;
+        self.previous_scale = self.scale;
+    }
+
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.position.x += x;
+        self.position.y += y;
+    }
+
+    pub fn rotate(&mut self, rotation: f32) {
+        self.rotation += rotation;
+    }
+
+    // translate(position) * translate(pivot) * rotate * scale * translate(-pivot)
+    // using the pose interpolation of the way from the previous pose
+    // to the current one, 0 is the previous pose and 1 the current.
+    pub fn to_local_matrix(&self, interpolation: f32) -> Mat4 {
+        let interpolation = interpolation.clamp(0., 1.);
+        let position = lerp(self.previous_position, self.position, interpolation);
+        let rotation = self.previous_rotation + (self.rotation - self.previous_rotation) * interpolation;
+        let scale = lerp(self.previous_scale, self.scale, interpolation);
+
+        Mat4::translation(position.x + self.pivot.x, position.y + self.pivot.y)
+            .multiply(&Mat4::rotation(rotation))
+            .multiply(&Mat4::scale(scale.x, scale.y))
+            .multiply(&Mat4::translation(-self.pivot.x, -self.pivot.y))
+    }
+
+}
+
+fn lerp(from: Vec2, to: Vec2, t: f32) -> Vec2 {
+    Vec2 {
+        x: from.x + (to.x - from.x) * t,
+        y: from.y + (to.y - from.y) * t
+    }
+}
@@ -1,7 +1,15 @@
-use miniquad::{ Bindings, BufferLayout, BufferSource, BufferType, BufferUsage, KeyCode, Pipeline, RenderingBackend, ShaderMeta, ShaderSource, UniformBlockLayout, UniformDesc, UniformsSource, UniformType, VertexAttribute, VertexFormat};
+use miniquad::BufferUsage;
+use crate::backend::drawing_context::{Bindings, BlendMode, DrawingContext};
 use crate::shapes::shared_c_resources::{Vec2, Vertex};
+use crate::shapes::color::Color;
+use crate::shapes::draw_parameters::DrawParameters;
 use crate::shapes::shape::{BaseShape, Shape};
 use crate::shapes::default_shader::default_shader;
+use crate::input::input_state::InputState;
+
+// Axes the triangle is steered with, see ActionMap.
+pub const MOVE_X: &str = "move_x";
+pub const MOVE_Y: &str = "move_y";
 
 pub struct Triangle {
     base_details: BaseShape,
@@ -12,14 +20,7 @@ pub struct Triangle {
 pub struct TriangleModel {
     red: f32,
     green: f32,
-    blue: f32,
-    offset: Vec2,
-    input: InputModel
-}
-
-#[repr(C)]
-pub struct InputModel {
-    keys_down: Vec<KeyCode>
+    blue: f32
 }
 
 impl TriangleModel {
@@ -36,84 +37,47 @@ impl TriangleModel {
     }
 }
 
-fn shader_meta() -> ShaderMeta {
-    ShaderMeta {
-        images: vec!["tex".to_string()],
-        uniforms: UniformBlockLayout {
-            uniforms: vec![
-                UniformDesc::new("red", UniformType::Float1),
-                UniformDesc::new("green", UniformType::Float1),
-                UniformDesc::new("blue", UniformType::Float1),
-                UniformDesc::new("offset", UniformType::Float2),
-            ],
-        },
-    }
-}
-
 impl Shape for Triangle {
 
-    fn get_bindings(&self) -> &Bindings {
-        &self.base_details.get_bindings()
+    fn base(&self) -> &BaseShape {
+        &self.base_details
     }
 
-    fn get_pipeline(&self) -> &Pipeline {
-        &self.base_details.get_pipeline()
+    fn base_mut(&mut self) -> &mut BaseShape {
+        &mut self.base_details
     }
 
-    fn get_segments(&self) -> i32 {
-        self.base_details.get_segments()
-    }
-
-    fn set_binding(&mut self, bindings: Bindings) {
-        self.base_details.set_binding(bindings);
-    }
-
-    fn set_pipeline(&mut self, pipeline: Pipeline) {
-        self.base_details.set_pipeline(pipeline);
-    }
+    fn update(&mut self, dt: f32, input: &InputState) {
+        // Units per second, what 0.009 per frame was at 60 fps.
+        const SPEED: f32 = 0.54;
+        // Red lost per second.
+        const FADE: f32 = 6.;
+
+        // Moved through the transform so fixed updates are
+        // interpolated when drawn.
+        let distance = SPEED * dt;
+        self.base_details.get_transform_mut().translate(
+            input.get_axis(MOVE_X) * distance,
+            input.get_axis(MOVE_Y) * distance
+        );
 
-    fn set_segments(&mut self, segments: i32) {
-        self.base_details.set_segments(segments);
+        self.uniforms.red = (self.uniforms.red - FADE * dt).max(0.);
     }
 
-    fn draw(&mut self, drawing_context: &mut Box<dyn RenderingBackend>, draw: bool) {
+    fn draw(&mut self, drawing_context: &mut dyn DrawingContext, parameters: &DrawParameters, draw: bool) {
         self.base_details.draw(drawing_context, false);
 
-        const ACCEL: f32 = 0.009;
-
-        if let Some(key_code) = self.uniforms.input.keys_down.iter().position(|key_down | key_down == &KeyCode::Down) {
-            self.uniforms.offset.y -= ACCEL;
-        }
-        if let Some(key_code) = self.uniforms.input.keys_down.iter().position(|key_down | key_down == &KeyCode::Up) {
-            self.uniforms.offset.y += ACCEL;
-        }
-        if let Some(key_code) = self.uniforms.input.keys_down.iter().position(|key_down | key_down == &KeyCode::Left) {
-            self.uniforms.offset.x -= ACCEL;
-        }
-        if let Some(key_code) = self.uniforms.input.keys_down.iter().position(|key_down | key_down == &KeyCode::Right) {
-            self.uniforms.offset.x += ACCEL;
-        }
-
-        self.uniforms.red -= 0.1;
-
-        drawing_context.apply_uniforms(UniformsSource::table(&self.uniforms));
+        let uniforms = default_shader::Uniforms::new(
+            &self.base_details,
+            parameters,
+            &Color::new(self.uniforms.red, self.uniforms.green, self.uniforms.blue)
+        );
+        drawing_context.apply_uniforms(&uniforms);
 
         if draw {
             drawing_context.draw(0, self.base_details.get_segments(), 1);
         }
     }
-
-    fn input_down(&mut self, key_code: KeyCode) {
-        if let None = self.uniforms.input.keys_down.iter().position(|key_down | key_down == &key_code) {
-            self.get_uniform().input.keys_down.push(key_code);
-        }
-    }
-
-    fn input_up(&mut self, key_code: KeyCode) {
-        if let Some(found_index) = self.get_uniform().input.keys_down.iter().position(|key| key == &key_code) {
-            self.get_uniform().input.keys_down.remove(found_index);
-        }
-    }
 }
 
 impl Triangle {
@@ -122,7 +86,8 @@ impl Triangle {
         &mut self.uniforms
     }
 
-    pub fn new(context: &mut Box<dyn RenderingBackend>,
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(context: &mut dyn DrawingContext,
                x: f32,
                y: f32,
                width: f32,
@@ -137,19 +102,17 @@ impl Triangle {
             Vertex { pos : Vec2 { x: width + x, y: -height + y }, uv: Vec2 { x: 1., y: 0. } },
             Vertex { pos : Vec2 { x, y: height + y }, uv: Vec2 { x: 1., y: 1. } },
         ];
-        let vertex_buffer = context.new_buffer(
-            BufferType::VertexBuffer,
+        let vertex_buffer = context.new_vertex_buffer(
             BufferUsage::Immutable,
-            BufferSource::slice(&vertices),
+            &vertices,
         );
 
         // Used to specify the order of the vertex indices
         // Is used to form a square using two triangles.
         let indices: [u16; 3] = [0, 1, 2];
-        let index_buffer = context.new_buffer(
-            BufferType::IndexBuffer,
+        let index_buffer = context.new_index_buffer(
             BufferUsage::Immutable,
-            BufferSource::slice(&indices),
+            &indices,
         );
 
         let pixels: [u8; 4 * 4 * 4] = [
@@ -169,44 +132,31 @@ impl Triangle {
 
         let shader = context
             .new_shader(
-                ShaderSource::Glsl {
-                    vertex: default_shader::VERTEX,
-                    fragment: default_shader::FRAGMENT,
-                },
-                shader_meta(),
+                default_shader::VERTEX,
+                default_shader::FRAGMENT,
+                default_shader::meta(),
             )
             .unwrap();
 
-        let pipeline = context.new_pipeline(
-            &[BufferLayout::default()],
-            &[
-                VertexAttribute::new("in_pos", VertexFormat::Float2),
-                VertexAttribute::new("in_uv", VertexFormat::Float2),
-            ],
-            shader,
-        );
+        let pipeline = context.new_pipeline(shader, BlendMode::Alpha);
 
 
-        Triangle {
+        let mut triangle = Triangle {
             base_details:
                 BaseShape::new (
                     bindings,
                     pipeline,
-                    3
+                    3,
+                    Vec2 { x, y }
                 ),
             uniforms:
                 TriangleModel {
                     red,
                     green,
-                    blue,
-                    offset: Vec2 {
-                        x: 0f32,
-                        y: 0f32
-                    },
-                    input: InputModel {
-                        keys_down: vec![]
-                    }
+                    blue
                 }
-        }
+        };
+        triangle.base_details.set_hit_mesh(&vertices, &indices);
+        triangle
     }
 }
\ No newline at end of file
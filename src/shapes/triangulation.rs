@@ -0,0 +1,341 @@
+use crate::shapes::shared_c_resources::Vec2;
+
+// Ear clipping triangulation for simple polygons, concave or convex.
+// Vertices are numbered outline first, then each hole in order, the
+// returned indices point into that combined list and are wound
+// counter-clockwise.
+pub fn triangulate(outline: &[Vec2], holes: &[Vec<Vec2>]) -> Vec<u16> {
+    let points: Vec<&Vec2> = outline.iter().chain(holes.iter().flatten()).collect();
+    if outline.len() < 3 {
+        return vec![];
+    }
+
+    // Outline runs counter-clockwise, holes clockwise, so after
+    // splicing every hole in the interior is always on the left.
+    let mut ring: Vec<usize> = (0..outline.len()).collect();
+    if signed_area(&points, &ring) < 0. {
+        ring.reverse();
+    }
+
+    let mut hole_rings: Vec<Vec<usize>> = vec![];
+    let mut start = outline.len();
+    for hole in holes {
+        let mut hole_ring: Vec<usize> = (start..start + hole.len()).collect();
+        start += hole.len();
+        if hole_ring.len() < 3 {
+            continue;
+        }
+        if signed_area(&points, &hole_ring) > 0. {
+            hole_ring.reverse();
+        }
+        hole_rings.push(hole_ring);
+    }
+
+    // Rightmost holes first so each bridge only has to look at
+    // the outline plus holes already merged into it.
+    hole_rings.sort_by(|a, b| max_x(&points, b).total_cmp(&max_x(&points, a)));
+    for hole_ring in hole_rings {
+        ring = bridge_hole(&points, ring, &hole_ring);
+    }
+
+    clip_ears(&points, ring)
+}
+
+fn signed_area(points: &[&Vec2], ring: &[usize]) -> f32 {
+    let mut area = 0.;
+    for i in 0..ring.len() {
+        let a = points[ring[i]];
+        let b = points[ring[(i + 1) % ring.len()]];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area * 0.5
+}
+
+fn max_x(points: &[&Vec2], ring: &[usize]) -> f32 {
+    ring.iter().map(|index| points[*index].x).fold(f32::MIN, f32::max)
+}
+
+fn cross(a: &Vec2, b: &Vec2, c: &Vec2) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+fn same_position(a: &Vec2, b: &Vec2) -> bool {
+    a.x == b.x && a.y == b.y
+}
+
+// Inclusive of the edges, a vertex touching a candidate ear
+// must still block it.
+fn in_triangle(a: &Vec2, b: &Vec2, c: &Vec2, point: &Vec2) -> bool {
+    cross(a, b, point) >= 0. && cross(b, c, point) >= 0. && cross(c, a, point) >= 0.
+}
+
+// Joins a hole to the outline with a zero-width slit from the hole's
+// rightmost vertex to a visible outline vertex, turning the pair into
+// a single ring that ear clipping can handle.
+fn bridge_hole(points: &[&Vec2], ring: Vec<usize>, hole: &[usize]) -> Vec<usize> {
+    let hole_start = (0..hole.len())
+        .max_by(|a, b| points[hole[*a]].x.total_cmp(&points[hole[*b]].x))
+        .unwrap();
+    let m = points[hole[hole_start]];
+
+    // Cast a ray towards +x and find the closest outline edge it hits.
+    let mut closest: Option<(f32, usize)> = None;
+    for i in 0..ring.len() {
+        let a = points[ring[i]];
+        let b = points[ring[(i + 1) % ring.len()]];
+        if a.y == b.y || m.y < a.y.min(b.y) || m.y > a.y.max(b.y) {
+            continue;
+        }
+        let x = a.x + (m.y - a.y) * (b.x - a.x) / (b.y - a.y);
+        if x >= m.x && closest.is_none_or(|(closest_x, _)| x < closest_x) {
+            closest = Some((x, i));
+        }
+    }
+
+    let Some((hit_x, edge)) = closest else {
+        // Hole is not inside the outline, nothing sensible to cut.
+        return ring;
+    };
+
+    // Of the edge's two ends the one further right is visible from m.
+    let next = (edge + 1) % ring.len();
+    let mut bridge = if points[ring[edge]].x > points[ring[next]].x { edge } else { next };
+    let hit = Vec2 { x: hit_x, y: m.y };
+
+    // Another outline vertex inside the triangle (m, hit, candidate)
+    // would block the slit, take the one closest in angle instead.
+    let candidate = points[ring[bridge]];
+    let mut best_tangent = f32::MAX;
+    for (position, index) in ring.iter().enumerate() {
+        let point = points[*index];
+        if position == bridge || point.x < m.x || same_position(point, candidate) {
+            continue;
+        }
+        let inside = in_triangle(m, &hit, candidate, point) || in_triangle(m, candidate, &hit, point);
+        if !inside {
+            continue;
+        }
+        let tangent = (point.y - m.y).abs() / (point.x - m.x).max(f32::EPSILON);
+        if tangent < best_tangent {
+            best_tangent = tangent;
+            bridge = position;
+        }
+    }
+
+    let mut merged = Vec::with_capacity(ring.len() + hole.len() + 2);
+    merged.extend_from_slice(&ring[..=bridge]);
+    merged.extend((0..hole.len()).map(|offset| hole[(hole_start + offset) % hole.len()]));
+    merged.push(hole[hole_start]);
+    merged.extend_from_slice(&ring[bridge..]);
+    merged
+}
+
+fn clip_ears(points: &[&Vec2], mut ring: Vec<usize>) -> Vec<u16> {
+    let mut indices = Vec::with_capacity((ring.len().saturating_sub(2)) * 3);
+    let mut position = 0;
+    let mut attempts = 0;
+
+    while ring.len() > 3 {
+        let count = ring.len();
+        position %= count;
+        let (a, b, c) = corner(points, &ring, position);
+        let turn = cross(a, b, c);
+
+        // Repeated points and zero-width spikes add nothing, drop them.
+        // A vertex partway along a straight edge stays, the triangles
+        // on the far side of a hole bridge share it.
+        if turn == 0. && !continues_straight(a, b, c) {
+            ring.remove(position);
+            attempts = 0;
+            continue;
+        }
+
+        if turn > 0. && !blocks_ear(points, &ring, a, b, c) {
+            clip(&mut indices, &mut ring, position);
+            attempts = 0;
+        } else if attempts >= count {
+            // A full lap without an ear means self-intersecting input.
+            // Clip the first convex corner so the loop terminates, with
+            // none left there is no area to fill.
+            match (0..count).find(|&candidate| {
+                let (a, b, c) = corner(points, &ring, candidate);
+                cross(a, b, c) > 0.
+            }) {
+                Some(convex) => {
+                    clip(&mut indices, &mut ring, convex);
+                    position = convex;
+                    attempts = 0;
+                }
+                None => break
+            }
+        } else {
+            position += 1;
+            attempts += 1;
+        }
+    }
+
+    if ring.len() == 3 {
+        let (a, b, c) = corner(points, &ring, 1);
+        if cross(a, b, c) > 0. {
+            indices.extend_from_slice(&[ring[0] as u16, ring[1] as u16, ring[2] as u16]);
+        }
+    }
+    indices
+}
+
+// The vertex at position with the ones either side of it.
+fn corner<'a>(points: &[&'a Vec2], ring: &[usize], position: usize) -> (&'a Vec2, &'a Vec2, &'a Vec2) {
+    let count = ring.len();
+    (
+        points[ring[(position + count - 1) % count]],
+        points[ring[position]],
+        points[ring[(position + 1) % count]]
+    )
+}
+
+fn clip(indices: &mut Vec<u16>, ring: &mut Vec<usize>, position: usize) {
+    let count = ring.len();
+    indices.extend_from_slice(&[
+        ring[(position + count - 1) % count] as u16,
+        ring[position] as u16,
+        ring[(position + 1) % count] as u16
+    ]);
+    ring.remove(position);
+}
+
+// Collinear and carrying on the same way, rather than doubling back.
+fn continues_straight(a: &Vec2, b: &Vec2, c: &Vec2) -> bool {
+    (b.x - a.x) * (c.x - b.x) + (b.y - a.y) * (c.y - b.y) > 0.
+}
+
+// Any other vertex inside or on the candidate ear, copies of its
+// own corners made by a hole bridge aside.
+fn blocks_ear(points: &[&Vec2], ring: &[usize], a: &Vec2, b: &Vec2, c: &Vec2) -> bool {
+    ring.iter().any(|index| {
+        let point = points[*index];
+        !same_position(point, a)
+            && !same_position(point, b)
+            && !same_position(point, c)
+            && in_triangle(a, b, c, point)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(coordinates: &[(f32, f32)]) -> Vec<Vec2> {
+        coordinates.iter().map(|&(x, y)| Vec2 { x, y }).collect()
+    }
+
+    fn polygon_area(ring: &[Vec2]) -> f32 {
+        let references: Vec<&Vec2> = ring.iter().collect();
+        signed_area(&references, &(0..ring.len()).collect::<Vec<_>>()).abs()
+    }
+
+    // Every triangle wound counter-clockwise, adding up to exactly
+    // the outline minus the holes, so nothing overlaps or is missing.
+    fn assert_covers(outline: &[Vec2], holes: &[Vec<Vec2>]) -> Vec<u16> {
+        let indices = triangulate(outline, holes);
+        let all: Vec<&Vec2> = outline.iter().chain(holes.iter().flatten()).collect();
+        assert_eq!(indices.len() % 3, 0);
+
+        let mut total = 0.;
+        for triangle in indices.chunks_exact(3) {
+            let (a, b, c) = (all[triangle[0] as usize], all[triangle[1] as usize], all[triangle[2] as usize]);
+            let area = cross(a, b, c) * 0.5;
+            assert!(area >= 0., "triangle {:?} is wound clockwise", triangle);
+            total += area;
+        }
+
+        let expected = polygon_area(outline) - holes.iter().map(|hole| polygon_area(hole)).sum::<f32>();
+        assert!((total - expected).abs() < 1e-4, "triangles cover {} of {}", total, expected);
+
+        // Watertight: an edge is either on the outline or a hole, or
+        // shared with a neighbour running the other way, so there
+        // are no T-junctions for the rasterizer to crack along.
+        let key = |point: &Vec2| (point.x.to_bits(), point.y.to_bits());
+        let edges: Vec<_> = indices.chunks_exact(3)
+            .flat_map(|triangle| (0..3).map(move |i| (triangle[i], triangle[(i + 1) % 3])))
+            .map(|(from, to)| (key(all[from as usize]), key(all[to as usize])))
+            .collect();
+        let mut rings = vec![outline.to_vec()];
+        rings.extend(holes.iter().cloned());
+        let boundary: Vec<_> = rings.iter()
+            .flat_map(|ring| (0..ring.len()).map(move |i| (key(&ring[i]), key(&ring[(i + 1) % ring.len()]))))
+            .collect();
+        for &(from, to) in edges.iter() {
+            let shared = edges.contains(&(to, from));
+            let on_boundary = boundary.contains(&(from, to)) || boundary.contains(&(to, from));
+            assert!(shared || on_boundary, "edge {:?} -> {:?} has no neighbour", from, to);
+        }
+        indices
+    }
+
+    #[test]
+    fn convex() {
+        let indices = assert_covers(&points(&[(0., 0.), (2., 0.), (3., 1.), (2., 2.), (0., 2.)]), &[]);
+        assert_eq!(indices.len(), 3 * 3);
+    }
+
+    #[test]
+    fn clockwise_outline() {
+        assert_covers(&points(&[(0., 0.), (0., 1.), (1., 1.), (1., 0.)]), &[]);
+    }
+
+    #[test]
+    fn concave() {
+        assert_covers(&points(&[(-0.9, -0.5), (-0.1, -0.5), (-0.1, 0.), (-0.5, 0.), (-0.5, 0.5), (-0.9, 0.5)]), &[]);
+        assert_covers(&points(&[(0., 0.), (4., 0.), (4., 3.), (3., 1.), (2., 3.), (1., 1.), (0., 3.)]), &[]);
+    }
+
+    #[test]
+    fn collinear() {
+        let indices = assert_covers(&points(&[(0., 0.), (1., 0.), (2., 0.), (2., 2.), (1., 2.), (0., 2.)]), &[]);
+        assert!(!indices.is_empty());
+        assert!(triangulate(&points(&[(0., 0.), (1., 0.), (2., 0.)]), &[]).is_empty());
+    }
+
+    // No ear exists, the fallback must still only clip convex corners.
+    #[test]
+    fn self_intersecting() {
+        let all = points(&[(0., 0.), (4., 0.), (4., 4.), (3., 4.), (3., -1.), (1., -1.), (1., 4.), (0., 4.)]);
+        let references: Vec<&Vec2> = all.iter().collect();
+        for triangle in triangulate(&all, &[]).chunks_exact(3) {
+            let (a, b, c) = (references[triangle[0] as usize], references[triangle[1] as usize], references[triangle[2] as usize]);
+            assert!(cross(a, b, c) > 0., "triangle {:?} is not a convex corner", triangle);
+        }
+    }
+
+    #[test]
+    fn hole() {
+        let frame = points(&[(0.1, -0.5), (0.9, -0.5), (0.9, 0.5), (0.1, 0.5)]);
+        let window = points(&[(0.3, -0.3), (0.7, -0.3), (0.7, 0.3), (0.3, 0.3)]);
+        assert_covers(&frame, &[window]);
+    }
+
+    #[test]
+    fn hole_bridged_to_straight_vertex() {
+        let outline = points(&[(0., 0.), (10., 0.), (10., 3.), (10., 6.), (0., 6.)]);
+        let hole = points(&[(2., 2.), (2., 4.), (8., 3.)]);
+        assert_covers(&outline, &[hole]);
+    }
+
+    #[test]
+    fn several_holes() {
+        let outline = points(&[(0., 0.), (10., 0.), (10., 6.), (0., 6.)]);
+        let holes = vec![
+            points(&[(1., 1.), (1., 2.), (3., 2.), (3., 1.)]),
+            points(&[(5., 1.), (7., 1.), (6., 4.)]),
+            points(&[(8., 3.), (9., 3.), (9., 5.), (8., 5.)]),
+        ];
+        assert_covers(&outline, &holes);
+    }
+
+    #[test]
+    fn hole_in_concave_outline() {
+        let outline = points(&[(0., 0.), (6., 0.), (6., 6.), (4., 6.), (4., 2.), (2., 2.), (2., 6.), (0., 6.)]);
+        let hole = points(&[(0.5, 0.5), (5.5, 0.5), (5.5, 1.5), (0.5, 1.5)]);
+        assert_covers(&outline, &[hole]);
+    }
+}
@@ -0,0 +1,509 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use miniquad::{FilterMode, PassAction, TextureWrap};
+use crate::backend::drawing_context::{BlendMode, DrawingContext};
+use crate::backend::framebuffer::Framebuffer;
+use crate::backend::atlas::AtlasBuilder;
+use crate::backend::software::SoftwareBackend;
+use crate::backend::sprite_sheet::SpriteSheet;
+use crate::backend::texture::Texture;
+use crate::scene::camera::Camera2D;
+use crate::scene::scene_file;
+use crate::scene::scene_graph::SceneGraph;
+use crate::shapes::animated_sprite::AnimatedSprite;
+use crate::shapes::animation::{AnimationClip, PlaybackMode};
+use crate::shapes::circle::Circle;
+use crate::shapes::color::Color;
+use crate::shapes::ellipse::Ellipse;
+use crate::shapes::gradient::{ColorStop, Gradient};
+use crate::shapes::polygon::Polygon;
+use crate::shapes::polyline::Polyline;
+use crate::shapes::shared_c_resources::Vec2;
+use crate::shapes::stroke::{LineCap, LineJoin};
+use crate::shapes::shape::Shape;
+use crate::shapes::sprite::{Region, Sprite};
+use crate::shapes::square::Square;
+
+// Golden images live next to Cargo.toml, diffs are written beside
+// them as <name>.diff.png when a comparison fails.
+pub const SNAPSHOT_DIRECTORY: &str = "snapshots";
+// Sprite sheet fixtures are built in, so only the golden images and
+// the stage scene are looked up when the snapshots run.
+const STRIP_JSON: &str = include_str!("../snapshots/sheets/strip.json");
+const STRIP_PNG: &[u8] = include_bytes!("../snapshots/sheets/strip.png");
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 600;
+
+// Largest per-channel difference still treated as equal, absorbs
+// float rounding between machines.
+pub const DEFAULT_TOLERANCE: u8 = 2;
+
+pub type SceneBuilder = Box<dyn Fn(&mut dyn DrawingContext) -> SceneGraph>;
+
+pub struct Scene {
+    pub name: &'static str,
+    pub build: SceneBuilder,
+    pub tolerance: u8
+}
+
+// stage_file is the scene file the window shows.
+pub fn scenes(stage_file: &Path) -> Vec<Scene> {
+    let stage_file = stage_file.to_path_buf();
+    vec![
+        Scene {
+            name: "stage",
+            build: Box::new(move |context| stage_scene(context, &stage_file)),
+            tolerance: DEFAULT_TOLERANCE
+        },
+        Scene {
+            name: "circles",
+            build: Box::new(circles_scene),
+            tolerance: DEFAULT_TOLERANCE
+        },
+        Scene {
+            name: "polygons",
+            build: Box::new(polygons_scene),
+            tolerance: DEFAULT_TOLERANCE
+        },
+        Scene {
+            name: "polylines",
+            build: Box::new(polylines_scene),
+            tolerance: DEFAULT_TOLERANCE
+        },
+        Scene {
+            name: "hierarchy",
+            build: Box::new(hierarchy_scene),
+            tolerance: DEFAULT_TOLERANCE
+        },
+        Scene {
+            name: "layers",
+            build: Box::new(layers_scene),
+            tolerance: DEFAULT_TOLERANCE
+        },
+        Scene {
+            name: "blending",
+            build: Box::new(blending_scene),
+            tolerance: DEFAULT_TOLERANCE
+        },
+        Scene {
+            name: "gradients",
+            build: Box::new(gradients_scene),
+            tolerance: DEFAULT_TOLERANCE
+        },
+        Scene {
+            name: "sprites",
+            build: Box::new(sprites_scene),
+            tolerance: DEFAULT_TOLERANCE
+        },
+        Scene {
+            name: "atlas",
+            build: Box::new(atlas_scene),
+            tolerance: DEFAULT_TOLERANCE
+        },
+        Scene {
+            name: "animation",
+            build: Box::new(animation_scene),
+            tolerance: DEFAULT_TOLERANCE
+        },
+    ]
+}
+
+// An empty scene fails the comparison, the error says why.
+fn stage_scene(context: &mut dyn DrawingContext, path: &Path) -> SceneGraph {
+    scene_file::load(context, path).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        SceneGraph::new()
+    })
+}
+
+fn circles_scene(context: &mut dyn DrawingContext) -> SceneGraph {
+    let mut low_poly_circle = Box::new(Circle::new(context, 0.5, 0.5, 0.2, 64, 0.0, 0.0, 1.0));
+    low_poly_circle.set_segment_count(6);
+
+    SceneGraph::from_shapes(vec![
+        Box::new(Ellipse::new(context, -0.4, 0.0, 0.4, 0.2, 32, 1.0, 0.0, 0.0)),
+        Box::new(Circle::new(context, 0.3, -0.3, 0.25, 48, 0.0, 1.0, 0.0)),
+        low_poly_circle,
+    ])
+}
+
+fn polygons_scene(context: &mut dyn DrawingContext) -> SceneGraph {
+    let concave = [
+        Vec2 { x: -0.9, y: -0.5 }, Vec2 { x: -0.1, y: -0.5 }, Vec2 { x: -0.1, y: 0.0 },
+        Vec2 { x: -0.5, y: 0.0 }, Vec2 { x: -0.5, y: 0.5 }, Vec2 { x: -0.9, y: 0.5 },
+    ];
+    let frame = [
+        Vec2 { x: 0.1, y: -0.5 }, Vec2 { x: 0.9, y: -0.5 }, Vec2 { x: 0.9, y: 0.5 }, Vec2 { x: 0.1, y: 0.5 },
+    ];
+    let window = vec![
+        Vec2 { x: 0.3, y: -0.3 }, Vec2 { x: 0.7, y: -0.3 }, Vec2 { x: 0.7, y: 0.3 }, Vec2 { x: 0.3, y: 0.3 },
+    ];
+
+    SceneGraph::from_shapes(vec![
+        Box::new(Polygon::new(context, &concave, 1.0, 0.5, 0.0)),
+        Box::new(Polygon::new_with_holes(context, &frame, &[window], 0.2, 0.2, 0.8)),
+    ])
+}
+
+fn polylines_scene(context: &mut dyn DrawingContext) -> SceneGraph {
+    let zigzag = |y: f32| [
+        Vec2 { x: -0.8, y }, Vec2 { x: -0.5, y: y + 0.2 }, Vec2 { x: -0.2, y }, Vec2 { x: 0.1, y: y + 0.2 },
+    ];
+    let loop_points = [
+        Vec2 { x: 0.4, y: -0.4 }, Vec2 { x: 0.8, y: -0.4 }, Vec2 { x: 0.6, y: 0.0 },
+    ];
+
+    SceneGraph::from_shapes(vec![
+        Box::new(Polyline::new(context, &zigzag(0.5), 0.05, LineJoin::Miter, LineCap::Butt, false, 0.0, 0.0, 0.0)),
+        Box::new(Polyline::new(context, &zigzag(0.0), 0.05, LineJoin::Bevel, LineCap::Square, false, 0.8, 0.0, 0.0)),
+        Box::new(Polyline::new(context, &zigzag(-0.5), 0.05, LineJoin::Round, LineCap::Round, false, 0.0, 0.0, 0.8)),
+        Box::new(Polyline::new(context, &loop_points, 0.04, LineJoin::Miter, LineCap::Butt, true, 0.0, 0.6, 0.0)),
+    ])
+}
+
+// A body with two arms, rotating the group has to carry the arms
+// with it while the hidden third arm stays out of the image.
+fn hierarchy_scene(context: &mut dyn DrawingContext) -> SceneGraph {
+    let mut scene = SceneGraph::new();
+    let root = scene.get_root();
+
+    let body = scene.add_group(root);
+    scene.get_transform_mut(body).set_position(0.2, 0.1);
+    scene.get_transform_mut(body).set_rotation(0.5);
+
+    scene.add_shape(body, Box::new(Square::new(context, 0.0, 0.0, 0.1, 0.2, 0.3, 0.3, 0.3)));
+    let left_arm = scene.add_shape(body, Box::new(Square::new(context, -0.15, 0.1, 0.05, 0.1, 0.8, 0.2, 0.2)));
+    let right_arm = scene.add_shape(body, Box::new(Square::new(context, 0.15, 0.1, 0.05, 0.1, 0.2, 0.2, 0.8)));
+    let hidden_arm = scene.add_shape(right_arm, Box::new(Square::new(context, 0.3, 0.1, 0.05, 0.05, 1.0, 1.0, 0.0)));
+
+    if let Some(arm) = scene.get_shape_mut(left_arm) {
+        arm.get_transform_mut().set_rotation(-0.3);
+    }
+    scene.set_visible(hidden_arm, false);
+    scene
+}
+
+// Inserted red, green, blue but drawn red (lower layer), blue, green.
+fn layers_scene(context: &mut dyn DrawingContext) -> SceneGraph {
+    let mut scene = SceneGraph::new();
+    let root = scene.get_root();
+
+    let red = scene.add_shape(root, Box::new(Square::new(context, -0.1, 0.0, 0.2, 0.2, 1.0, 0.0, 0.0)));
+    let green = scene.add_shape(root, Box::new(Square::new(context, 0.0, -0.1, 0.2, 0.2, 0.0, 1.0, 0.0)));
+    let blue = scene.add_shape(root, Box::new(Square::new(context, 0.1, 0.1, 0.2, 0.2, 0.0, 0.0, 1.0)));
+
+    scene.send_to_back(blue);
+    scene.bring_to_front(green);
+    if let Some(shape) = scene.get_shape_mut(red) {
+        shape.set_layer(-1);
+    }
+    scene
+}
+
+// A grey strip with one half-transparent orange square per blend
+// mode across it, each square also straddles the black clear colour.
+fn blending_scene(context: &mut dyn DrawingContext) -> SceneGraph {
+    let mut scene = SceneGraph::new();
+    let root = scene.get_root();
+    scene.add_shape(root, Box::new(Square::new(context, 0.0, 0.0, 1.0, 0.2, 0.5, 0.5, 0.5)));
+
+    let blend_modes = [
+        BlendMode::Alpha, BlendMode::Premultiplied, BlendMode::Additive, BlendMode::Multiply, BlendMode::Screen,
+    ];
+    for (index, blend_mode) in blend_modes.iter().enumerate() {
+        let mut square = Box::new(Square::new(context, -0.8 + index as f32 * 0.4, 0.15, 0.12, 0.25, 1.0, 0.5, 0.0));
+        square.get_uniform().set_alpha(0.5);
+        if *blend_mode == BlendMode::Premultiplied {
+            square.get_uniform().set_red(0.5);
+            square.get_uniform().set_green(0.25);
+        }
+        square.set_blend_mode(*blend_mode);
+        scene.add_shape(root, square);
+    }
+    scene
+}
+
+// One shape per gradient kind, the linear one has a hard edge
+// from two stops sharing an offset.
+fn gradients_scene(context: &mut dyn DrawingContext) -> SceneGraph {
+    let stop = |offset: f32, color: &str| ColorStop::new(offset, Color::parse(color).unwrap());
+
+    let mut linear = Box::new(Square::new(context, -0.6, 0.3, 0.3, 0.25, 0.0, 0.0, 0.0));
+    linear.set_gradient(Some(Gradient::new_linear(
+        Vec2 { x: 0., y: 0. },
+        Vec2 { x: 1., y: 1. },
+        vec![stop(0.0, "gold"), stop(0.5, "crimson"), stop(0.5, "#1e90ff"), stop(1.0, "navy")]
+    )));
+
+    let mut radial = Box::new(Circle::new(context, 0.2, 0.3, 0.3, 64, 0.0, 0.0, 0.0));
+    radial.set_gradient(Some(Gradient::new_radial(
+        Vec2 { x: 0.5, y: 0.5 },
+        0.5,
+        vec![stop(0.0, "white"), stop(0.6, "rgb(255 128 0)"), stop(1.0, "rgba(255, 0, 0, 0)")]
+    )));
+
+    let mut conic = Box::new(Square::new(context, 0.0, -0.4, 0.3, 0.25, 0.0, 0.0, 0.0));
+    conic.set_gradient(Some(Gradient::new_conic(
+        Vec2 { x: 0.5, y: 0.5 },
+        0.0,
+        vec![stop(0.0, "hsl(0, 100%, 50%)"), stop(0.33, "hsl(120, 100%, 50%)"),
+             stop(0.66, "hsl(240, 100%, 50%)"), stop(1.0, "hsl(360, 100%, 50%)")]
+    )));
+
+    SceneGraph::from_shapes(vec![linear, radial, conic])
+}
+
+// Textures are generated so the snapshot does not depend on files.
+// Left: a 2x2 checker tiled four times with Nearest and Repeat.
+// Right: white fading to transparent, Linear filtered and tinted.
+fn sprites_scene(context: &mut dyn DrawingContext) -> SceneGraph {
+    let checker = Texture::from_rgba8(context, 2, 2, vec![
+        0, 0, 0, 255, 255, 255, 255, 255,
+        255, 255, 255, 255, 0, 0, 0, 255,
+    ]).unwrap();
+    checker.set_filter(context, FilterMode::Nearest);
+    checker.set_wrap(context, TextureWrap::Repeat);
+
+    let fade = Texture::from_rgba8(context, 2, 1, vec![
+        255, 255, 255, 255, 255, 255, 255, 0,
+    ]).unwrap();
+
+    let mut tiled = Box::new(Sprite::new(context, &checker, -0.45, 0.0, 0.35, 0.35));
+    tiled.set_region(Region { u_min: 0., v_min: 0., u_max: 4., v_max: 4. });
+
+    let mut tinted = Box::new(Sprite::new(context, &fade, 0.45, 0.0, 0.35, 0.35));
+    tinted.set_tint(Color::parse("tomato").unwrap());
+
+    SceneGraph::from_shapes(vec![tiled, tinted])
+}
+
+// Three solid images of different sizes packed onto one page, each
+// sprite must show only its own colour.
+// Pages small enough that the images spill onto a second one.
+fn atlas_scene(context: &mut dyn DrawingContext) -> SceneGraph {
+    let solid = |width: u32, height: u32, rgba: [u8; 4]| rgba.repeat((width * height) as usize);
+
+    let mut builder = AtlasBuilder::new();
+    builder.set_page_size(24, 24);
+    builder.add_rgba8("red", 8, 16, solid(8, 16, [255, 0, 0, 255])).unwrap();
+    builder.add_rgba8("green", 16, 8, solid(16, 8, [0, 255, 0, 255])).unwrap();
+    builder.add_rgba8("blue", 4, 4, solid(4, 4, [0, 0, 255, 128])).unwrap();
+    builder.add_bytes("strip", STRIP_PNG).unwrap();
+    let atlas = builder.build(context).unwrap();
+
+    SceneGraph::from_shapes(vec![
+        Box::new(Sprite::from_atlas(context, &atlas, "red", -0.5, 0.0, 0.1, 0.2).unwrap()),
+        Box::new(Sprite::from_atlas(context, &atlas, "green", 0.0, 0.0, 0.2, 0.1).unwrap()),
+        Box::new(Sprite::from_atlas(context, &atlas, "blue", 0.5, 0.0, 0.15, 0.15).unwrap()),
+        Box::new(Sprite::from_atlas(context, &atlas, "strip", 0.0, -0.5, 0.4, 0.1).unwrap()),
+    ])
+}
+
+// A four frame red, green, blue, white strip. The first sprite is
+// advanced 0.25s into a looping clip and must show blue. The
+// ping-pong one turns back at 0.4s without repeating the white end
+// frame, so 0.55s in it is on its way back and must show green. The
+// last plays the strip's sprite sheet tag, which runs backwards, at
+// double speed, so 0.175s in it is on its fourth frame and must
+// show red.
+fn animation_scene(context: &mut dyn DrawingContext) -> SceneGraph {
+    let strip = Texture::from_rgba8(context, 4, 1, vec![
+        255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255,
+    ]).unwrap();
+    strip.set_filter(context, FilterMode::Nearest);
+
+    let looping = AnimationClip::from_grid("loop", &strip, 4, 1, 4, 0.1, PlaybackMode::Loop);
+    let mut looping = Box::new(AnimatedSprite::new(context, looping, -0.5, 0.0, 0.2, 0.2));
+    looping.advance(0.25);
+
+    let ping_pong = AnimationClip::from_grid("ping_pong", &strip, 4, 1, 4, 0.1, PlaybackMode::PingPong);
+    let mut ping_pong = Box::new(AnimatedSprite::new(context, ping_pong, 0.0, 0.0, 0.2, 0.2));
+    ping_pong.advance(0.55);
+
+    let mut builder = AtlasBuilder::new();
+    let sheet = SpriteSheet::from_json(&mut builder, STRIP_JSON, STRIP_PNG).unwrap();
+    let atlas = builder.build(context).unwrap();
+    let backwards = AnimationClip::from_sprite_sheet(&atlas, &sheet, "backwards", 0.1).unwrap();
+    let mut backwards = Box::new(AnimatedSprite::new(context, backwards, 0.5, 0.0, 0.2, 0.2));
+    backwards.get_player_mut().set_speed(2.);
+    backwards.advance(0.175);
+
+    SceneGraph::from_shapes(vec![looping, ping_pong, backwards])
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(PathBuf, String),
+    MissingGolden(PathBuf),
+    SizeMismatch { expected: (u32, u32), actual: (u32, u32) },
+    PixelMismatch { mismatched_pixels: usize, max_difference: u8, diff_path: PathBuf },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(path, error) =>
+                write!(f, "{}: {}", path.display(), error),
+            SnapshotError::MissingGolden(path) =>
+                write!(f, "no golden image at {}, record it with --snapshot --update", path.display()),
+            SnapshotError::SizeMismatch { expected, actual } =>
+                write!(f, "expected {}x{} image, rendered {}x{}", expected.0, expected.1, actual.0, actual.1),
+            SnapshotError::PixelMismatch { mismatched_pixels, max_difference, diff_path } =>
+                write!(f, "{} pixels differ (max channel difference {}), see {}",
+                       mismatched_pixels, max_difference, diff_path.display()),
+        }
+    }
+}
+
+pub struct Comparison {
+    pub mismatched_pixels: usize,
+    pub max_difference: u8,
+    pub diff: Framebuffer
+}
+
+// Renders a single frame of the scene the way Stage::draw does.
+pub fn render(width: u32, height: u32, build: &dyn Fn(&mut dyn DrawingContext) -> SceneGraph) -> Framebuffer {
+    let mut context = SoftwareBackend::new(width, height);
+    let mut scene = build(&mut context);
+    let camera = Camera2D::new(width as f32, height as f32);
+
+    context.begin_default_pass(PassAction::default());
+    scene.draw(&mut context, &camera, 1.);
+    context.end_render_pass();
+    context.commit_frame();
+
+    context.get_framebuffer().clone()
+}
+
+// Matching pixels are dimmed copies of the expected image,
+// anything outside tolerance is painted solid red.
+pub fn compare(actual: &Framebuffer, expected: &Framebuffer, tolerance: u8) -> Comparison {
+    let mut diff = Framebuffer::new(expected.get_width(), expected.get_height());
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0u8;
+
+    for y in 0..expected.get_height() {
+        for x in 0..expected.get_width() {
+            let actual_pixel = actual.get_pixel(x, y);
+            let expected_pixel = expected.get_pixel(x, y);
+            let difference = actual_pixel.iter()
+                .zip(expected_pixel.iter())
+                .map(|(a, e)| a.abs_diff(*e))
+                .max()
+                .unwrap_or(0);
+
+            max_difference = max_difference.max(difference);
+            if difference > tolerance {
+                mismatched_pixels += 1;
+                diff.set_pixel(x, y, [0xFF, 0x00, 0x00, 0xFF]);
+            } else {
+                let [red, green, blue, _] = expected_pixel;
+                diff.set_pixel(x, y, [red / 3, green / 3, blue / 3, 0xFF]);
+            }
+        }
+    }
+
+    Comparison {
+        mismatched_pixels,
+        max_difference,
+        diff
+    }
+}
+
+// A missing golden image fails like a mismatch would, new scenes
+// are recorded with update set and the output checked in.
+pub fn check(directory: &Path, name: &str, actual: &Framebuffer, tolerance: u8, update: bool) -> Result<(), SnapshotError> {
+    let golden_path = directory.join(format!("{}.png", name));
+    let diff_path = directory.join(format!("{}.diff.png", name));
+
+    if update {
+        return save_png(&golden_path, actual);
+    }
+    if !golden_path.exists() {
+        return Err(SnapshotError::MissingGolden(golden_path));
+    }
+
+    let expected = load_png(&golden_path)?;
+    if (expected.get_width(), expected.get_height()) != (actual.get_width(), actual.get_height()) {
+        return Err(SnapshotError::SizeMismatch {
+            expected: (expected.get_width(), expected.get_height()),
+            actual: (actual.get_width(), actual.get_height()),
+        });
+    }
+
+    let comparison = compare(actual, &expected, tolerance);
+    if comparison.mismatched_pixels > 0 {
+        save_png(&diff_path, &comparison.diff)?;
+        return Err(SnapshotError::PixelMismatch {
+            mismatched_pixels: comparison.mismatched_pixels,
+            max_difference: comparison.max_difference,
+            diff_path,
+        });
+    }
+
+    if diff_path.exists() {
+        let _ = std::fs::remove_file(&diff_path);
+    }
+    Ok(())
+}
+
+// Golden images are read from and written to directory.
+pub fn run_all(directory: &Path, stage_file: &Path, update: bool) -> bool {
+    let mut passed = true;
+
+    for scene in scenes(stage_file) {
+        let framebuffer = render(WIDTH, HEIGHT, &scene.build);
+        match check(directory, scene.name, &framebuffer, scene.tolerance, update) {
+            Ok(()) => println!("snapshot {} ... ok", scene.name),
+            Err(error) => {
+                println!("snapshot {} ... FAILED: {}", scene.name, error);
+                passed = false;
+            }
+        }
+    }
+
+    passed
+}
+
+pub fn load_png(path: &Path) -> Result<Framebuffer, SnapshotError> {
+    let image = image::open(path)
+        .map_err(|error| SnapshotError::Io(path.to_path_buf(), error.to_string()))?
+        .to_rgba8();
+    Ok(Framebuffer::from_rgba8(image.width(), image.height(), image.into_raw()))
+}
+
+pub fn save_png(path: &Path, framebuffer: &Framebuffer) -> Result<(), SnapshotError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|error| SnapshotError::Io(parent.to_path_buf(), error.to_string()))?;
+    }
+    image::RgbaImage::from_raw(framebuffer.get_width(), framebuffer.get_height(), framebuffer.get_pixels().to_vec())
+        .expect("Framebuffer is always width * height * 4 bytes.")
+        .save(path)
+        .map_err(|error| SnapshotError::Io(path.to_path_buf(), error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From the crate, whichever directory cargo test runs in.
+    const DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots");
+    const STAGE_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/stage.json");
+
+    #[test]
+    fn scenes_match_golden_images() {
+        assert!(run_all(Path::new(DIRECTORY), Path::new(STAGE_FILE), false),
+                "snapshot scenes differ from their golden images");
+    }
+
+    #[test]
+    fn missing_golden_fails_without_update() {
+        let framebuffer = Framebuffer::new(4, 4);
+        match check(Path::new(DIRECTORY), "does_not_exist", &framebuffer, DEFAULT_TOLERANCE, false) {
+            Err(SnapshotError::MissingGolden(_)) => {}
+            other => panic!("expected MissingGolden, got {:?}", other)
+        }
+        assert!(!Path::new(DIRECTORY).join("does_not_exist.png").exists());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod frame_pacer;
+pub mod frame_stats;
+pub mod simulation_clock;
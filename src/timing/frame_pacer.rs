@@ -0,0 +1,271 @@
+// Sleeping is only trusted to within this many seconds, the rest of
+// the wait is spun out so frames land on time.
+pub const DEFAULT_SPIN_THRESHOLD: f64 = 0.002;
+// Adaptive mode never drops below rate / MAX_DIVISOR.
+const MAX_DIVISOR: u32 = 4;
+// Weight of the newest frame in the smoothed work time.
+const SMOOTHING: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PacingMode {
+    // The driver blocks on present, nothing is waited for here.
+    VSync,
+    // At most fps frames a second.
+    Capped { fps: f64 },
+    // As fast as the frames can be made.
+    Uncapped,
+    // Aims for fps, dropping to fps / 2, fps / 3... while frames
+    // take longer than that, so pacing stays even instead of
+    // alternating between fast and slow frames.
+    Adaptive { fps: f64 }
+}
+
+impl PacingMode {
+
+    // "vsync", "uncapped", "capped:<fps>" or "adaptive:<fps>".
+    pub fn parse(text: &str) -> Option<PacingMode> {
+        let (name, fps) = match text.split_once(':') {
+            Some((name, fps)) => (name, Some(fps.parse::<f64>().ok().filter(|fps| *fps > 0.)?)),
+            None => (text, None)
+        };
+        match (name, fps) {
+            ("vsync", None) => Some(PacingMode::VSync),
+            ("uncapped", None) => Some(PacingMode::Uncapped),
+            ("capped", Some(fps)) => Some(PacingMode::Capped { fps }),
+            ("adaptive", Some(fps)) => Some(PacingMode::Adaptive { fps }),
+            _ => None
+        }
+    }
+
+    // For conf::Platform::swap_interval, which is only read when the
+    // window opens, so VSync can only be the mode a FramePacer starts
+    // in, see set_mode.
+    pub fn swap_interval(&self) -> i32 {
+        match self {
+            PacingMode::VSync => 1,
+            _ => 0
+        }
+    }
+}
+
+// Call begin_frame before drawing and end_frame after. On native
+// targets end_frame blocks until the next frame is due. WebAssembly
+// cannot block the browser's thread, so there begin_frame returns
+// false for frames that come too early and should not be drawn.
+pub struct FramePacer {
+    mode: PacingMode,
+    next_deadline: Option<f64>,
+    frame_start: f64,
+    // Seconds spent between begin_frame and end_frame, smoothed.
+    work_time: f64,
+    // Adaptive mode runs at fps / divisor.
+    divisor: u32,
+    spin_threshold: f64
+}
+
+impl FramePacer {
+
+    pub fn new(mode: PacingMode) -> FramePacer {
+        FramePacer {
+            mode,
+            next_deadline: None,
+            frame_start: 0.,
+            work_time: 0.,
+            divisor: 1,
+            spin_threshold: DEFAULT_SPIN_THRESHOLD
+        }
+    }
+
+    pub fn get_mode(&self) -> PacingMode {
+        self.mode
+    }
+
+    // Switches between the modes the pacer waits for itself. Nothing
+    // can change the swap interval once the window is open, so moving
+    // to or from VSync is refused and false returned: the driver would
+    // keep blocking for the display, or never start to.
+    pub fn set_mode(&mut self, mode: PacingMode) -> bool {
+        if (mode == PacingMode::VSync) != (self.mode == PacingMode::VSync) {
+            return false;
+        }
+        self.mode = mode;
+        self.next_deadline = None;
+        self.divisor = 1;
+        true
+    }
+
+    // Seconds between frames the pacer is currently aiming for,
+    // None when it leaves that to the driver or does not wait.
+    pub fn get_target_frame_time(&self) -> Option<f64> {
+        match self.mode {
+            PacingMode::VSync | PacingMode::Uncapped => None,
+            PacingMode::Capped { fps } => Some(1. / fps.max(1.)),
+            PacingMode::Adaptive { fps } => Some(self.divisor as f64 / fps.max(1.))
+        }
+    }
+
+    pub fn get_spin_threshold(&self) -> f64 {
+        self.spin_threshold
+    }
+
+    // 0 sleeps the whole wait, cheaper on the CPU but late by
+    // however coarse the OS timer is.
+    pub fn set_spin_threshold(&mut self, spin_threshold: f64) {
+        self.spin_threshold = spin_threshold.max(0.);
+    }
+
+    // time in seconds, e.g. miniquad::date::now. False means skip
+    // this frame, which only happens on WebAssembly.
+    pub fn begin_frame(&mut self, time: f64) -> bool {
+        if cfg!(target_arch = "wasm32") {
+            if let (Some(deadline), Some(_)) = (self.next_deadline, self.get_target_frame_time()) {
+                if time < deadline {
+                    return false;
+                }
+            }
+        }
+        self.frame_start = time;
+        true
+    }
+
+    pub fn end_frame(&mut self, time: f64) {
+        let work_time = (time - self.frame_start).max(0.);
+        self.work_time += (work_time - self.work_time) * SMOOTHING;
+        if let PacingMode::Adaptive { fps } = self.mode {
+            self.adapt(fps.max(1.));
+        }
+
+        let Some(frame_time) = self.get_target_frame_time() else {
+            self.next_deadline = None;
+            return;
+        };
+        let deadline = self.next_deadline.unwrap_or(self.frame_start + frame_time);
+
+        let time = if cfg!(target_arch = "wasm32") {
+            time
+        } else {
+            wait_until(deadline, self.spin_threshold)
+        };
+        self.next_deadline = Some(next_deadline(deadline, time, frame_time));
+    }
+
+    fn adapt(&mut self, fps: f64) {
+        let budget = self.divisor as f64 / fps;
+        if self.work_time > budget * 0.95 && self.divisor < MAX_DIVISOR {
+            self.divisor += 1;
+        } else if self.divisor > 1 && self.work_time < (self.divisor - 1) as f64 / fps * 0.8 {
+            self.divisor -= 1;
+        }
+    }
+}
+
+// A frame that ran over a whole period starts a new schedule
+// instead of rushing the next few to catch up.
+fn next_deadline(deadline: f64, time: f64, frame_time: f64) -> f64 {
+    if time > deadline + frame_time {
+        time + frame_time
+    } else {
+        deadline + frame_time
+    }
+}
+
+// How long to sleep at time for, leaving spin_threshold of the wait
+// to spin. 0 when the deadline is that close or gone.
+fn sleep_time(deadline: f64, time: f64, spin_threshold: f64) -> f64 {
+    (deadline - time - spin_threshold).max(0.)
+}
+
+// Sleeps most of the way then spins, returning the time it woke at.
+fn wait_until(deadline: f64, spin_threshold: f64) -> f64 {
+    let mut time = miniquad::date::now();
+    let sleep_time = sleep_time(deadline, time, spin_threshold);
+    if sleep_time > 0. {
+        std::thread::sleep(std::time::Duration::from_secs_f64(sleep_time));
+        time = miniquad::date::now();
+    }
+    while time < deadline {
+        std::hint::spin_loop();
+        time = miniquad::date::now();
+    }
+    time
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pacing_modes() {
+        assert_eq!(PacingMode::parse("vsync"), Some(PacingMode::VSync));
+        assert_eq!(PacingMode::parse("uncapped"), Some(PacingMode::Uncapped));
+        assert_eq!(PacingMode::parse("capped:60"), Some(PacingMode::Capped { fps: 60. }));
+        assert_eq!(PacingMode::parse("adaptive:144"), Some(PacingMode::Adaptive { fps: 144. }));
+        for bad in ["capped", "capped:0", "adaptive:fast", "vsync:60", "turbo"] {
+            assert_eq!(PacingMode::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn switches_modes_except_to_and_from_vsync() {
+        let mut pacer = FramePacer::new(PacingMode::Capped { fps: 30. });
+        assert!(pacer.set_mode(PacingMode::Uncapped));
+        assert_eq!(pacer.get_target_frame_time(), None);
+        assert!(pacer.set_mode(PacingMode::Adaptive { fps: 50. }));
+        assert_eq!(pacer.get_target_frame_time(), Some(0.02));
+        assert!(!pacer.set_mode(PacingMode::VSync));
+        assert_eq!(pacer.get_mode(), PacingMode::Adaptive { fps: 50. });
+
+        let mut pacer = FramePacer::new(PacingMode::VSync);
+        assert!(!pacer.set_mode(PacingMode::Uncapped));
+        assert_eq!(pacer.get_mode(), PacingMode::VSync);
+    }
+
+    #[test]
+    fn adaptive_drops_and_recovers_the_rate() {
+        let fps = 60.;
+        let mut pacer = FramePacer::new(PacingMode::Adaptive { fps });
+
+        // Over 95% of the 60 fps budget steps down to 30.
+        pacer.work_time = 0.96 / fps;
+        pacer.adapt(fps);
+        assert_eq!(pacer.get_target_frame_time(), Some(2. / fps));
+
+        // Still fits 30, stays there.
+        pacer.work_time = 1.5 / fps;
+        pacer.adapt(fps);
+        assert_eq!(pacer.get_target_frame_time(), Some(2. / fps));
+
+        // Never below fps / MAX_DIVISOR however slow frames get.
+        pacer.work_time = 1.;
+        for _ in 0..10 {
+            pacer.adapt(fps);
+        }
+        assert_eq!(pacer.get_target_frame_time(), Some(MAX_DIVISOR as f64 / fps));
+
+        // Back up one divisor at a time once under 80% of the
+        // faster budget.
+        pacer.work_time = 0.5 / fps;
+        pacer.adapt(fps);
+        assert_eq!(pacer.get_target_frame_time(), Some(3. / fps));
+        pacer.adapt(fps);
+        pacer.adapt(fps);
+        assert_eq!(pacer.get_target_frame_time(), Some(1. / fps));
+    }
+
+    #[test]
+    fn sleeps_until_the_spin_threshold() {
+        assert_eq!(sleep_time(1., 0.5, 0.125), 0.375);
+        assert_eq!(sleep_time(1., 0.9375, 0.125), 0.);
+        assert_eq!(sleep_time(1., 2., 0.125), 0.);
+        assert_eq!(sleep_time(1., 0.5, 0.), 0.5);
+    }
+
+    #[test]
+    fn schedules_from_the_deadline_unless_a_period_was_missed() {
+        // On time or a little late keeps the schedule.
+        assert_eq!(next_deadline(1., 1., 0.25), 1.25);
+        assert_eq!(next_deadline(1., 1.125, 0.25), 1.25);
+        // Over a whole period late starts again from now.
+        assert_eq!(next_deadline(1., 1.5, 0.25), 1.75);
+    }
+}
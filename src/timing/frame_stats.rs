@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use std::fmt;
+use crate::backend::profiling_context::DrawCounters;
+
+// Frames the rolling figures cover, four seconds at 60 fps.
+pub const DEFAULT_WINDOW: usize = 240;
+
+// The rolling figures at one moment. Times are in seconds, counters
+// are those of the latest frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameSummary {
+    pub frames: usize,
+    pub fps: f64,
+    pub min_frame_time: f64,
+    pub average_frame_time: f64,
+    pub max_frame_time: f64,
+    pub p99_frame_time: f64,
+    pub counters: DrawCounters
+}
+
+impl fmt::Display for FrameSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f,
+               "{:.1} fps, frame ms min {:.2} avg {:.2} max {:.2} p99 {:.2}, {} draws, {} vertices, {} pipeline / {} binding switches",
+               self.fps,
+               self.min_frame_time * 1000.,
+               self.average_frame_time * 1000.,
+               self.max_frame_time * 1000.,
+               self.p99_frame_time * 1000.,
+               self.counters.draw_calls,
+               self.counters.vertices,
+               self.counters.pipeline_switches,
+               self.counters.binding_switches)
+    }
+}
+
+// Frame times and draw counters for the last window frames.
+pub struct FrameStats {
+    frame_times: VecDeque<f64>,
+    counters: VecDeque<DrawCounters>,
+    window: usize
+}
+
+impl FrameStats {
+
+    pub fn new(window: usize) -> FrameStats {
+        let window = window.max(1);
+        FrameStats {
+            frame_times: VecDeque::with_capacity(window),
+            counters: VecDeque::with_capacity(window),
+            window
+        }
+    }
+
+    pub fn record_frame(&mut self, frame_time: f64, counters: DrawCounters) {
+        if self.frame_times.len() == self.window {
+            self.frame_times.pop_front();
+            self.counters.pop_front();
+        }
+        self.frame_times.push_back(frame_time.max(0.));
+        self.counters.push_back(counters);
+    }
+
+    pub fn clear(&mut self) {
+        self.frame_times.clear();
+        self.counters.clear();
+    }
+
+    pub fn get_window(&self) -> usize {
+        self.window
+    }
+
+    pub fn get_frame_count(&self) -> usize {
+        self.frame_times.len()
+    }
+
+    // Oldest first.
+    pub fn get_frame_times(&self) -> impl Iterator<Item = f64> + '_ {
+        self.frame_times.iter().copied()
+    }
+
+    // Oldest first.
+    pub fn get_counters(&self) -> impl Iterator<Item = DrawCounters> + '_ {
+        self.counters.iter().copied()
+    }
+
+    pub fn get_last_counters(&self) -> DrawCounters {
+        self.counters.back().copied().unwrap_or_default()
+    }
+
+    // Frames over the time they took, 0 before any were recorded.
+    pub fn get_fps(&self) -> f64 {
+        let total: f64 = self.frame_times.iter().sum();
+        if total > 0. { self.frame_times.len() as f64 / total } else { 0. }
+    }
+
+    pub fn get_min_frame_time(&self) -> f64 {
+        self.frame_times.iter().copied().reduce(f64::min).unwrap_or(0.)
+    }
+
+    pub fn get_average_frame_time(&self) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.;
+        }
+        self.frame_times.iter().sum::<f64>() / self.frame_times.len() as f64
+    }
+
+    pub fn get_max_frame_time(&self) -> f64 {
+        self.frame_times.iter().copied().reduce(f64::max).unwrap_or(0.)
+    }
+
+    // Nearest rank, percentile from 0 to 100.
+    pub fn get_percentile_frame_time(&self, percentile: f64) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.;
+        }
+        let mut sorted: Vec<f64> = self.frame_times.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (percentile.clamp(0., 100.) / 100. * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+
+    pub fn get_summary(&self) -> FrameSummary {
+        FrameSummary {
+            frames: self.get_frame_count(),
+            fps: self.get_fps(),
+            min_frame_time: self.get_min_frame_time(),
+            average_frame_time: self.get_average_frame_time(),
+            max_frame_time: self.get_max_frame_time(),
+            p99_frame_time: self.get_percentile_frame_time(99.),
+            counters: self.get_last_counters()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draws(draw_calls: u32) -> DrawCounters {
+        DrawCounters {
+            draw_calls,
+            ..DrawCounters::default()
+        }
+    }
+
+    fn stats_of(frame_times: &[f64]) -> FrameStats {
+        let mut stats = FrameStats::new(DEFAULT_WINDOW);
+        for frame_time in frame_times {
+            stats.record_frame(*frame_time, DrawCounters::default());
+        }
+        stats
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        // Recorded out of order, ranked sorted.
+        let stats = stats_of(&[7., 3., 10., 1., 5., 2., 9., 4., 8., 6.]);
+        assert_eq!(stats.get_percentile_frame_time(0.), 1.);
+        assert_eq!(stats.get_percentile_frame_time(10.), 1.);
+        assert_eq!(stats.get_percentile_frame_time(11.), 2.);
+        assert_eq!(stats.get_percentile_frame_time(50.), 5.);
+        assert_eq!(stats.get_percentile_frame_time(99.), 10.);
+        assert_eq!(stats.get_percentile_frame_time(100.), 10.);
+        assert_eq!(stats.get_percentile_frame_time(250.), 10.);
+    }
+
+    #[test]
+    fn percentiles_of_empty_and_single_frame_windows() {
+        let empty = stats_of(&[]);
+        assert_eq!(empty.get_percentile_frame_time(99.), 0.);
+        assert_eq!(empty.get_summary(), FrameSummary::default());
+
+        let single = stats_of(&[0.016]);
+        for percentile in [0., 50., 99., 100.] {
+            assert_eq!(single.get_percentile_frame_time(percentile), 0.016);
+        }
+    }
+
+    #[test]
+    fn record_frame_evicts_the_oldest() {
+        let mut stats = FrameStats::new(3);
+        for frame in 1..=5 {
+            stats.record_frame(frame as f64, draws(frame));
+        }
+        assert_eq!(stats.get_window(), 3);
+        assert_eq!(stats.get_frame_count(), 3);
+        assert_eq!(stats.get_frame_times().collect::<Vec<_>>(), vec![3., 4., 5.]);
+        assert_eq!(stats.get_counters().collect::<Vec<_>>(), vec![draws(3), draws(4), draws(5)]);
+        assert_eq!(stats.get_last_counters(), draws(5));
+        assert_eq!(stats.get_max_frame_time(), 5.);
+
+        stats.clear();
+        assert_eq!(stats.get_frame_count(), 0);
+        assert_eq!(stats.get_fps(), 0.);
+    }
+}
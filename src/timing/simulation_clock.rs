@@ -0,0 +1,217 @@
+// Longest frame the clock will account for, so a stall (window
+// drag, debugger) does not launch everything across the screen.
+pub const MAX_FRAME_TIME: f64 = 0.25;
+pub const DEFAULT_MAX_STEPS_PER_FRAME: u32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimestepMode {
+    // One update per frame with however long the frame took.
+    Variable,
+    // Updates of exactly 1 / rate seconds, as many as the elapsed
+    // time pays for. Drawing interpolates between the last two.
+    Fixed { rate: f64 }
+}
+
+impl TimestepMode {
+
+    // "variable" or "fixed:<rate>".
+    pub fn parse(text: &str) -> Option<TimestepMode> {
+        match text.split_once(':') {
+            Some(("fixed", rate)) => rate
+                .parse::<f64>()
+                .ok()
+                .filter(|rate| *rate > 0.)
+                .map(|rate| TimestepMode::Fixed { rate }),
+            None if text == "variable" => Some(TimestepMode::Variable),
+            _ => None
+        }
+    }
+}
+
+// How many updates to run this frame and the dt for each.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Steps {
+    pub count: u32,
+    pub dt: f32
+}
+
+pub struct SimulationClock {
+    mode: TimestepMode,
+    // Fixed mode time not yet simulated, always under one step.
+    accumulator: f64,
+    // Spiral of death guard: when updates cannot keep up, the time
+    // left over after this many steps is dropped.
+    max_steps_per_frame: u32,
+    last_time: Option<f64>,
+    alpha: f32
+}
+
+impl SimulationClock {
+
+    pub fn new(mode: TimestepMode) -> SimulationClock {
+        SimulationClock {
+            mode,
+            accumulator: 0.,
+            max_steps_per_frame: DEFAULT_MAX_STEPS_PER_FRAME,
+            last_time: None,
+            alpha: 1.
+        }
+    }
+
+    pub fn get_mode(&self) -> TimestepMode {
+        self.mode
+    }
+
+    pub fn get_max_steps_per_frame(&self) -> u32 {
+        self.max_steps_per_frame
+    }
+
+    // How far between the previous and the latest update the frame
+    // being drawn is, 0..1. Always 1 in Variable mode.
+    pub fn get_alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    pub fn set_mode(&mut self, mode: TimestepMode) {
+        self.mode = mode;
+        self.accumulator = 0.;
+        self.alpha = 1.;
+    }
+
+    pub fn set_max_steps_per_frame(&mut self, max_steps_per_frame: u32) {
+        self.max_steps_per_frame = max_steps_per_frame.max(1);
+    }
+
+    // time in seconds, e.g. miniquad::date::now. The first tick only
+    // starts the clock, no update has run yet so there is nothing
+    // to interpolate from.
+    pub fn tick(&mut self, time: f64) -> Steps {
+        if self.last_time.is_none() {
+            self.last_time = Some(time);
+            self.alpha = 1.;
+            return Steps {
+                count: 0,
+                dt: 0.
+            };
+        }
+        let frame_time = (time - self.last_time.unwrap_or(time)).clamp(0., MAX_FRAME_TIME);
+        self.last_time = Some(time);
+
+        match self.mode {
+            TimestepMode::Variable => {
+                self.alpha = 1.;
+                Steps {
+                    count: 1,
+                    dt: frame_time as f32
+                }
+            }
+            TimestepMode::Fixed { rate } => {
+                let step = 1. / rate.max(1.);
+                self.accumulator += frame_time;
+
+                let mut count = (self.accumulator / step).floor() as u32;
+                if count > self.max_steps_per_frame {
+                    count = self.max_steps_per_frame;
+                    self.accumulator = self.accumulator % step + count as f64 * step;
+                }
+                self.accumulator = (self.accumulator - count as f64 * step).max(0.);
+                self.alpha = (self.accumulator / step) as f32;
+
+                Steps {
+                    count,
+                    dt: step as f32
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Eight steps a second keeps the times below exact in binary.
+    const RATE: f64 = 8.;
+    const STEP: f64 = 1. / RATE;
+
+    fn started(mode: TimestepMode) -> SimulationClock {
+        let mut clock = SimulationClock::new(mode);
+        assert_eq!(clock.tick(0.).count, 0);
+        clock
+    }
+
+    #[test]
+    fn parses_timestep_modes() {
+        assert_eq!(TimestepMode::parse("variable"), Some(TimestepMode::Variable));
+        assert_eq!(TimestepMode::parse("fixed:60"), Some(TimestepMode::Fixed { rate: 60. }));
+        for bad in ["fixed", "fixed:0", "fixed:fast", "variable:60", "turbo"] {
+            assert_eq!(TimestepMode::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn carries_the_remainder_into_the_next_frame() {
+        let mut clock = started(TimestepMode::Fixed { rate: RATE });
+
+        let steps = clock.tick(STEP * 1.5);
+        assert_eq!(steps, Steps { count: 1, dt: STEP as f32 });
+        assert_eq!(clock.get_alpha(), 0.5);
+
+        // Half a step left over plus half a step more makes one.
+        assert_eq!(clock.tick(STEP * 2.).count, 1);
+        assert_eq!(clock.get_alpha(), 0.);
+
+        assert_eq!(clock.tick(STEP * 2.25).count, 0);
+        assert_eq!(clock.get_alpha(), 0.25);
+    }
+
+    #[test]
+    fn drops_time_past_the_step_limit() {
+        let rate = 64.;
+        let mut clock = started(TimestepMode::Fixed { rate });
+
+        // 13.5 steps due, only the limit runs and the half step
+        // left over is all that carries.
+        let steps = clock.tick(13.5 / rate);
+        assert_eq!(steps.count, DEFAULT_MAX_STEPS_PER_FRAME);
+        assert_eq!(clock.get_alpha(), 0.5);
+        assert_eq!(clock.tick(14. / rate).count, 1);
+
+        clock.set_max_steps_per_frame(2);
+        assert_eq!(clock.tick(24. / rate).count, 2);
+        assert_eq!(clock.get_alpha(), 0.);
+    }
+
+    #[test]
+    fn clamps_long_frames() {
+        let mut clock = started(TimestepMode::Fixed { rate: RATE });
+        assert_eq!(clock.tick(10.).count, (MAX_FRAME_TIME / STEP) as u32);
+        assert_eq!(clock.get_alpha(), 0.);
+
+        let mut clock = started(TimestepMode::Variable);
+        assert_eq!(clock.tick(10.), Steps { count: 1, dt: MAX_FRAME_TIME as f32 });
+        // Time going backwards is no time at all.
+        assert_eq!(clock.tick(9.), Steps { count: 1, dt: 0. });
+    }
+
+    #[test]
+    fn variable_mode_passes_the_frame_time() {
+        let mut clock = started(TimestepMode::Variable);
+        assert_eq!(clock.tick(0.1), Steps { count: 1, dt: 0.1 });
+        assert_eq!(clock.tick(0.15), Steps { count: 1, dt: 0.05f64 as f32 });
+        assert_eq!(clock.get_alpha(), 1.);
+    }
+
+    #[test]
+    fn alpha_stays_within_a_step() {
+        let mut clock = started(TimestepMode::Fixed { rate: 60. });
+        let mut time = 0.;
+        for frame in 0..500 {
+            // Uneven frames, some shorter and some longer than a step.
+            time += 0.002 + (frame % 7) as f64 * 0.0041;
+            clock.tick(time);
+            let alpha = clock.get_alpha();
+            assert!((0. ..1.).contains(&alpha), "frame {} alpha {}", frame, alpha);
+        }
+    }
+}